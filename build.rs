@@ -0,0 +1,33 @@
+fn main() {
+    // the gRPC service is generated from its protobuf definition,
+    // protox compiles it without needing protoc installed
+    #[cfg(feature = "grpc")]
+    {
+        println!("cargo:rerun-if-changed=proto/hulth.proto");
+        let descriptors = protox::compile(["proto/hulth.proto"], ["proto"])
+            .expect("failed to parse proto/hulth.proto");
+        tonic_prost_build::configure()
+            .build_client(false)
+            .compile_fds(descriptors)
+            .expect("failed to generate the gRPC service");
+    }
+
+    // the C header of the `ffi` feature, kept in the repository for C users
+    #[cfg(feature = "ffi")]
+    {
+        println!("cargo:rerun-if-changed=src/ffi.rs");
+        let config = cbindgen::Config {
+            usize_is_size_t: true,
+            ..cbindgen::Config::default()
+        };
+        cbindgen::Builder::new()
+            .with_config(config)
+            .with_src("src/ffi.rs")
+            .with_language(cbindgen::Language::C)
+            .with_include_guard("HULTH_EVAL_H")
+            .with_header("/* generated by cbindgen from src/ffi.rs, do not edit */")
+            .generate()
+            .expect("failed to generate the C header")
+            .write_to_file("include/hulth_eval.h");
+    }
+}
@@ -0,0 +1,9 @@
+//! arbitrary bytes as the JSON lines of `rank-batch` and `convert`,
+//! lines given as text are tokenized as well.
+//! `cargo +nightly fuzz run batch`
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|jsonl: &[u8]| {
+    let _ = hulth_eval::parse_batch(jsonl);
+});
@@ -0,0 +1,8 @@
+//! arbitrary bytes as a dataset document, malformed ones must be errors.
+//! `cargo +nightly fuzz run document`, seeded with `fuzz/corpus/document`
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|json: &[u8]| {
+    let _ = hulth_eval::parse_document(json);
+});
@@ -0,0 +1,72 @@
+/* generated by cbindgen from src/ffi.rs, do not edit */
+
+#ifndef HULTH_EVAL_H
+#define HULTH_EVAL_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * a saved model fitted for ranking, opaque to C
+ */
+typedef struct HulthExtractor HulthExtractor;
+
+typedef struct HulthKeyword {
+  /**
+   * NUL-terminated UTF-8
+   */
+  char *term;
+  double score;
+} HulthKeyword;
+
+/**
+ * ranked keywords, best first
+ */
+typedef struct HulthKeywords {
+  struct HulthKeyword *keywords;
+  size_t len;
+} HulthKeywords;
+
+/**
+ * loads and fits the model written by `hulth-eval eval --save-model` to path,
+ * NULL on failure. free it with `hulth_extractor_free`
+ *
+ * # Safety
+ * path must be a NUL-terminated string
+ */
+struct HulthExtractor *hulth_extractor_load(const char *path);
+
+/**
+ * # Safety
+ * extractor must come from `hulth_extractor_load` and not be used afterwards, NULL is ignored
+ */
+void hulth_extractor_free(struct HulthExtractor *extractor);
+
+/**
+ * ranks the UTF-8 document of len bytes at text, raw or Hulth JSON, and returns
+ * its best top_k keywords, NULL on failure. free them with `hulth_keywords_free`
+ *
+ * # Safety
+ * extractor must come from `hulth_extractor_load` and text point to len readable bytes
+ */
+struct HulthKeywords *hulth_rank(const struct HulthExtractor *extractor,
+                                 const uint8_t *text,
+                                 size_t len,
+                                 size_t top_k);
+
+/**
+ * # Safety
+ * keywords must come from `hulth_rank` and not be used afterwards, NULL is ignored
+ */
+void hulth_keywords_free(struct HulthKeywords *keywords);
+
+/**
+ * the message of the last failed call on this thread, NULL if none.
+ * valid until the next failing call on the thread
+ */
+const char *hulth_last_error(void);
+
+#endif  /* HULTH_EVAL_H */
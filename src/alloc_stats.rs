@@ -0,0 +1,124 @@
+//! heap statistics of the `alloc-stats` feature, a global allocator around the
+//! system one counting allocations and the peak of live bytes. phases of a run
+//! are measured between `start` and `finish`, without the feature they measure nothing
+#[cfg(feature = "alloc-stats")]
+use std::alloc::{GlobalAlloc, Layout, System};
+#[cfg(feature = "alloc-stats")]
+use std::fs::File;
+#[cfg(feature = "alloc-stats")]
+use std::io::{self, BufWriter, Write};
+#[cfg(feature = "alloc-stats")]
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+static LIVE: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+/// the phases finished so far
+static PHASES: Mutex<Vec<Phase>> = Mutex::new(vec![]);
+
+#[cfg(feature = "alloc-stats")]
+#[global_allocator]
+static COUNTING: Counting = Counting;
+
+#[cfg(feature = "alloc-stats")]
+pub struct Counting;
+
+#[cfg(feature = "alloc-stats")]
+impl Counting {
+    fn grow(size: usize) {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        let live = LIVE.fetch_add(size, Ordering::Relaxed) + size;
+        PEAK.fetch_max(live, Ordering::Relaxed);
+    }
+}
+
+#[cfg(feature = "alloc-stats")]
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            Counting::grow(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            Counting::grow(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        LIVE.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new = System.realloc(ptr, layout, new_size);
+        if !new.is_null() {
+            LIVE.fetch_sub(layout.size(), Ordering::Relaxed);
+            Counting::grow(new_size);
+        }
+        new
+    }
+}
+
+/// the heap use of one phase of a run
+#[derive(Debug, Clone, Serialize)]
+pub struct Phase {
+    pub name: String,
+    /// the most bytes allocated at once during the phase, including
+    /// those still allocated from before it
+    pub peak_bytes: usize,
+    /// allocations and reallocations during the phase
+    pub allocations: u64,
+    /// bytes still allocated at its end
+    pub live_bytes: usize,
+}
+
+/// the allocations when a phase started
+pub struct Mark {
+    allocations: u64,
+}
+
+/// starts a phase, the peak restarts from the bytes allocated now
+pub fn start() -> Mark {
+    PEAK.store(LIVE.load(Ordering::Relaxed), Ordering::Relaxed);
+    Mark {
+        allocations: ALLOCATIONS.load(Ordering::Relaxed),
+    }
+}
+
+/// ends the phase started at mark, logging and keeping its heap use
+pub fn finish(mark: Mark, name: &str) {
+    if !cfg!(feature = "alloc-stats") {
+        return;
+    }
+    let phase = Phase {
+        name: name.into(),
+        peak_bytes: PEAK.load(Ordering::Relaxed),
+        allocations: ALLOCATIONS.load(Ordering::Relaxed) - mark.allocations,
+        live_bytes: LIVE.load(Ordering::Relaxed),
+    };
+    info!(
+        phase = name,
+        peak_bytes = phase.peak_bytes,
+        allocations = phase.allocations,
+        live_bytes = phase.live_bytes,
+        "heap use"
+    );
+    PHASES.lock().unwrap_or_else(|e| e.into_inner()).push(phase);
+}
+
+/// writes the phases finished so far as a JSON array
+#[cfg(feature = "alloc-stats")]
+pub fn save<P: AsRef<Path>>(path: P) -> io::Result<()> {
+    let phases = PHASES.lock().unwrap_or_else(|e| e.into_inner());
+    let mut writer = BufWriter::new(File::create(path)?);
+    serde_json::to_writer_pretty(&mut writer, &*phases)?;
+    writeln!(writer)
+}
@@ -0,0 +1,101 @@
+use crate::corpus::Reading;
+use crate::spans::Span;
+use crate::{text, HulthDocument};
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+
+/// one line of a rank-batch input file.
+/// either `{"id": .., "text": ..}` or a Hulth JSON document with an optional id
+#[derive(Debug, Deserialize)]
+pub struct BatchDocument {
+    #[serde(default)]
+    pub(crate) id: Option<String>,
+    #[serde(flatten)]
+    content: BatchContent,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum BatchContent {
+    Text { text: String },
+    Hulth(HulthDocument),
+}
+
+impl BatchDocument {
+    /// the document and its text if it was given as text
+    pub fn into_document(self) -> (HulthDocument, Option<String>) {
+        match self.content {
+            BatchContent::Text { text } => (text::document_from_text(&text), Some(text)),
+            BatchContent::Hulth(doc) => (doc, None),
+        }
+    }
+}
+
+/// one line of a rank-batch output file
+#[derive(Debug, Serialize)]
+pub struct Extraction {
+    pub(crate) id: String,
+    pub(crate) keywords: Vec<Keyword>,
+    /// the occurrences of the keywords, with --spans
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) spans: Option<Vec<Span>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Keyword {
+    pub(crate) term: String,
+    pub(crate) score: f64,
+    /// the most frequent form of term in the document if it differs,
+    /// e.g. `Support Vector Machines` of `support vector machin`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) surface: Option<String>,
+}
+
+impl Keyword {
+    /// term with its form in surfaces, see `spans::surface_forms`
+    pub fn new(term: String, score: f64, surfaces: &HashMap<String, String>) -> Self {
+        let surface = surfaces.get(&term).filter(|s| **s != term).cloned();
+        Keyword {
+            term,
+            score,
+            surface,
+        }
+    }
+}
+
+/// reads reader line by line and applies f to every non-empty line,
+/// ids default to the 1-based line number or the hash of `--ids hash`.
+/// Hulth JSON lines are checked for missing token fields like dataset files
+pub fn for_each_document<R, F>(reader: R, reading: &Reading, mut f: F) -> io::Result<()>
+where
+    R: BufRead,
+    F: FnMut(String, HulthDocument, Option<String>) -> io::Result<()>,
+{
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let origin = format!("line {}", i + 1);
+        let doc: BatchDocument = reading.check(Path::new(&origin), || {
+            serde_json::from_str(&line).map_err(|e| {
+                io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", origin, e))
+            })
+        })?;
+        let given = doc.id.clone();
+        let (doc, text) = doc.into_document();
+        let id = match given {
+            Some(id) => id,
+            None => reading.ids.id((i + 1).to_string(), &origin, &doc),
+        };
+        f(id, doc, text)?;
+    }
+    Ok(())
+}
+
+pub fn write_extraction<W: Write>(mut writer: W, extraction: &Extraction) -> io::Result<()> {
+    serde_json::to_writer(&mut writer, extraction)?;
+    writeln!(writer)
+}
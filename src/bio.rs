@@ -0,0 +1,259 @@
+//! span-aware evaluation: keyphrases and predictions become BIO tags over the
+//! words of each sentence and are compared chunk by chunk as in seqeval.
+//! words are the unigram tokens of the preprocessed document and the reference
+//! keyphrases are normalized the same way, dropped words are skipped
+use crate::phrases::is_phrase;
+use crate::preprocess::Normalizer;
+use crate::{HulthDocument, HulthToken};
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Tag {
+    Begin,
+    Inside,
+    Outside,
+}
+
+/// chunk counts of one or more documents
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct BioCounts {
+    pub predicted: usize,
+    pub reference: usize,
+    pub correct: usize,
+}
+
+impl BioCounts {
+    pub fn add(&mut self, other: &BioCounts) {
+        self.predicted += other.predicted;
+        self.reference += other.reference;
+        self.correct += other.correct;
+    }
+}
+
+/// tags the occurrences of the ranked terms, better ranked first, and of the
+/// keyphrases, longer first. occurrences overlapping a tagged one are left out
+/// since BIO tags can't represent them
+pub fn counts(
+    doc: &HulthDocument,
+    ranked: &[(Arc<str>, f64)],
+    keyphrases: &[Vec<String>],
+    normalizer: &Normalizer,
+) -> BioCounts {
+    let rank = ranked
+        .iter()
+        .enumerate()
+        .map(|(i, (term, _))| (&**term, i))
+        .collect::<HashMap<_, _>>();
+    let keyphrases = keyphrases
+        .iter()
+        .flatten()
+        .map(|phrase| normalizer.reference_words(phrase))
+        .filter(|words| !words.is_empty())
+        .collect::<Vec<_>>();
+
+    let mut counts = BioCounts::default();
+    for sentence in &doc.sentences {
+        let words = sentence
+            .tokens
+            .iter()
+            .filter(|t| !is_phrase(&t.word))
+            .collect::<Vec<_>>();
+
+        let mut reference = vec![];
+        for phrase in &keyphrases {
+            for (start, window) in words.windows(phrase.len()).enumerate() {
+                if window.iter().zip(phrase).all(|(t, w)| *t.word == **w) {
+                    reference.push((phrase.len(), start, start + phrase.len()));
+                }
+            }
+        }
+        reference.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+        let reference = tag(words.len(), reference.into_iter().map(|(_, s, e)| (s, e)));
+
+        let mut predicted = sentence
+            .tokens
+            .iter()
+            .filter_map(|t| rank.get(&*t.word).map(|r| (*r, t)))
+            .filter_map(|(r, t)| word_span(&words, t).map(|(s, e)| (r, s, e)))
+            .collect::<Vec<_>>();
+        predicted.sort_unstable();
+        let predicted = tag(words.len(), predicted.into_iter().map(|(_, s, e)| (s, e)));
+
+        let reference = chunks(&reference);
+        let predicted = chunks(&predicted);
+        counts.reference += reference.len();
+        counts.predicted += predicted.len();
+        counts.correct += predicted.intersection(&reference).count();
+    }
+    counts
+}
+
+/// the words token covers, by its offsets
+fn word_span(words: &[&HulthToken], token: &HulthToken) -> Option<(usize, usize)> {
+    let start = words
+        .iter()
+        .position(|w| w.offset_begin == token.offset_begin)?;
+    let end = words
+        .iter()
+        .position(|w| w.offset_end == token.offset_end)?;
+    if start > end {
+        return None;
+    }
+    Some((start, end + 1))
+}
+
+/// tags the spans in order, skipping those overlapping an earlier one
+fn tag<I: IntoIterator<Item = (usize, usize)>>(len: usize, spans: I) -> Vec<Tag> {
+    let mut tags = vec![Tag::Outside; len];
+    for (start, end) in spans {
+        if tags[start..end].iter().any(|t| *t != Tag::Outside) {
+            continue;
+        }
+        tags[start] = Tag::Begin;
+        for t in &mut tags[start + 1..end] {
+            *t = Tag::Inside;
+        }
+    }
+    tags
+}
+
+/// the chunks of tags as word ranges, an inside tag
+/// without a chunk before it starts a new one
+fn chunks(tags: &[Tag]) -> HashSet<(usize, usize)> {
+    let mut chunks = HashSet::new();
+    let mut start = None;
+    for (i, t) in tags.iter().enumerate() {
+        match (t, start) {
+            (Tag::Begin, Some(s)) => {
+                chunks.insert((s, i));
+                start = Some(i);
+            }
+            (Tag::Begin, None) | (Tag::Inside, None) => start = Some(i),
+            (Tag::Outside, Some(s)) => {
+                chunks.insert((s, i));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        chunks.insert((s, tags.len()));
+    }
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::preprocess::Preprocessing;
+    use crate::Sentence;
+
+    /// a sentence of "deep neural network model" with the phrase tokens of phrases
+    fn document(phrases: &[(&str, i64, i64)]) -> HulthDocument {
+        let words = [
+            ("deep", 0, 4),
+            ("neural", 5, 11),
+            ("network", 12, 19),
+            ("model", 20, 25),
+        ];
+        let tokens = words
+            .iter()
+            .chain(phrases)
+            .map(|(word, begin, end)| HulthToken {
+                word: (*word).into(),
+                lemma: (*word).into(),
+                offset_begin: *begin,
+                offset_end: *end,
+                pos: "".into(),
+            })
+            .collect();
+        HulthDocument {
+            sentences: vec![Sentence { tokens }],
+        }
+    }
+
+    fn ranked(terms: &[&str]) -> Vec<(Arc<str>, f64)> {
+        terms.iter().map(|t| (Arc::from(*t), 1.0)).collect()
+    }
+
+    fn keyphrases(phrases: &[&str]) -> Vec<Vec<String>> {
+        vec![phrases.iter().map(|p| p.to_string()).collect()]
+    }
+
+    fn triple(counts: BioCounts) -> (usize, usize, usize) {
+        (counts.predicted, counts.reference, counts.correct)
+    }
+
+    #[test]
+    fn multi_token_phrases_are_one_chunk() {
+        let normalizer = Preprocessing::default().normalizer().unwrap();
+        let doc = document(&[("neural network", 5, 19)]);
+        let found = counts(
+            &doc,
+            &ranked(&["neural network"]),
+            &keyphrases(&["neural network"]),
+            &normalizer,
+        );
+        assert_eq!(triple(found), (1, 1, 1));
+        // the words on their own are other chunks than the phrase
+        let found = counts(
+            &doc,
+            &ranked(&["neural", "network"]),
+            &keyphrases(&["neural network"]),
+            &normalizer,
+        );
+        assert_eq!(triple(found), (2, 1, 0));
+    }
+
+    #[test]
+    fn overlapping_phrases_keep_the_better_ranked_and_the_longer() {
+        let normalizer = Preprocessing::default().normalizer().unwrap();
+        let doc = document(&[("neural network", 5, 19), ("network model", 12, 25)]);
+        // "network model" overlaps the better ranked "neural network",
+        // and "neural network" the longer "deep neural network"
+        let found = counts(
+            &doc,
+            &ranked(&["neural network", "network model"]),
+            &keyphrases(&["neural network", "deep neural network"]),
+            &normalizer,
+        );
+        assert_eq!(triple(found), (1, 1, 0));
+        let found = counts(
+            &doc,
+            &ranked(&["network model", "neural network"]),
+            &keyphrases(&["network model", "deep neural"]),
+            &normalizer,
+        );
+        assert_eq!(triple(found), (1, 2, 1));
+    }
+
+    #[test]
+    fn phrases_can_end_the_sentence() {
+        let normalizer = Preprocessing::default().normalizer().unwrap();
+        let doc = document(&[("network model", 12, 25)]);
+        let found = counts(
+            &doc,
+            &ranked(&["network model"]),
+            &keyphrases(&["network model", "model"]),
+            &normalizer,
+        );
+        assert_eq!(triple(found), (1, 1, 1));
+        let tags = tag(4, vec![(2, 4)]);
+        assert_eq!(tags, [Tag::Outside, Tag::Outside, Tag::Begin, Tag::Inside]);
+        assert_eq!(chunks(&tags), HashSet::from([(2, 4)]));
+    }
+
+    #[test]
+    fn stray_inside_tags_start_a_chunk() {
+        let tags = [
+            Tag::Inside,
+            Tag::Outside,
+            Tag::Begin,
+            Tag::Begin,
+            Tag::Inside,
+        ];
+        assert_eq!(chunks(&tags), HashSet::from([(0, 1), (2, 3), (3, 5)]));
+    }
+}
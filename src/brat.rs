@@ -0,0 +1,89 @@
+//! BRAT standoff export, a `.txt` and an `.ann` file per document
+//! with the predicted and optionally the reference keyphrases as text-bound annotations
+use crate::spans::Span;
+use crate::HulthDocument;
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+/// the document text with every word at its offsets and spaces in between.
+/// the Hulth data does not include the abstracts themselves
+pub fn text(doc: &HulthDocument) -> Vec<char> {
+    let tokens = doc.sentences.iter().flat_map(|s| s.tokens.iter());
+    let length = tokens
+        .clone()
+        .map(|t| t.offset_end)
+        .max()
+        .unwrap_or(0)
+        .max(0);
+    let mut text = vec![' '; length as usize];
+    for token in tokens {
+        let start = token.offset_begin.max(0) as usize;
+        for (slot, c) in text.iter_mut().skip(start).zip(token.word.chars()) {
+            *slot = c;
+        }
+    }
+    text
+}
+
+/// the spans of every occurrence of the keyphrases in doc,
+/// phrases are matched word by word ignoring case
+pub fn reference_spans(doc: &HulthDocument, keyphrases: &[Vec<String>]) -> Vec<(i64, i64)> {
+    let mut spans = vec![];
+    for phrase in keyphrases.iter().flatten() {
+        let words = phrase.split(' ').map(str::to_lowercase).collect::<Vec<_>>();
+        for sentence in &doc.sentences {
+            for window in sentence.tokens.windows(words.len()) {
+                let matches = window
+                    .iter()
+                    .zip(&words)
+                    .all(|(t, w)| t.word.to_lowercase() == *w);
+                if matches {
+                    spans.push((window[0].offset_begin, window[window.len() - 1].offset_end));
+                }
+            }
+        }
+    }
+    spans.sort_unstable();
+    spans.dedup();
+    spans
+}
+
+/// writes `<name>.txt` and `<name>.ann` to dir. predictions are `Keyphrase`
+/// annotations with their score as a note, references are `Reference` annotations
+pub fn write(
+    dir: &Path,
+    name: &str,
+    text: &[char],
+    predicted: &[Span],
+    references: &[(i64, i64)],
+) -> io::Result<()> {
+    let mut txt = BufWriter::new(File::create(dir.join(format!("{}.txt", name)))?);
+    writeln!(txt, "{}", text.iter().collect::<String>())?;
+    txt.flush()?;
+
+    let slice = |start: i64, end: i64| -> String {
+        text.get(start.max(0) as usize..end.max(0) as usize)
+            .map(|chars| chars.iter().collect())
+            .unwrap_or_default()
+    };
+    let mut ann = BufWriter::new(File::create(dir.join(format!("{}.ann", name)))?);
+    let mut id = 0;
+    for span in predicted {
+        id += 1;
+        let covered = slice(span.start, span.end);
+        writeln!(
+            ann,
+            "T{}\tKeyphrase {} {}\t{}",
+            id, span.start, span.end, covered
+        )?;
+        writeln!(ann, "#{}\tAnnotatorNotes T{}\tscore {}", id, id, span.score)?;
+    }
+    for (start, end) in references {
+        id += 1;
+        let covered = slice(*start, *end);
+        writeln!(ann, "T{}\tReference {} {}\t{}", id, start, end, covered)?;
+    }
+    ann.flush()
+}
@@ -0,0 +1,630 @@
+//! measures of subsets of the predictions, printed after the summary with --breakdown
+use crate::bio::BioCounts;
+use crate::intern::Interner;
+use crate::matching::References;
+use crate::metrics::{self, f1};
+use crate::preprocess::Normalizer;
+use crate::report::Summary;
+use crate::results::DocumentResult;
+use crate::HulthDocument;
+
+use clap::ValueEnum;
+
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::{self, Write};
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Breakdown {
+    /// by the part of speech of the predicted terms
+    Pos,
+    /// by the number of tokens of the documents, see --length-buckets
+    Length,
+    /// by the number of reference keyphrases of the documents, see --reference-buckets
+    References,
+    /// by the sentence in which matched terms first occur
+    Position,
+    /// chunk-level measures of BIO tags over the words, a span-aware complement
+    Bio,
+    /// rank correlation of the scores with the gold relevance of the terms
+    Correlation,
+    /// share of keywords among the candidates of each score decile over the corpus
+    Calibration,
+    /// against every keyword group of a document on its own, the best and the mean group
+    Groups,
+    /// rank-aware measures of the rankings, MAP and nDCG
+    Ranking,
+    /// at K = the number of reference keyphrases of every document next to --top-k
+    Adaptive,
+    /// against the keyphrases occurring in their document and those absent from it
+    Presence,
+    /// the share of the reference words found in a ranked term next to the recall
+    Coverage,
+    /// ROUGE-1 and ROUGE-2 of the ranked terms against the keyphrases as texts
+    Rouge,
+}
+
+/// coarse word class of a Penn Treebank tag
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PosClass {
+    Noun,
+    Adjective,
+    Verb,
+    Other,
+}
+
+impl PosClass {
+    /// the class of a phrase is that of its last word, its head in English
+    fn of(tags: &str) -> Self {
+        let head = tags.rsplit(' ').next().unwrap_or_default();
+        if head.starts_with("NN") {
+            PosClass::Noun
+        } else if head.starts_with("JJ") {
+            PosClass::Adjective
+        } else if head.starts_with("VB") {
+            PosClass::Verb
+        } else {
+            PosClass::Other
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            PosClass::Noun => "noun",
+            PosClass::Adjective => "adjective",
+            PosClass::Verb => "verb",
+            PosClass::Other => "other",
+        }
+    }
+}
+
+/// token counts of one class in one or more documents
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Counts {
+    pub predicted: usize,
+    pub relevant: usize,
+    pub reference: usize,
+}
+
+impl Counts {
+    fn add(&mut self, other: &Counts) {
+        self.predicted += other.predicted;
+        self.relevant += other.relevant;
+        self.reference += other.reference;
+    }
+}
+
+pub type PosCounts = BTreeMap<PosClass, Counts>;
+
+/// counts the ranked and reference terms of doc per class of their first
+/// occurrence in doc, reference terms missing from doc count as other
+pub fn pos_counts(
+    doc: &HulthDocument,
+    ranked: &[(Arc<str>, f64)],
+    references: &References,
+) -> PosCounts {
+    let mut tags = HashMap::new();
+    for token in doc.sentences.iter().flat_map(|s| s.tokens.iter()) {
+        tags.entry(&*token.word).or_insert(&*token.pos);
+    }
+    let class = |term: &str| tags.get(term).map_or(PosClass::Other, |t| PosClass::of(t));
+
+    let mut counts = PosCounts::new();
+    for (term, _) in ranked {
+        let counts = counts.entry(class(term)).or_default();
+        counts.predicted += 1;
+        if references.contains(term) {
+            counts.relevant += 1;
+        }
+    }
+    for (term, n) in references.iter() {
+        counts.entry(class(term)).or_default().reference += n;
+    }
+    counts
+}
+
+/// the measures of a ranking against one keyword group of a document
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct GroupMeasure {
+    pub precision: f64,
+    pub recall: f64,
+    pub f1: f64,
+}
+
+/// measures ranked against every keyword group on its own,
+/// as if each were the keyphrases of a separate annotator
+pub fn groups(
+    ranked: &[(Arc<str>, f64)],
+    keyphrases: &[Vec<String>],
+    normalizer: &Normalizer,
+    interner: &mut Interner,
+) -> Vec<GroupMeasure> {
+    keyphrases
+        .iter()
+        .map(|group| {
+            let reference = References::new(std::slice::from_ref(group), normalizer, interner);
+            let terms = ranked.iter().map(|(term, _)| &**term).collect::<Vec<_>>();
+            let hits = reference.hits(&terms);
+            let precision = metrics::precision(&hits);
+            let recall = metrics::recall(&hits, reference.len());
+            GroupMeasure {
+                precision,
+                recall,
+                f1: f1(precision, recall),
+            }
+        })
+        .collect()
+}
+
+/// measures ranked against the keyphrases that occur in the document and
+/// against the unreachable ones that don't, None for a side without keyphrases
+pub fn presence(
+    ranked: &[(Arc<str>, f64)],
+    keyphrases: &[Vec<String>],
+    unreachable: &[&str],
+    normalizer: &Normalizer,
+    interner: &mut Interner,
+) -> (Option<GroupMeasure>, Option<GroupMeasure>) {
+    let unreachable = unreachable.iter().copied().collect::<HashSet<_>>();
+    let (mut present, mut absent) = (vec![], vec![]);
+    for group in keyphrases {
+        let (a, p): (Vec<_>, Vec<_>) = group
+            .iter()
+            .cloned()
+            .partition(|phrase| unreachable.contains(phrase.as_str()));
+        present.push(p);
+        absent.push(a);
+    }
+    let terms = ranked.iter().map(|(term, _)| &**term).collect::<Vec<_>>();
+    let mut measure = |keyphrases: &[Vec<String>]| {
+        let reference = References::new(keyphrases, normalizer, interner);
+        if reference.len() == 0 {
+            return None;
+        }
+        let hits = reference.hits(&terms);
+        let precision = metrics::precision(&hits);
+        let recall = metrics::recall(&hits, reference.len());
+        Some(GroupMeasure {
+            precision,
+            recall,
+            f1: f1(precision, recall),
+        })
+    };
+    (measure(&present), measure(&absent))
+}
+
+/// the measures against all keyphrases next to those against the present and
+/// the absent ones, each averaged over the documents that have such keyphrases
+pub fn print_presence<W: Write>(mut w: W, measures: &[DocumentResult]) -> io::Result<()> {
+    let measures = measures
+        .iter()
+        .filter(|m| m.degenerate.is_none())
+        .collect::<Vec<_>>();
+    writeln!(
+        w,
+        "{:<10}  {:>9}  {:>9}  {:>9}  {:>9}",
+        "keyphrases", "documents", "precision", "recall", "f1"
+    )?;
+    let all = Summary::from_measures(measures.iter().copied());
+    writeln!(
+        w,
+        "{:<10}  {:>9}  {:>9.4}  {:>9.4}  {:>9.4}",
+        "all",
+        measures.len(),
+        all.precision,
+        all.recall,
+        all.f1
+    )?;
+    let present = measures.iter().filter_map(|m| m.present);
+    let absent = measures.iter().filter_map(|m| m.absent);
+    let sides = [("present", present.collect()), ("absent", absent.collect())];
+    for (name, rows) in sides {
+        let rows: Vec<GroupMeasure> = rows;
+        let mean = |f: fn(&GroupMeasure) -> f64| {
+            metrics::mean(&rows.iter().map(f).collect::<Vec<_>>()).format()
+        };
+        writeln!(
+            w,
+            "{:<10}  {:>9}  {:>9}  {:>9}  {:>9}",
+            name,
+            rows.len(),
+            mean(|g| g.precision),
+            mean(|g| g.recall),
+            mean(|g| g.f1)
+        )?;
+    }
+    let absent = measures.iter().map(|m| m.unreachable).sum::<usize>();
+    let total = measures.iter().map(|m| m.keyphrases).sum::<usize>();
+    writeln!(
+        w,
+        "{} of {} reference keyphrases are absent from their document",
+        absent, total
+    )
+}
+
+/// where in a document the matched terms first occur,
+/// the first sentence is the title in the Hulth data
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Positions {
+    /// matched terms per part
+    pub matched: [usize; 3],
+    /// sentences per part
+    pub sentences: [usize; 3],
+}
+
+const PARTS: [&str; 3] = ["first", "middle", "last"];
+
+/// a document of a single sentence has only a first one
+pub fn positions(doc: &HulthDocument, relevant: &[&Arc<str>]) -> Positions {
+    let relevant = relevant.iter().map(|t| &***t).collect::<HashSet<&str>>();
+    let mut seen = HashSet::new();
+    let mut positions = Positions::default();
+    let last = doc.sentences.len().saturating_sub(1);
+    for (i, sentence) in doc.sentences.iter().enumerate() {
+        let part = match i {
+            0 => 0,
+            i if i == last => 2,
+            _ => 1,
+        };
+        positions.sentences[part] += 1;
+        for token in &sentence.tokens {
+            if relevant.contains(&*token.word) && seen.insert(&*token.word) {
+                positions.matched[part] += 1;
+            }
+        }
+    }
+    positions
+}
+
+/// the share of matched terms first occurring in each part of the documents
+/// and their density, matched terms per sentence of that part
+pub fn print_positions<W: Write>(mut w: W, measures: &[DocumentResult]) -> io::Result<()> {
+    let mut total = Positions::default();
+    for measure in measures {
+        for part in 0..PARTS.len() {
+            total.matched[part] += measure.positions.matched[part];
+            total.sentences[part] += measure.positions.sentences[part];
+        }
+    }
+    let matched = total.matched.iter().sum();
+    writeln!(
+        w,
+        "{:<10}  {:>9}  {:>9}  {:>9}  {:>9}",
+        "sentence", "sentences", "matched", "share", "density"
+    )?;
+    for (part, name) in PARTS.iter().enumerate() {
+        writeln!(
+            w,
+            "{:<10}  {:>9}  {:>9}  {:>9.4}  {:>9.4}",
+            name,
+            total.sentences[part],
+            total.matched[part],
+            ratio(total.matched[part], matched),
+            ratio(total.matched[part], total.sentences[part])
+        )?;
+    }
+    Ok(())
+}
+
+/// precision and recall per class, micro-averaged over all documents
+/// since most documents predict only a few terms of the rarer classes
+pub fn print_pos<W: Write>(mut w: W, measures: &[DocumentResult]) -> io::Result<()> {
+    let mut total = PosCounts::new();
+    for measure in measures {
+        for (class, counts) in &measure.pos {
+            total.entry(*class).or_default().add(counts);
+        }
+    }
+    writeln!(
+        w,
+        "{:<10}  {:>9}  {:>9}  {:>9}  {:>9}",
+        "pos", "predicted", "relevant", "precision", "recall"
+    )?;
+    for (class, counts) in &total {
+        writeln!(
+            w,
+            "{:<10}  {:>9}  {:>9}  {:>9.4}  {:>9.4}",
+            class.name(),
+            counts.predicted,
+            counts.relevant,
+            ratio(counts.relevant, counts.predicted),
+            ratio(counts.relevant, counts.reference)
+        )?;
+    }
+    Ok(())
+}
+
+/// the summary of the documents in each bucket of key.
+/// bounds are the exclusive upper bounds of all but the last bucket
+pub fn print_buckets<W, K>(
+    mut w: W,
+    name: &str,
+    bounds: &[usize],
+    measures: &[DocumentResult],
+    key: K,
+) -> io::Result<()>
+where
+    W: Write,
+    K: Fn(&DocumentResult) -> usize,
+{
+    let mut bounds = bounds.to_vec();
+    bounds.sort_unstable();
+    bounds.dedup();
+    let mut buckets = vec![vec![]; bounds.len() + 1];
+    for measure in measures {
+        let value = key(measure);
+        let bucket = bounds
+            .iter()
+            .position(|bound| value < *bound)
+            .unwrap_or(bounds.len());
+        buckets[bucket].push(measure);
+    }
+
+    writeln!(
+        w,
+        "{:<10}  {:>9}  {:>9}  {:>9}  {:>9}",
+        name, "documents", "precision", "recall", "f1"
+    )?;
+    for (i, bucket) in buckets.iter().enumerate() {
+        let label = match (i.checked_sub(1).map(|i| bounds[i]), bounds.get(i)) {
+            (None, Some(upper)) => format!("<{}", upper),
+            (Some(lower), Some(upper)) => format!("{}-{}", lower, upper - 1),
+            (Some(lower), None) => format!(">={}", lower),
+            (None, None) => "all".into(),
+        };
+        if bucket.is_empty() {
+            writeln!(
+                w,
+                "{:<10}  {:>9}  {:>9}  {:>9}  {:>9}",
+                label, 0, "-", "-", "-"
+            )?;
+            continue;
+        }
+        let summary = Summary::from_measures(bucket.iter().copied());
+        writeln!(
+            w,
+            "{:<10}  {:>9}  {:>9.4}  {:>9.4}  {:>9.4}",
+            label,
+            bucket.len(),
+            summary.precision,
+            summary.recall,
+            summary.f1
+        )?;
+    }
+    Ok(())
+}
+
+/// seqeval-style chunk precision, recall and F1, micro-averaged
+pub fn print_bio<W: Write>(mut w: W, measures: &[DocumentResult]) -> io::Result<()> {
+    let mut total = BioCounts::default();
+    for measure in measures {
+        total.add(&measure.bio);
+    }
+    let precision = ratio(total.correct, total.predicted);
+    let recall = ratio(total.correct, total.reference);
+    writeln!(
+        w,
+        "{:<10}  {:>9}  {:>9}  {:>9}  {:>9}  {:>9}  {:>9}",
+        "bio", "predicted", "reference", "correct", "precision", "recall", "f1"
+    )?;
+    writeln!(
+        w,
+        "{:<10}  {:>9}  {:>9}  {:>9}  {:>9.4}  {:>9.4}  {:>9.4}",
+        "chunks",
+        total.predicted,
+        total.reference,
+        total.correct,
+        precision,
+        recall,
+        f1(precision, recall)
+    )
+}
+
+/// the candidates of all documents ordered by score and split into ten
+/// buckets of equal size, lowest first, each with the share of keywords in it.
+/// equal scores at a bucket boundary may fall on both sides
+pub fn print_calibration<W: Write>(mut w: W, measures: &[DocumentResult]) -> io::Result<()> {
+    let mut candidates = measures
+        .iter()
+        .flat_map(|m| m.candidates.iter().copied())
+        .collect::<Vec<_>>();
+    candidates.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal));
+    writeln!(
+        w,
+        "{:<10}  {:>9}  {:>9}  {:>9}  {:>9}  {:>9}",
+        "decile", "min", "max", "terms", "keywords", "share"
+    )?;
+    for decile in 0..10 {
+        let bucket =
+            &candidates[decile * candidates.len() / 10..(decile + 1) * candidates.len() / 10];
+        let (min, max) = match (bucket.first(), bucket.last()) {
+            (Some(first), Some(last)) => (first.0, last.0),
+            _ => (f64::NAN, f64::NAN),
+        };
+        let keywords = bucket.iter().filter(|(_, relevant)| *relevant).count();
+        writeln!(
+            w,
+            "{:<10}  {:>9.4}  {:>9.4}  {:>9}  {:>9}  {:>9.4}",
+            decile + 1,
+            min,
+            max,
+            bucket.len(),
+            keywords,
+            ratio(keywords, bucket.len())
+        )?;
+    }
+    Ok(())
+}
+
+/// the mean average precision and nDCG over the documents
+pub fn print_ranking<W: Write>(mut w: W, measures: &[DocumentResult]) -> io::Result<()> {
+    let measures = measures
+        .iter()
+        .filter(|m| m.degenerate.is_none())
+        .collect::<Vec<_>>();
+    let map = metrics::mean(
+        &measures
+            .iter()
+            .map(|m| m.metrics.average_precision)
+            .collect::<Vec<_>>(),
+    );
+    let ndcg = metrics::mean(&measures.iter().map(|m| m.metrics.ndcg).collect::<Vec<_>>());
+    writeln!(w, "{:<10}  {:>9}  {:>9}", "ranking", "map", "ndcg")?;
+    writeln!(
+        w,
+        "{:<10}  {:>9}  {:>9}",
+        "documents",
+        map.format(),
+        ndcg.format()
+    )
+}
+
+/// the token coverage, crediting partially found keyphrases, next to the recall
+pub fn print_coverage<W: Write>(mut w: W, measures: &[DocumentResult]) -> io::Result<()> {
+    let measures = measures
+        .iter()
+        .filter(|m| m.degenerate.is_none())
+        .collect::<Vec<_>>();
+    let mean = |f: fn(&DocumentResult) -> f64| {
+        metrics::mean(&measures.iter().map(|m| f(m)).collect::<Vec<_>>()).format()
+    };
+    writeln!(w, "{:<10}  {:>9}  {:>9}", "measure", "documents", "mean")?;
+    for (name, value) in [
+        ("recall", mean(|m| m.metrics.recall)),
+        ("coverage", mean(|m| m.metrics.coverage)),
+    ] {
+        writeln!(w, "{:<10}  {:>9}  {:>9}", name, measures.len(), value)?;
+    }
+    Ok(())
+}
+
+/// the measures of a document at K = its number of reference terms, for `--breakdown adaptive`
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Adaptive {
+    pub k: usize,
+    pub precision: f64,
+    pub recall: f64,
+    pub f1: f64,
+}
+
+impl Adaptive {
+    /// hits are those of the whole ranking, before --top-k cuts it
+    pub fn of(hits: &[bool], reference: usize) -> Self {
+        let hits = &hits[..reference.min(hits.len())];
+        let precision = metrics::precision(hits);
+        let recall = metrics::recall(hits, reference);
+        Adaptive {
+            k: reference,
+            precision,
+            recall,
+            f1: f1(precision, recall),
+        }
+    }
+}
+
+/// the measures at --top-k next to those at K = the number of reference terms
+/// of every document, whose precision is the R-precision
+pub fn print_adaptive<W: Write>(
+    mut w: W,
+    measures: &[DocumentResult],
+    top_k: Option<usize>,
+) -> io::Result<()> {
+    let measures = measures
+        .iter()
+        .filter(|m| m.degenerate.is_none())
+        .collect::<Vec<_>>();
+    let mean = |f: &dyn Fn(&DocumentResult) -> f64| {
+        metrics::mean(&measures.iter().map(|m| f(m)).collect::<Vec<_>>()).format()
+    };
+    let fixed = match top_k {
+        Some(k) => format!("top {}", k),
+        None => "all".into(),
+    };
+    writeln!(
+        w,
+        "{:<10}  {:>9}  {:>9}  {:>9}  {:>9}",
+        "k", "mean k", "precision", "recall", "f1"
+    )?;
+    writeln!(
+        w,
+        "{:<10}  {:>9}  {:>9}  {:>9}  {:>9}",
+        fixed,
+        mean(&|m| m.ranking.len() as f64),
+        mean(&|m| m.metrics.precision),
+        mean(&|m| m.metrics.recall),
+        mean(&|m| m.metrics.f1)
+    )?;
+    writeln!(
+        w,
+        "{:<10}  {:>9}  {:>9}  {:>9}  {:>9}",
+        "references",
+        mean(&|m| m.adaptive.k as f64),
+        mean(&|m| m.adaptive.precision),
+        mean(&|m| m.adaptive.recall),
+        mean(&|m| m.adaptive.f1)
+    )
+}
+
+/// the measures against all groups together next to those against the best
+/// group of every document and the mean over its groups, averaged over documents
+pub fn print_groups<W: Write>(mut w: W, measures: &[DocumentResult]) -> io::Result<()> {
+    let mut best = vec![];
+    let mut means = vec![];
+    for measure in measures
+        .iter()
+        .filter(|m| !m.groups.is_empty() && m.degenerate.is_none())
+    {
+        let groups = &measure.groups;
+        let top = groups
+            .iter()
+            .max_by(|a, b| a.f1.partial_cmp(&b.f1).unwrap_or(Ordering::Equal));
+        best.extend(top.copied());
+        let n = groups.len() as f64;
+        means.push(GroupMeasure {
+            precision: groups.iter().map(|g| g.precision).sum::<f64>() / n,
+            recall: groups.iter().map(|g| g.recall).sum::<f64>() / n,
+            f1: groups.iter().map(|g| g.f1).sum::<f64>() / n,
+        });
+    }
+    let several = measures.iter().filter(|m| m.groups.len() > 1).count();
+    writeln!(
+        w,
+        "{:<10}  {:>9}  {:>9}  {:>9}",
+        "groups", "precision", "recall", "f1"
+    )?;
+    let pooled = Summary::from_measures(measures);
+    writeln!(
+        w,
+        "{:<10}  {:>9.4}  {:>9.4}  {:>9.4}",
+        "all", pooled.precision, pooled.recall, pooled.f1
+    )?;
+    for (name, rows) in [("best", &best), ("mean", &means)] {
+        let average = |f: fn(&GroupMeasure) -> f64| {
+            rows.iter().map(f).sum::<f64>() / rows.len().max(1) as f64
+        };
+        writeln!(
+            w,
+            "{:<10}  {:>9.4}  {:>9.4}  {:>9.4}",
+            name,
+            average(|g| g.precision),
+            average(|g| g.recall),
+            average(|g| g.f1)
+        )?;
+    }
+    writeln!(
+        w,
+        "{} of {} documents have more than one group",
+        several,
+        measures.len()
+    )
+}
+
+fn ratio(part: usize, whole: usize) -> f64 {
+    if whole == 0 {
+        return 0f64;
+    }
+    part as f64 / whole as f64
+}
@@ -0,0 +1,97 @@
+//! the content-addressed cache of `--cache-dir`. a preprocessed document is kept
+//! under the hash of its file and the preprocessing settings, a corpus under the
+//! hash of the keys of its documents. editing a file misses only that document
+//! and the corpora holding it, switching back to earlier contents hits again.
+//! a lemma dictionary is hashed by its contents, runs with an external
+//! lemmatizer aren't cached since its answers may change between runs
+use crate::corpus::Reading;
+use crate::fnv::Fnv;
+use crate::lemmatize::Lemmatizer;
+use crate::preprocess::Preprocessing;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// the entries of single preprocessed documents
+pub const DOCUMENTS: &str = "documents";
+/// the entries of whole preprocessed corpora
+pub const CORPORA: &str = "corpora";
+
+#[derive(Debug)]
+pub struct Cache {
+    dir: PathBuf,
+    /// the hash of everything besides the file contents that shapes a document
+    settings: Vec<u8>,
+}
+
+impl Cache {
+    pub fn open(dir: &Path, preprocessing: &Preprocessing, reading: &Reading) -> io::Result<Self> {
+        for kind in [DOCUMENTS, CORPORA] {
+            fs::create_dir_all(dir.join(kind))?;
+        }
+        let mut settings = Fnv::new();
+        // entries of other releases may not deserialize
+        settings.write(env!("CARGO_PKG_VERSION").as_bytes());
+        settings.write(&serde_json::to_vec(preprocessing)?);
+        settings.write(&[reading.lenient() as u8, reading.ids.hashed() as u8]);
+        if let Some(Lemmatizer::Dictionary(path)) = &preprocessing.lemmatize {
+            settings.write(&fs::read(path)?);
+        }
+        Ok(Cache {
+            dir: dir.into(),
+            settings: settings.finish().to_le_bytes().to_vec(),
+        })
+    }
+
+    /// the key of the document in the file at path
+    pub fn document_key(&self, path: &Path) -> io::Result<String> {
+        let mut hash = Fnv::new();
+        hash.write(&self.settings);
+        // the name may be the id of the document
+        hash.write(path.file_name().unwrap_or_default().as_encoded_bytes());
+        hash.write(&[0]);
+        hash.write(&fs::read(path)?);
+        Ok(hash.hex())
+    }
+
+    /// the key of the corpus of the documents of keys, in their order
+    pub fn corpus_key(&self, keys: &[String]) -> String {
+        let mut hash = Fnv::new();
+        for key in keys {
+            hash.write(key.as_bytes());
+        }
+        hash.hex()
+    }
+
+    /// the entry of kind under key, None if there is none or it can't be read
+    pub fn get<T: DeserializeOwned>(&self, kind: &str, key: &str) -> Option<T> {
+        let path = self.path(kind, key);
+        let file = File::open(&path).ok()?;
+        match bincode::deserialize_from(BufReader::new(file)) {
+            Ok(value) => Some(value),
+            Err(e) => {
+                warn!(path = %path.display(), error = %e, "ignoring unreadable cache entry");
+                None
+            }
+        }
+    }
+
+    /// writes the entry of kind under key, through a temporary file
+    /// so an interrupted run leaves no partial entry behind
+    pub fn put<T: Serialize>(&self, kind: &str, key: &str, value: &T) -> io::Result<()> {
+        let path = self.path(kind, key);
+        let partial = path.with_extension("partial");
+        let mut writer = BufWriter::new(File::create(&partial)?);
+        bincode::serialize_into(&mut writer, value).map_err(io::Error::other)?;
+        writer.flush()?;
+        fs::rename(partial, path)
+    }
+
+    fn path(&self, kind: &str, key: &str) -> PathBuf {
+        self.dir.join(kind).join(key).with_extension("bin")
+    }
+}
@@ -0,0 +1,54 @@
+//! every ranked candidate of a run as a Parquet file,
+//! one row per (document, term) for analysis with DuckDB or pandas
+use crate::results::DocumentResult;
+
+use arrow_array::{ArrayRef, BooleanArray, Float64Array, RecordBatch, StringArray, UInt32Array};
+use arrow_schema::{DataType, Field, Schema};
+use parquet::arrow::ArrowWriter;
+
+use std::fs::File;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+/// writes `doc_id, term, score, rank, matched` for the candidates of measures,
+/// rank is 1-based within the document
+pub fn write_candidates(path: &Path, measures: &[DocumentResult]) -> io::Result<()> {
+    let mut doc_ids = vec![];
+    let mut terms = vec![];
+    let mut scores = vec![];
+    let mut ranks = vec![];
+    let mut matched = vec![];
+    for measure in measures {
+        for (rank, (term, (score, relevant))) in
+            measure.ranking.iter().zip(&measure.candidates).enumerate()
+        {
+            doc_ids.push(measure.document.as_str());
+            terms.push(&**term);
+            scores.push(*score);
+            ranks.push(rank as u32 + 1);
+            matched.push(*relevant);
+        }
+    }
+
+    let schema = Arc::new(Schema::new(vec![
+        Field::new("doc_id", DataType::Utf8, false),
+        Field::new("term", DataType::Utf8, false),
+        Field::new("score", DataType::Float64, false),
+        Field::new("rank", DataType::UInt32, false),
+        Field::new("matched", DataType::Boolean, false),
+    ]));
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(StringArray::from(doc_ids)),
+        Arc::new(StringArray::from(terms)),
+        Arc::new(Float64Array::from(scores)),
+        Arc::new(UInt32Array::from(ranks)),
+        Arc::new(BooleanArray::from(matched)),
+    ];
+    let batch = RecordBatch::try_new(schema.clone(), columns).map_err(io::Error::other)?;
+    let mut writer =
+        ArrowWriter::try_new(File::create(path)?, schema, None).map_err(io::Error::other)?;
+    writer.write(&batch).map_err(io::Error::other)?;
+    writer.close().map_err(io::Error::other)?;
+    Ok(())
+}
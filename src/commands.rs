@@ -0,0 +1,117 @@
+//! the subcommands of `hulth-eval`, one module each with its options
+//! and a `run` function
+use crate::corpus::Reading;
+use crate::features;
+use crate::granularity::Granularity;
+use crate::intern::Interner;
+use crate::matching::References;
+use crate::model::Model;
+use crate::scoring::{Extractor, Scoring};
+use crate::weighting::DocumentFrequencies;
+use crate::{corpus, HulthDocumentKeywords, HulthToken};
+
+use lib_tfidf::{Document, Tfidf};
+
+use std::borrow::Cow;
+use std::fs;
+use std::io::{self, BufReader};
+use std::path::Path;
+
+pub mod ablate;
+pub mod compare;
+pub mod completions;
+pub mod convert;
+pub mod convert_vectors;
+pub mod eval;
+pub mod export_brat;
+pub mod export_features;
+pub mod export_idf;
+pub mod export_matrix;
+pub mod gen_corpus;
+pub mod merge;
+pub mod rank;
+pub mod rank_batch;
+pub mod schema;
+#[cfg(feature = "serve")]
+pub mod serve;
+#[cfg(feature = "grpc")]
+pub mod serve_grpc;
+pub mod stats;
+pub mod sweep;
+pub mod train;
+pub mod update_model;
+pub mod validate;
+pub mod verify_offsets;
+
+/// the corpus of model in the units of granularity with their document frequencies,
+/// those stored with the model for documents and counted for sentences
+#[allow(clippy::type_complexity)]
+pub fn units(
+    model: &Model,
+    granularity: Granularity,
+) -> (
+    Vec<Box<dyn Document<String, HulthToken>>>,
+    Cow<'_, DocumentFrequencies>,
+) {
+    match granularity {
+        Granularity::Document => (model.corpus.boxed(), Cow::Borrowed(&model.frequencies)),
+        Granularity::Sentence => {
+            let docs = model.corpus.boxed_sentences();
+            let frequencies = DocumentFrequencies::new(&docs);
+            (docs, Cow::Owned(frequencies))
+        }
+    }
+}
+
+/// ranks every document of dataset against the model and calls f with the
+/// document name, term, features and gold label of each candidate
+#[allow(clippy::too_many_arguments)]
+pub fn for_each_candidate<F>(
+    model: &Model,
+    dataset: &Path,
+    references: &Path,
+    reading: &Reading,
+    extractor: Extractor,
+    scoring: &Scoring,
+    top_k: Option<usize>,
+    mut f: F,
+) -> io::Result<()>
+where
+    F: FnMut(&str, &str, &features::Features, bool) -> io::Result<()>,
+{
+    let docs = model.corpus.boxed();
+    let mut tfidf = Tfidf::new(docs.as_slice());
+    tfidf.fit_transform()?;
+    info!(documents = docs.len(), "fitted model");
+    let scoring = scoring.for_extractor(extractor)?;
+    let reranker = scoring.reranker(extractor, || model.frequencies.clone())?;
+    let frequencies = &model.frequencies;
+
+    let keywords: HulthDocumentKeywords =
+        serde_json::from_reader(BufReader::new(fs::File::open(references)?))?;
+    let normalizer = model.preprocessing.normalizer()?;
+    let mut interner = Interner::new();
+    for entry in corpus::stream_dir(dataset, reading)? {
+        let (name, original) = entry?;
+        let keyphrases = match keywords.get(&name) {
+            Some(keyphrases) => keyphrases,
+            None => {
+                warn!(document = %name, "found no keywords, skipping");
+                continue;
+            }
+        };
+        let reference = References::new(keyphrases, &normalizer, &mut interner);
+        let doc = normalizer.apply(&original);
+        let scores = tfidf.rank_tokens(&doc.get_content())?.into_iter().collect();
+        let scores = model.preprocessing.resolve(&doc, scores);
+        let mut ranked = scoring.rank(extractor, reranker.as_ref(), &doc, scores);
+        if let Some(k) = top_k {
+            ranked.truncate(k);
+        }
+        let candidates = features::extract(&doc, &ranked, frequencies);
+        for ((term, _), features) in ranked.iter().zip(&candidates) {
+            f(&name, term, features, reference.contains(term))?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,87 @@
+//! `ablate`, measures the effect of every preprocessing stage on F1
+use super::eval::{first_extractor, in_run_dir, run_dataset, DatasetSpec, EvalArgs};
+use crate::corpus::Reading;
+use crate::report::Summary;
+use crate::scoring::Collapse;
+use crate::weighting::Idf;
+use crate::{manifest, preflight, preprocess};
+
+use std::io::{self, Write};
+
+/// evaluates the configuration of args, then each stage toggled on its own.
+/// the effect of a stage is the F1 with it minus the F1 without it
+pub fn run(args: &EvalArgs, reading: &Reading) -> io::Result<()> {
+    let args = &in_run_dir(args)?;
+    let mut variants = vec![];
+    for stage in preprocess::STAGES.iter() {
+        let mut variant = args.clone();
+        variant.preprocessing = args.preprocessing.toggled(*stage);
+        let enabled = args.preprocessing.is_enabled(*stage);
+        variants.push((stage.to_string(), enabled, variant));
+    }
+    let mut variant = args.clone();
+    variant.scoring.collapse = match args.scoring.collapse {
+        Some(_) => None,
+        None => Some(Collapse::Highest),
+    };
+    variants.push(("collapse".into(), args.scoring.collapse.is_some(), variant));
+    if args.weighting.is_clamped() {
+        // without the clamps but with the same harness-computed idf
+        let mut variant = args.clone();
+        variant.weighting.idf_min = None;
+        variant.weighting.idf_max = None;
+        variant.weighting.idf = Some(args.weighting.idf.unwrap_or(Idf::Standard));
+        variants.push(("idf-clamp".into(), true, variant));
+    }
+    if args.dry_run {
+        let extractors = [first_extractor(args)];
+        return preflight::check(
+            args,
+            &[DatasetSpec::of(args)],
+            reading,
+            &extractors,
+            1 + variants.len(),
+        );
+    }
+    if let Some(path) = &args.manifest {
+        manifest::write(path, "ablate", args.scoring.seed, args)?;
+    }
+
+    let run = |variant: &EvalArgs, label: &str| -> io::Result<f64> {
+        info!(configuration = %label, "evaluating configuration");
+        let measures = run_dataset(
+            variant,
+            &args.dataset,
+            &args.references,
+            reading,
+            first_extractor(args),
+            Some(label),
+        )?;
+        Ok(Summary::from_measures(&measures).f1)
+    };
+    let baseline = run(args, "baseline")?;
+
+    let mut out = io::stdout().lock();
+    writeln!(
+        out,
+        "{:<12} {:>10} {:>10} {:>10}",
+        "stage", "f1 without", "f1 with", "effect"
+    )?;
+    for (stage, enabled, variant) in &variants {
+        let toggled = run(variant, stage)?;
+        let (with, without) = if *enabled {
+            (baseline, toggled)
+        } else {
+            (toggled, baseline)
+        };
+        writeln!(
+            out,
+            "{:<12} {:>10.4} {:>10.4} {:>+10.4}",
+            stage,
+            without,
+            with,
+            with - without
+        )?;
+    }
+    Ok(())
+}
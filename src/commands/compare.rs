@@ -0,0 +1,83 @@
+//! `compare`, compares the per-document results of two runs
+use crate::cmp_f64;
+use crate::report::Summary;
+use crate::results::{self, DocumentResult};
+
+use clap::Args;
+
+use std::collections::HashMap;
+use std::io::{self, IsTerminal, Write};
+use std::path::PathBuf;
+
+/// F1 changes smaller than this count as unchanged
+const EPSILON: f64 = 1e-4;
+
+#[derive(Debug, Args, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CompareArgs {
+    /// result file of the earlier run, written by `eval --results`
+    baseline: PathBuf,
+
+    /// result file of the run compared against the baseline
+    candidate: PathBuf,
+
+    /// number of documents with the largest F1 changes to list
+    #[arg(long, default_value_t = 10)]
+    top: usize,
+
+    /// disables colors in the summary
+    #[arg(long)]
+    no_color: bool,
+}
+
+/// prints the summary of the candidate with its changes against the baseline,
+/// both over the documents they have in common, and the documents that changed most
+pub fn run(args: &CompareArgs) -> io::Result<()> {
+    let baseline = results::read(&args.baseline)?
+        .into_iter()
+        .map(|m| (m.document.clone(), m))
+        .collect::<HashMap<_, _>>();
+    let candidate = results::read(&args.candidate)?;
+    let pairs = candidate
+        .iter()
+        .filter_map(|c| baseline.get(&c.document).map(|b| (b, c)))
+        .collect::<Vec<(&DocumentResult, &DocumentResult)>>();
+    let missing = baseline.len() + candidate.len() - 2 * pairs.len();
+    if missing > 0 {
+        warn!(documents = missing, "left out documents not in both runs");
+    }
+
+    let before = Summary::from_measures(pairs.iter().map(|(b, _)| *b));
+    let after = Summary::from_measures(pairs.iter().map(|(_, c)| *c));
+    let color = !args.no_color && io::stdout().is_terminal();
+    let mut out = io::stdout().lock();
+    after.print(&mut out, Some(&before), color)?;
+
+    let mut changes = pairs
+        .iter()
+        .map(|(b, c)| (c.document.as_str(), c.metrics.f1 - b.metrics.f1))
+        .collect::<Vec<_>>();
+    let improved = changes.iter().filter(|(_, d)| *d > EPSILON).count();
+    let worse = changes.iter().filter(|(_, d)| *d < -EPSILON).count();
+    writeln!(out)?;
+    writeln!(
+        out,
+        "{} documents: {} improved, {} worse, {} unchanged",
+        pairs.len(),
+        improved,
+        worse,
+        pairs.len() - improved - worse
+    )?;
+
+    changes.retain(|(_, d)| d.abs() > EPSILON);
+    changes.sort_by(|a, b| cmp_f64(a.1.abs(), b.1.abs()));
+    if changes.is_empty() || args.top == 0 {
+        return Ok(());
+    }
+    writeln!(out)?;
+    writeln!(out, "{:<20} {:>10}", "document", "f1 change")?;
+    for (document, change) in changes.iter().take(args.top) {
+        writeln!(out, "{:<20} {:>+10.4}", document, change)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,49 @@
+//! `completions`, shell completion scripts for `hulth-eval`
+use crate::config::Config;
+use crate::Cli;
+
+use clap::builder::PossibleValuesParser;
+use clap::{Args, CommandFactory};
+use clap_complete::Shell;
+
+use std::io::{self, Write};
+
+#[derive(Debug, Args)]
+pub struct CompletionsArgs {
+    /// the shell to write the completion script for
+    #[arg(value_enum)]
+    shell: Shell,
+}
+
+/// writes the completion script to stdout. with --config the names of its
+/// `[[eval.datasets]]` are completed for `eval --only`, so the script has
+/// to be generated again when they change
+pub fn run(args: &CompletionsArgs, config: Option<&Config>) -> io::Result<()> {
+    let mut command = Cli::command();
+    let names = config.map(dataset_names).unwrap_or_default();
+    if !names.is_empty() {
+        command = command.mut_subcommand("eval", |eval| {
+            eval.mut_arg("only", |only| {
+                only.value_parser(PossibleValuesParser::new(names))
+            })
+        });
+    }
+    // generated into memory, writing straight to stdout panics on a closed pipe
+    let mut script = vec![];
+    clap_complete::generate(args.shell, &mut command, "hulth-eval", &mut script);
+    io::stdout().write_all(&script)
+}
+
+/// the names of the `[[eval.datasets]]` entries of config
+fn dataset_names(config: &Config) -> Vec<String> {
+    let datasets = config
+        .section("eval")
+        .and_then(|eval| eval.get("datasets"))
+        .and_then(|datasets| datasets.as_array());
+    datasets
+        .into_iter()
+        .flatten()
+        .filter_map(|spec| spec.get("name")?.as_str())
+        .map(String::from)
+        .collect()
+}
@@ -0,0 +1,77 @@
+//! `convert`, converts a dataset between a directory of Hulth JSON files
+//! and a JSON lines file as read by `rank-batch`
+use crate::corpus::{self, Reading};
+use crate::{batch, HulthDocument};
+
+use clap::Args;
+
+use std::fs;
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Args, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConvertArgs {
+    /// a directory of Hulth JSON documents or a JSON lines file
+    input: PathBuf,
+
+    /// a JSON lines file if input is a directory, otherwise the directory
+    /// the documents are written to as `<id>.json`
+    #[arg(long, short)]
+    output: PathBuf,
+}
+
+/// one line of a converted directory, the document name becomes its id
+#[derive(Serialize)]
+struct Line<'a> {
+    id: &'a str,
+    #[serde(flatten)]
+    document: &'a HulthDocument,
+}
+
+pub fn run(args: &ConvertArgs, reading: &Reading) -> io::Result<()> {
+    let documents = if args.input.is_dir() {
+        to_lines(&args.input, &args.output, reading)?
+    } else {
+        to_dir(&args.input, &args.output, reading)?
+    };
+    info!(documents, output = %args.output.display(), "converted dataset");
+    Ok(())
+}
+
+pub fn to_lines(dir: &Path, output: &Path, reading: &Reading) -> io::Result<usize> {
+    let mut w = BufWriter::new(fs::File::create(output)?);
+    let mut documents = 0;
+    for entry in corpus::stream_dir(dir, reading)? {
+        let (name, document) = entry?;
+        let line = Line {
+            id: &name,
+            document: &document,
+        };
+        serde_json::to_writer(&mut w, &line)?;
+        writeln!(w)?;
+        documents += 1;
+    }
+    w.flush()?;
+    Ok(documents)
+}
+
+/// lines given as text are tokenized like `rank` does
+pub fn to_dir(input: &Path, dir: &Path, reading: &Reading) -> io::Result<usize> {
+    fs::create_dir_all(dir)?;
+    let mut documents = 0;
+    let reader = BufReader::new(fs::File::open(input)?);
+    batch::for_each_document(reader, reading, |id, document, _| {
+        if id.is_empty() || id.contains(['/', '\\']) || id == "." || id == ".." {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("id {:?} is not a valid file name", id),
+            ));
+        }
+        let mut w = BufWriter::new(fs::File::create(dir.join(format!("{}.json", id)))?);
+        serde_json::to_writer(&mut w, &document)?;
+        documents += 1;
+        w.flush()
+    })?;
+    Ok(documents)
+}
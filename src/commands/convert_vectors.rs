@@ -0,0 +1,61 @@
+//! `convert-vectors`, converts GloVe or word2vec vectors into a store
+//! that is memory-mapped when read
+use crate::vectors::{Precision, VectorFormat, Vectors};
+
+use clap::Args;
+
+use std::io;
+use std::path::PathBuf;
+
+/// words compared between the input and the store written
+const SAMPLE: usize = 1000;
+
+#[derive(Debug, Args, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConvertVectorsArgs {
+    /// GloVe or word2vec vectors in text or binary format
+    input: PathBuf,
+
+    /// the store to write, usually ending in `.hvec`
+    #[arg(long, short)]
+    output: PathBuf,
+
+    /// the format of input
+    #[arg(long, value_enum, default_value_t = VectorFormat::Auto)]
+    format: VectorFormat,
+
+    /// precision of the components in the store
+    #[arg(long, value_enum, default_value_t = Precision::F32)]
+    precision: Precision,
+}
+
+/// writes the store and reads it back, logging the largest difference
+/// of a component from the input, the error of --precision i8
+pub fn run(args: &ConvertVectorsArgs) -> io::Result<()> {
+    let input = Vectors::open(&args.input, args.format, Precision::F32, 0)?;
+    input.convert(args.precision, 0)?.write(&args.output)?;
+    let written = Vectors::open(&args.output, VectorFormat::Store, args.precision, 0)?;
+    let mut error = 0f32;
+    for word in input.words().take(SAMPLE) {
+        let (expected, actual) = match (input.get(&word), written.get(&word)) {
+            (Some(expected), Some(actual)) => (expected, actual),
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{} is missing from the store", word),
+                ))
+            }
+        };
+        for (e, a) in expected.iter().zip(actual.iter()) {
+            error = error.max((e - a).abs());
+        }
+    }
+    info!(
+        words = written.len(),
+        dim = written.dim(),
+        max_error = error,
+        output = %args.output.display(),
+        "converted vectors"
+    );
+    Ok(())
+}
@@ -0,0 +1,907 @@
+//! `eval`, evaluates ranked keywords against the references, the default subcommand
+use crate::alloc_stats;
+use crate::breakdown::{self, Breakdown};
+use crate::cache::Cache;
+#[cfg(feature = "parquet")]
+use crate::columnar;
+use crate::corpus::{Reading, Surfaces};
+use crate::granularity::{self, Granularity, Pooling};
+use crate::intern::Interner;
+use crate::lemmatize::Lemmatizer;
+use crate::matching::{Matching, ReferenceTokenization};
+use crate::model::Model;
+use crate::preprocess::Preprocessing;
+#[cfg(feature = "profile")]
+use crate::profile::Profiler;
+use crate::protocol::Protocol;
+use crate::report::{self, ReportFormat, Summary};
+use crate::results::{self, Checkpoint, DocumentResult, RunResult};
+use crate::scoring::{Extractor, Scoring};
+use crate::shard::Shard;
+#[cfg(feature = "tui")]
+use crate::tui;
+use crate::weighting::{DocumentFrequencies, Weighting};
+use crate::{
+    batch, corpus, correlation, interrupt, logging, manifest, pipeline, preflight, rouge, run_dir,
+    significance, source, spans, watch,
+};
+use crate::{HulthDocument, HulthDocumentKeywords, DEFAULT_DATASET, DEFAULT_REFERENCES};
+
+use clap::Args;
+use lib_tfidf::Tfidf;
+
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{self, BufWriter, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Instant;
+
+#[derive(Debug, Clone, Args, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EvalArgs {
+    /// directory containing the Hulth JSON documents, or a tar archive of them,
+    /// the `http://` URL of an index of their URLs or, with the `s3` feature,
+    /// an `s3://bucket/prefix`
+    #[arg(long, env = "HULTH_EVAL_DATASET", default_value = DEFAULT_DATASET)]
+    pub(crate) dataset: PathBuf,
+
+    /// JSON file mapping document names to their reference keywords
+    #[arg(long, env = "HULTH_EVAL_REFERENCES", default_value = DEFAULT_REFERENCES)]
+    pub(crate) references: PathBuf,
+
+    /// writes the fitted corpus to this file for later use by `rank`
+    #[arg(long)]
+    pub(crate) save_model: Option<PathBuf>,
+
+    /// fits on compact documents and re-reads each document for ranking,
+    /// so only one full document is held in memory at a time
+    #[arg(long, conflicts_with = "save_model")]
+    pub(crate) low_memory: bool,
+
+    #[command(flatten)]
+    #[serde(default)]
+    pub(crate) preprocessing: Preprocessing,
+
+    #[command(flatten)]
+    #[serde(default)]
+    pub(crate) scoring: Scoring,
+
+    #[command(flatten)]
+    #[serde(default)]
+    pub(crate) weighting: Weighting,
+
+    #[command(flatten)]
+    #[serde(default)]
+    pub(crate) reference_tokenization: ReferenceTokenization,
+
+    #[command(flatten)]
+    #[serde(default)]
+    pub(crate) matching: Matching,
+
+    /// the units the corpus is fitted on, the idf granularity
+    #[arg(long, value_enum, default_value_t = Granularity::Document, conflicts_with = "low_memory")]
+    #[serde(default)]
+    pub(crate) granularity: Granularity,
+
+    /// how the sentence scores of a term are combined with --granularity sentence
+    #[arg(long, value_enum, default_value_t = Pooling::Sum)]
+    #[serde(default)]
+    pub(crate) pooling: Pooling,
+
+    /// evaluates only the best k terms of every document instead of all ranked terms
+    #[arg(long)]
+    pub(crate) top_k: Option<usize>,
+
+    /// the preprocessing, matching and cutoff of a paper, see `protocol`
+    #[arg(long, value_enum)]
+    pub(crate) protocol: Option<Protocol>,
+
+    /// ranking variants to compare, more than one prints a table
+    #[arg(long, value_enum, value_delimiter = ',', default_value = "tfidf")]
+    pub(crate) extractors: Vec<Extractor>,
+
+    /// reads the dataset concurrently with async I/O, for network storage
+    #[cfg(feature = "async")]
+    #[arg(long, conflicts_with = "low_memory")]
+    pub(crate) async_io: bool,
+
+    /// maximum number of files read at once with --async-io
+    #[cfg(feature = "async")]
+    #[arg(long, default_value_t = 64)]
+    pub(crate) io_concurrency: usize,
+
+    /// keeps the preprocessed documents and corpora here, keyed by the contents
+    /// of the dataset files and the preprocessing settings, and reuses them
+    /// while they are unchanged. the directory is created if missing.
+    /// ignored with a `command:` or `http://` --lemmatize
+    #[arg(long, env = "HULTH_EVAL_CACHE_DIR", conflicts_with = "low_memory")]
+    pub(crate) cache_dir: Option<PathBuf>,
+
+    /// number of scoring threads, defaults to the available parallelism
+    #[arg(long, short, env = "HULTH_EVAL_JOBS")]
+    pub(crate) jobs: Option<usize>,
+
+    /// evaluates only the i-th of n slices of the corpus, 1 <= i <= n.
+    /// the model is still fitted on the whole corpus
+    #[arg(long)]
+    pub(crate) shard: Option<Shard>,
+
+    /// writes the measures of every document as JSON lines as they are computed, see `merge`
+    #[arg(long)]
+    pub(crate) results: Option<PathBuf>,
+
+    /// continues an interrupted run, skipping the documents already in --results
+    #[arg(long, requires = "results")]
+    pub(crate) resume: bool,
+
+    /// keeps no measures in memory, the summary is taken from --results once the
+    /// documents are written to it, for corpora too large to hold. needs --results
+    /// or --run-dir and evaluates a single dataset with a single extractor
+    #[arg(long, conflicts_with_all = ["breakdown", "baseline_extractor", "watch"])]
+    pub(crate) stream: bool,
+
+    /// dataset/reference pairs evaluated one after another instead of
+    /// --dataset and --references, only settable in the config file
+    #[arg(skip)]
+    #[serde(default)]
+    pub(crate) datasets: Vec<DatasetSpec>,
+
+    /// evaluates only these entries of the datasets of the config file, by name
+    #[arg(long, value_delimiter = ',')]
+    #[serde(default)]
+    pub(crate) only: Vec<String>,
+
+    /// summary of an earlier run (see --summary-out) to show changes against
+    #[arg(long)]
+    pub(crate) baseline: Option<PathBuf>,
+
+    /// writes the aggregate measures as JSON, usable as a later --baseline.
+    /// with several datasets or extractors the measures of each, under `rows`
+    #[arg(long)]
+    pub(crate) summary_out: Option<PathBuf>,
+
+    /// writes the measures of every extractor and dataset as a matrix, an extractor
+    /// per row and the metrics of every dataset as columns, the best of each column
+    /// highlighted. its format follows the extension, `.md`, `.csv` or `.html`
+    #[arg(long)]
+    pub(crate) report: Option<PathBuf>,
+
+    /// the format of --report, overriding its extension
+    #[arg(long, value_enum)]
+    pub(crate) report_format: Option<ReportFormat>,
+
+    /// marks the measures of the other extractors that differ significantly from
+    /// those of this one on the same dataset, by a paired randomization test
+    /// over the documents. one of --extractors
+    #[arg(long, value_enum)]
+    pub(crate) baseline_extractor: Option<Extractor>,
+
+    /// random sign flips of the randomization test of --baseline-extractor
+    #[arg(long, default_value_t = 10_000)]
+    pub(crate) significance_trials: usize,
+
+    /// runs the random extractor once per seed and reports the mean and
+    /// standard deviation of its measures, overriding --seed
+    #[arg(long, value_delimiter = ',')]
+    #[serde(default)]
+    pub(crate) seeds: Vec<u64>,
+
+    /// writes the peak heap use and the allocations of the phases of the run as JSON
+    #[cfg(feature = "alloc-stats")]
+    #[arg(long)]
+    pub(crate) memory_out: Option<PathBuf>,
+
+    /// samples the run and writes a flamegraph of where it spent its time
+    /// to the run directory
+    #[cfg(feature = "profile")]
+    #[arg(long, requires = "run_dir")]
+    pub(crate) profile: bool,
+
+    /// where --profile writes the flamegraph, set with the run directory
+    #[cfg(feature = "profile")]
+    #[arg(skip)]
+    #[serde(skip)]
+    pub(crate) flamegraph: Option<PathBuf>,
+
+    /// writes the options of the run as JSON
+    #[arg(long)]
+    pub(crate) manifest: Option<PathBuf>,
+
+    /// writes the ranked keywords of every document as JSON lines like `rank-batch`.
+    /// documents resumed from --results are left out
+    #[arg(long)]
+    pub(crate) keywords: Option<PathBuf>,
+
+    /// writes the manifest, summary, results, keywords and log of the run
+    /// to a new directory `runs/<timestamp>-<label>/` instead
+    #[arg(long, value_name = "LABEL", conflicts_with_all = ["results", "summary_out", "manifest", "keywords"])]
+    pub(crate) run_dir: Option<String>,
+
+    /// writes every ranked candidate with its score, rank and whether it matched
+    /// as Parquet. documents resumed from --results are left out
+    #[cfg(feature = "parquet")]
+    #[arg(long)]
+    pub(crate) candidates: Option<PathBuf>,
+
+    /// disables colors in the summary, they are also off when stdout is not a terminal
+    #[arg(long)]
+    pub(crate) no_color: bool,
+
+    /// checks the paths and references and prints the size of the run
+    /// without fitting or ranking anything
+    #[arg(long)]
+    pub(crate) dry_run: bool,
+
+    /// prints the measures of subsets of the predictions after the summary
+    #[arg(long, value_enum, value_delimiter = ',')]
+    #[serde(default)]
+    pub(crate) breakdown: Vec<Breakdown>,
+
+    /// bounds between the document length buckets of `--breakdown length`, in tokens
+    #[arg(long, value_delimiter = ',', default_values_t = [100, 200])]
+    pub(crate) length_buckets: Vec<usize>,
+
+    /// bounds between the buckets of `--breakdown references`, in reference keyphrases
+    #[arg(long, value_delimiter = ',', default_values_t = [5, 10, 15])]
+    pub(crate) reference_buckets: Vec<usize>,
+
+    /// evaluates again whenever a file of the dataset or the references change,
+    /// ranking only the documents whose files changed
+    #[arg(long, conflicts_with_all = ["results", "keywords", "run_dir", "dry_run"])]
+    pub(crate) watch: bool,
+
+    /// milliseconds between the checks of --watch for changed files
+    #[arg(long, default_value_t = 1000)]
+    pub(crate) watch_interval: u64,
+
+    /// the documents ranked, all if None. set by --watch to the changed ones
+    #[arg(skip)]
+    #[serde(skip)]
+    pub(crate) changed: Option<HashSet<String>>,
+
+    /// shows a live dashboard while the documents are ranked
+    #[cfg(feature = "tui")]
+    #[arg(long)]
+    pub(crate) tui: bool,
+}
+
+/// one entry of `[[eval.datasets]]` in the config file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DatasetSpec {
+    pub(crate) name: String,
+    pub(crate) dataset: PathBuf,
+    pub(crate) references: PathBuf,
+}
+
+impl DatasetSpec {
+    /// the --dataset and --references of args, named after the dataset directory
+    pub fn of(args: &EvalArgs) -> Self {
+        let name = args.dataset.file_name().unwrap_or_default();
+        DatasetSpec {
+            name: name.to_string_lossy().into_owned(),
+            dataset: args.dataset.clone(),
+            references: args.references.clone(),
+        }
+    }
+}
+
+pub fn run(args: &EvalArgs, reading: &Reading) -> io::Result<()> {
+    if args.dry_run {
+        let specs = specs(args)?;
+        return preflight::check(
+            args,
+            &specs,
+            reading,
+            &args.extractors,
+            args.extractors.len(),
+        );
+    }
+    if !args.watch {
+        interrupt::install()?;
+    }
+    let args = &in_run_dir(args)?;
+    if let Some(path) = &args.manifest {
+        manifest::write(path, "eval", args.scoring.seed, args)?;
+    }
+    #[cfg(feature = "profile")]
+    let profiler = match &args.flamegraph {
+        Some(path) => Some(Profiler::start(path)?),
+        None => None,
+    };
+    let result = evaluate(args, reading);
+    #[cfg(feature = "profile")]
+    if let Some(profiler) = profiler {
+        profiler.finish()?;
+    }
+    result
+}
+
+/// the evaluation of run once the run directory is set up
+fn evaluate(args: &EvalArgs, reading: &Reading) -> io::Result<()> {
+    if args.watch {
+        return watch::run(args, reading);
+    }
+    let table = !args.datasets.is_empty() || args.extractors.len() > 1 || !args.seeds.is_empty();
+    if args.stream {
+        let unsupported = if args.results.is_none() {
+            Some("--stream needs --results or --run-dir")
+        } else if table {
+            Some("--stream evaluates a single dataset with a single extractor")
+        } else {
+            None
+        };
+        #[cfg(feature = "parquet")]
+        let unsupported = unsupported.or(args
+            .candidates
+            .as_ref()
+            .map(|_| "--stream can't write --candidates"));
+        if let Some(message) = unsupported {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, message));
+        }
+    }
+    if table {
+        return eval_table(args, reading);
+    }
+    let measures = run_dataset(
+        args,
+        &args.dataset,
+        &args.references,
+        reading,
+        first_extractor(args),
+        None,
+    )?;
+
+    let (documents, mut summary) = match (&args.results, args.stream) {
+        (Some(path), true) => {
+            let totals = results::totals(path)?;
+            (totals.documents(), totals.summary())
+        }
+        _ => (measures.len(), Summary::from_measures(&measures)),
+    };
+    summary.partial = interrupt::requested();
+    let baseline = match &args.baseline {
+        Some(path) => Some(RunResult::load_summary(path)?),
+        None => None,
+    };
+    let color = !args.no_color && io::stdout().is_terminal();
+    summary.print(io::stdout().lock(), baseline.as_ref(), color)?;
+    for breakdown in &args.breakdown {
+        println!();
+        print_breakdown(args, *breakdown, &measures)?;
+    }
+    if let Some(path) = &args.summary_out {
+        RunResult::new(summary).save(path)?;
+    }
+    #[cfg(feature = "alloc-stats")]
+    if let Some(path) = &args.memory_out {
+        alloc_stats::save(path)?;
+    }
+    if let Some(path) = &args.report {
+        let row = report::Row {
+            dataset: DatasetSpec::of(args).name,
+            extractor: first_extractor(args).to_string(),
+            documents,
+            summary,
+            spread: None,
+            p_values: None,
+        };
+        report::save_matrix(path, &[row], args.report_format, None)?;
+    }
+
+    Ok(())
+}
+
+fn print_breakdown(
+    args: &EvalArgs,
+    breakdown: Breakdown,
+    measures: &[DocumentResult],
+) -> io::Result<()> {
+    let out = io::stdout().lock();
+    match breakdown {
+        Breakdown::Pos => breakdown::print_pos(out, measures),
+        Breakdown::Length => {
+            breakdown::print_buckets(out, "tokens", &args.length_buckets, measures, |m| m.tokens)
+        }
+        Breakdown::References => {
+            breakdown::print_buckets(out, "keyphrases", &args.reference_buckets, measures, |m| {
+                m.keyphrases
+            })
+        }
+        Breakdown::Position => breakdown::print_positions(out, measures),
+        Breakdown::Bio => breakdown::print_bio(out, measures),
+        Breakdown::Correlation => correlation::print_relevance(out, measures),
+        Breakdown::Calibration => breakdown::print_calibration(out, measures),
+        Breakdown::Groups => breakdown::print_groups(out, measures),
+        Breakdown::Ranking => breakdown::print_ranking(out, measures),
+        Breakdown::Adaptive => breakdown::print_adaptive(out, measures, args.top_k),
+        Breakdown::Presence => breakdown::print_presence(out, measures),
+        Breakdown::Coverage => breakdown::print_coverage(out, measures),
+        Breakdown::Rouge => rouge::print_rouge(out, measures),
+    }
+}
+
+/// the extractor of runs that evaluate a single one
+pub fn first_extractor(args: &EvalArgs) -> Extractor {
+    args.extractors.first().copied().unwrap_or(Extractor::Tfidf)
+}
+
+/// args with the output files of the run in a new run directory
+/// with --run-dir, unless it is a --dry-run
+pub fn in_run_dir(args: &EvalArgs) -> io::Result<EvalArgs> {
+    let mut args = args.clone();
+    if let (Some(label), false) = (&args.run_dir, args.dry_run) {
+        let dir = run_dir::create(label)?;
+        logging::tee(dir.join(run_dir::LOG))?;
+        info!(path = %dir.display(), "created run directory");
+        args.manifest = Some(dir.join(run_dir::MANIFEST));
+        args.summary_out = Some(dir.join(run_dir::SUMMARY));
+        args.results = Some(dir.join(run_dir::RESULTS));
+        args.keywords = Some(dir.join(run_dir::KEYWORDS));
+        #[cfg(feature = "alloc-stats")]
+        {
+            args.memory_out = Some(dir.join(run_dir::MEMORY));
+        }
+        #[cfg(feature = "profile")]
+        if args.profile {
+            args.flamegraph = Some(dir.join(run_dir::FLAMEGRAPH));
+        }
+    }
+    Ok(args)
+}
+
+/// the datasets of the config file selected by --only,
+/// or --dataset and --references without any
+fn specs(args: &EvalArgs) -> io::Result<Vec<DatasetSpec>> {
+    if let Some(name) = args
+        .only
+        .iter()
+        .find(|name| !args.datasets.iter().any(|spec| spec.name == **name))
+    {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("--only: no dataset named {} in the config file", name),
+        ));
+    }
+    if args.datasets.is_empty() {
+        return Ok(vec![DatasetSpec::of(args)]);
+    }
+    Ok(args
+        .datasets
+        .iter()
+        .filter(|spec| args.only.is_empty() || args.only.contains(&spec.name))
+        .cloned()
+        .collect())
+}
+
+/// runs the whole evaluation for every configured dataset and extractor
+/// and prints one row for each pair
+fn eval_table(args: &EvalArgs, reading: &Reading) -> io::Result<()> {
+    let specs = specs(args)?;
+    if let Some(baseline) = args.baseline_extractor {
+        if !args.extractors.contains(&baseline) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "--baseline-extractor {} is not one of --extractors",
+                    baseline
+                ),
+            ));
+        }
+    }
+    if !args.seeds.is_empty() && !args.extractors.iter().any(|e| e.is_stochastic()) {
+        warn!("none of the extractors is random, --seeds has no effect");
+    }
+    let mut rows = vec![];
+    let mut agreements = vec![];
+    for spec in &specs {
+        let mut runs = vec![];
+        for extractor in &args.extractors {
+            if interrupt::requested() {
+                break;
+            }
+            info!(dataset = %spec.name, extractor = %extractor, "evaluating dataset");
+            let mut label = vec![];
+            if !args.datasets.is_empty() {
+                label.push(spec.name.clone());
+            }
+            if args.extractors.len() > 1 {
+                label.push(extractor.to_string());
+            }
+            let seeds = if extractor.is_stochastic() && !args.seeds.is_empty() {
+                args.seeds.iter().copied().map(Some).collect()
+            } else {
+                vec![None]
+            };
+            let mut summaries = vec![];
+            // the first seed stands for the extractor in the agreements and significance tests
+            let mut first = None;
+            for seed in seeds {
+                let mut variant = args.clone();
+                let mut label = label.clone();
+                if let Some(seed) = seed {
+                    // resolved first so `[extractors.random]` doesn't override the seed
+                    variant.scoring = variant.scoring.for_extractor(*extractor)?;
+                    variant.scoring.seed = seed;
+                    label.push(format!("seed{}", seed));
+                    info!(extractor = %extractor, seed, "evaluating seed");
+                }
+                let measures = run_dataset(
+                    &variant,
+                    &spec.dataset,
+                    &spec.references,
+                    reading,
+                    *extractor,
+                    Some(&label.join(".")),
+                )?;
+                let mut summary = Summary::from_measures(&measures);
+                summary.partial = interrupt::requested();
+                summaries.push(summary);
+                first.get_or_insert(measures);
+            }
+            let measures = first.unwrap_or_default();
+            let (summary, spread) = match summaries.as_slice() {
+                [summary] => (*summary, None),
+                summaries => {
+                    let (summary, spread) = report::across_seeds(summaries);
+                    (summary, Some(spread))
+                }
+            };
+            rows.push(report::Row {
+                dataset: spec.name.clone(),
+                extractor: extractor.to_string(),
+                documents: measures.len(),
+                summary,
+                spread,
+                p_values: None,
+            });
+            runs.push((extractor.to_string(), measures));
+        }
+        if interrupt::requested() {
+            // the runs of the dataset cover different documents, nothing to compare
+            break;
+        }
+        if let Some(baseline) = args.baseline_extractor {
+            let baseline = baseline.to_string();
+            let (_, expected) = runs
+                .iter()
+                .find(|(e, _)| *e == baseline)
+                .expect("the baseline is one of the extractors");
+            let spec_rows = rows.len() - runs.len();
+            for (row, (extractor, measures)) in rows[spec_rows..].iter_mut().zip(&runs) {
+                if *extractor != baseline {
+                    row.p_values = Some(significance::test(
+                        expected,
+                        measures,
+                        args.significance_trials,
+                        args.scoring.seed,
+                    ));
+                }
+            }
+        }
+        for (i, (first, a)) in runs.iter().enumerate() {
+            for (second, b) in &runs[i + 1..] {
+                agreements.push(correlation::agreement(&spec.name, (first, a), (second, b)));
+            }
+        }
+    }
+    if rows.iter().any(|r| r.summary.partial) {
+        println!("{}", report::PARTIAL);
+    }
+    report::print_table(io::stdout().lock(), &rows)?;
+    if let Some(baseline) = args.baseline_extractor {
+        println!("{}", report::legend(&baseline.to_string()));
+    }
+    if !agreements.is_empty() {
+        println!();
+        correlation::print_agreements(io::stdout().lock(), &agreements)?;
+    }
+    #[cfg(feature = "alloc-stats")]
+    if let Some(path) = &args.memory_out {
+        alloc_stats::save(path)?;
+    }
+    if let Some(path) = &args.report {
+        let baseline = args.baseline_extractor.map(|e| e.to_string());
+        report::save_matrix(path, &rows, args.report_format, baseline.as_deref())?;
+    }
+    if let Some(path) = &args.summary_out {
+        RunResult::with_rows(rows).save(path)?;
+    }
+    Ok(())
+}
+
+/// a named preprocessed document with the surface forms of its terms for --keywords
+type Loaded = (String, Arc<HulthDocument>, Option<Arc<Surfaces>>);
+
+/// fits and evaluates one dataset, label is inserted into
+/// the names of the files written for the run
+pub fn run_dataset(
+    args: &EvalArgs,
+    dataset: &Path,
+    references: &Path,
+    reading: &Reading,
+    extractor: Extractor,
+    label: Option<&str>,
+) -> io::Result<Vec<DocumentResult>> {
+    let preprocessing = &args.preprocessing;
+    let normalizer = preprocessing.normalizer()?;
+    let phase = |name: &str| match label {
+        Some(label) if !label.is_empty() => format!("{}.{}", label, name),
+        _ => name.to_string(),
+    };
+    #[cfg(feature = "async")]
+    let local_only = args.cache_dir.is_some() || args.async_io;
+    #[cfg(not(feature = "async"))]
+    let local_only = args.cache_dir.is_some();
+    if local_only && !source::is_local(dataset) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "{}: --cache-dir and --async-io read a dataset directory",
+                dataset.display()
+            ),
+        ));
+    }
+    let started = Instant::now();
+    let heap = alloc_stats::start();
+    let mut interner = Interner::new();
+    let surfaces = args.keywords.is_some();
+    let (corpus, docs) = if args.low_memory {
+        (
+            None,
+            corpus::load_compact(dataset, reading, &normalizer, &mut interner)?.boxed(),
+        )
+    } else {
+        let cache = match &args.cache_dir {
+            Some(_)
+                if preprocessing
+                    .lemmatize
+                    .as_ref()
+                    .is_some_and(Lemmatizer::is_external) =>
+            {
+                warn!("not caching, the answers of an external lemmatizer may change between runs");
+                None
+            }
+            Some(dir) => Some(Cache::open(dir, preprocessing, reading)?),
+            None => None,
+        };
+        #[cfg(feature = "async")]
+        let corpus = if let Some(cache) = &cache {
+            corpus::load_dir_cached(
+                dataset,
+                reading,
+                &normalizer,
+                &mut interner,
+                cache,
+                surfaces,
+            )?
+        } else if args.async_io {
+            corpus::load_dir_async(
+                dataset,
+                reading,
+                args.io_concurrency,
+                &normalizer,
+                &mut interner,
+                surfaces,
+            )?
+        } else {
+            corpus::load_dir(dataset, reading, &normalizer, &mut interner, surfaces)?
+        };
+        #[cfg(not(feature = "async"))]
+        let corpus = match &cache {
+            Some(cache) => corpus::load_dir_cached(
+                dataset,
+                reading,
+                &normalizer,
+                &mut interner,
+                cache,
+                surfaces,
+            )?,
+            None => corpus::load_dir(dataset, reading, &normalizer, &mut interner, surfaces)?,
+        };
+        let model = Model::new(corpus, preprocessing.clone());
+        if let Some(path) = &args.save_model {
+            let path = labeled(path, label);
+            model.save(&path)?;
+            info!(path = %path.display(), "saved model");
+        }
+        let docs = match args.granularity {
+            Granularity::Document => model.corpus.boxed(),
+            Granularity::Sentence => model.corpus.boxed_sentences(),
+        };
+        (Some(model.corpus), docs)
+    };
+    info!(
+        documents = docs.len(),
+        strings = interner.len(),
+        dataset = %dataset.display(),
+        low_memory = args.low_memory,
+        elapsed = ?started.elapsed(),
+        "loaded corpus"
+    );
+    alloc_stats::finish(heap, &phase("load"));
+
+    let started = Instant::now();
+    let heap = alloc_stats::start();
+    let mut tfidf = Tfidf::new(docs.as_slice());
+    tfidf.fit_transform()?;
+    info!(elapsed = ?started.elapsed(), "fitted corpus");
+    alloc_stats::finish(heap, &phase("fit"));
+    let scoring = args.scoring.for_extractor(extractor)?;
+    let reranker = scoring.reranker(extractor, || DocumentFrequencies::new(&docs))?;
+    let frequencies = if args.weighting.is_custom() {
+        Some(DocumentFrequencies::new(&docs))
+    } else {
+        None
+    };
+
+    let keywords: HulthDocumentKeywords =
+        serde_json::from_reader(std::fs::File::open(references)?)?;
+
+    #[cfg(feature = "tui")]
+    let mut dashboard = if args.tui {
+        Some(tui::Dashboard::start(docs.len())?)
+    } else {
+        None
+    };
+
+    let started = Instant::now();
+    let heap = alloc_stats::start();
+    let source: Box<dyn Iterator<Item = io::Result<Loaded>> + Send + '_> = match &corpus {
+        Some(corpus) => Box::new(corpus.shared_with_surfaces().map(Ok)),
+        None => Box::new(corpus::stream_dir(dataset, reading)?.map(|entry| {
+            entry.map(|(name, original)| {
+                let doc = normalizer.apply(&original);
+                let kept = surfaces.then(|| Arc::new(spans::surface_forms(&original, &doc)));
+                (name, Arc::new(doc), kept)
+            })
+        })),
+    };
+    let (finished, mut checkpoint) = match args.results.as_ref().map(|p| labeled(p, label)) {
+        Some(path) if args.resume => {
+            let (finished, checkpoint) = Checkpoint::resume(path)?;
+            info!(documents = finished.len(), "resuming run");
+            (finished, Some(checkpoint))
+        }
+        Some(path) => (vec![], Some(Checkpoint::create(path)?)),
+        None => (vec![], None),
+    };
+    let skip = finished
+        .iter()
+        .map(|m| m.document.as_str())
+        .collect::<HashSet<_>>();
+    let source = source.filter(|entry| match entry {
+        Ok((name, _, _)) => {
+            !skip.contains(name.as_str())
+                && args.shard.is_none_or(|s| s.contains(name))
+                && args.changed.as_ref().is_none_or(|c| c.contains(name))
+        }
+        Err(_) => true,
+    });
+    // documents in flight at a Ctrl-C are still evaluated and written
+    let source = source.take_while(|_| !interrupt::requested());
+    let synonyms = args.matching.synonym_file()?;
+    let jobs = args.jobs.unwrap_or_else(|| {
+        std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1)
+    });
+    let mut keywords_out = match &args.keywords {
+        Some(path) => Some(BufWriter::new(File::create(labeled(path, label))?)),
+        None => None,
+    };
+    let (mut evaluated, mut unreachable, mut total) = (0, 0, 0);
+    let measures = pipeline::run(
+        source,
+        |doc| {
+            let scores = granularity::rank_tokens(&tfidf, doc, args.granularity, args.pooling)?;
+            let scores = args.weighting.apply(doc, scores, frequencies.as_ref());
+            let scores = preprocessing.resolve(doc, scores.into_iter().collect());
+            Ok(scoring.rank(extractor, reranker.as_ref(), doc, scores))
+        },
+        &keywords,
+        args.top_k,
+        || {
+            normalizer
+                .clone()
+                .with_references(args.reference_tokenization)
+                .with_synonyms(synonyms.as_ref())
+        },
+        jobs,
+        !args.stream,
+        |measure, surfaces| {
+            debug!(
+                document = %measure.document,
+                precision = measure.metrics.precision,
+                recall = measure.metrics.recall,
+                f1 = measure.metrics.f1,
+                "evaluated document"
+            );
+            evaluated += 1;
+            unreachable += measure.unreachable;
+            total += measure.keyphrases;
+            if let Some(checkpoint) = checkpoint.as_mut() {
+                checkpoint.append(measure)?;
+            }
+            if let Some(writer) = keywords_out.as_mut() {
+                write_keywords(
+                    writer,
+                    measure,
+                    surfaces.as_deref().unwrap_or(&HashMap::new()),
+                )?;
+            }
+            #[cfg(feature = "tui")]
+            if let Some(dashboard) = dashboard.as_mut() {
+                dashboard.update(measure)?;
+            }
+            Ok(())
+        },
+    )?;
+    #[cfg(feature = "tui")]
+    drop(dashboard);
+    info!(elapsed = ?started.elapsed(), "ranked corpus");
+    if interrupt::requested() {
+        warn!(
+            evaluated,
+            documents = docs.len(),
+            "interrupted, evaluated the documents started before"
+        );
+    }
+    alloc_stats::finish(heap, &phase("rank"));
+    if unreachable > 0 {
+        warn!(
+            keyphrases = unreachable,
+            total, "reference keyphrases never occur in their document, see `stats`"
+        );
+    }
+    #[cfg(feature = "parquet")]
+    if let Some(path) = &args.candidates {
+        let path = labeled(path, label);
+        columnar::write_candidates(&path, &measures)?;
+        info!(path = %path.display(), "wrote candidates");
+    }
+    if let (Some(path), Some(mut writer)) = (&args.keywords, keywords_out) {
+        writer.flush()?;
+        info!(path = %labeled(path, label).display(), "wrote keywords");
+    }
+    if args.stream {
+        return Ok(vec![]);
+    }
+
+    Ok(finished.into_iter().chain(measures).collect())
+}
+
+/// the ranked keywords of measure as a line of `rank-batch`
+fn write_keywords<W: Write>(
+    writer: &mut W,
+    measure: &DocumentResult,
+    surfaces: &HashMap<String, String>,
+) -> io::Result<()> {
+    let keywords = measure
+        .ranking
+        .iter()
+        .zip(&measure.candidates)
+        .map(|(term, (score, _))| batch::Keyword::new(term.to_string(), *score, surfaces))
+        .collect();
+    let extraction = batch::Extraction {
+        id: measure.document.clone(),
+        keywords,
+        spans: None,
+    };
+    batch::write_extraction(writer, &extraction)
+}
+
+/// inserts label before the extension of path, `results.jsonl` becomes `results.<label>.jsonl`
+fn labeled(path: &Path, label: Option<&str>) -> PathBuf {
+    let label = match label {
+        Some(label) => label,
+        None => return path.into(),
+    };
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match path.extension() {
+        Some(ext) => format!("{}.{}.{}", stem, label, ext.to_string_lossy()),
+        None => format!("{}.{}", stem, label),
+    };
+    path.with_file_name(name)
+}
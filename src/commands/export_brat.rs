@@ -0,0 +1,84 @@
+//! `export-brat`, exports predicted and reference keywords for review in BRAT
+use crate::corpus::{self, Reading};
+use crate::model::Model;
+use crate::scoring::{Extractor, Scoring};
+use crate::{brat, spans, HulthDocumentKeywords, DEFAULT_DATASET};
+
+use clap::Args;
+use lib_tfidf::{Document, Tfidf};
+
+use std::fs;
+use std::io::{self, BufReader};
+use std::path::PathBuf;
+
+#[derive(Debug, Args, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExportBratArgs {
+    /// model file written by `eval --save-model`
+    #[arg(long)]
+    load_model: PathBuf,
+
+    /// directory containing the Hulth JSON documents to annotate
+    #[arg(long, env = "HULTH_EVAL_DATASET", default_value = DEFAULT_DATASET)]
+    dataset: PathBuf,
+
+    /// also annotates the reference keywords from this file
+    #[arg(long)]
+    references: Option<PathBuf>,
+
+    /// directory the `.txt` and `.ann` files are written to
+    #[arg(long, short)]
+    output: PathBuf,
+
+    /// number of keywords per document
+    #[arg(long, env = "HULTH_EVAL_TOP_K", default_value_t = 10)]
+    top_k: usize,
+
+    /// ranking variant
+    #[arg(long, value_enum, default_value_t = Extractor::Tfidf)]
+    extractor: Extractor,
+
+    #[command(flatten)]
+    #[serde(default)]
+    scoring: Scoring,
+}
+
+/// ranks every document of the dataset and writes its text
+/// and annotations for review in BRAT
+pub fn run(args: &ExportBratArgs, reading: &Reading) -> io::Result<()> {
+    let model = Model::load(&args.load_model)?;
+    let normalizer = model.preprocessing.normalizer()?;
+    let docs = model.corpus.boxed();
+    let mut tfidf = Tfidf::new(docs.as_slice());
+    tfidf.fit_transform()?;
+    info!(documents = docs.len(), "fitted model");
+    let scoring = args.scoring.for_extractor(args.extractor)?;
+    let reranker = scoring.reranker(args.extractor, || model.frequencies.clone())?;
+
+    let keywords: Option<HulthDocumentKeywords> = match &args.references {
+        Some(path) => Some(serde_json::from_reader(BufReader::new(fs::File::open(
+            path,
+        )?))?),
+        None => None,
+    };
+    fs::create_dir_all(&args.output)?;
+    let mut exported = 0;
+    for entry in corpus::stream_dir(&args.dataset, reading)? {
+        let (name, original) = entry?;
+        let doc = normalizer.apply(&original);
+        let scores = tfidf.rank_tokens(&doc.get_content())?.into_iter().collect();
+        let scores = model.preprocessing.resolve(&doc, scores);
+        let mut ranked = scoring.rank(args.extractor, reranker.as_ref(), &doc, scores);
+        ranked.truncate(args.top_k);
+        let predicted = spans::spans(&original, None, &doc, &ranked);
+        let references = match keywords.as_ref().and_then(|k| k.get(&name)) {
+            Some(keyphrases) => brat::reference_spans(&original, keyphrases),
+            None => vec![],
+        };
+        let text = brat::text(&original);
+        brat::write(&args.output, &name, &text, &predicted, &references)?;
+        exported += 1;
+    }
+    info!(documents = exported, output = %args.output.display(), "exported annotations");
+    Ok(())
+}
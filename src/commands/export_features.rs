@@ -0,0 +1,73 @@
+//! `export-features`, exports the features of every ranked candidate as CSV
+use super::for_each_candidate;
+use crate::corpus::Reading;
+use crate::features;
+use crate::model::Model;
+use crate::scoring::{Extractor, Scoring};
+use crate::{DEFAULT_DATASET, DEFAULT_REFERENCES};
+
+use clap::Args;
+
+use std::fs;
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+
+#[derive(Debug, Args, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExportFeaturesArgs {
+    /// model file written by `eval --save-model`
+    #[arg(long)]
+    load_model: PathBuf,
+
+    /// directory containing the Hulth JSON documents
+    #[arg(long, env = "HULTH_EVAL_DATASET", default_value = DEFAULT_DATASET)]
+    dataset: PathBuf,
+
+    /// JSON file mapping document names to their reference keywords, the gold labels
+    #[arg(long, env = "HULTH_EVAL_REFERENCES", default_value = DEFAULT_REFERENCES)]
+    references: PathBuf,
+
+    /// CSV file the features are written to, stdout if omitted
+    #[arg(long, short)]
+    output: Option<PathBuf>,
+
+    /// exports only the best k candidates of every document instead of all
+    #[arg(long)]
+    top_k: Option<usize>,
+
+    /// ranking variant
+    #[arg(long, value_enum, default_value_t = Extractor::Tfidf)]
+    extractor: Extractor,
+
+    #[command(flatten)]
+    #[serde(default)]
+    scoring: Scoring,
+}
+
+/// ranks every document of the dataset and writes one CSV row per candidate,
+/// labelled 1 if it matches a reference keyword
+pub fn run(args: &ExportFeaturesArgs, reading: &Reading) -> io::Result<()> {
+    let model = Model::load(&args.load_model)?;
+    let mut output: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(BufWriter::new(fs::File::create(path)?)),
+        None => Box::new(BufWriter::new(io::stdout())),
+    };
+    features::write_header(&mut output)?;
+    let mut rows = 0;
+    for_each_candidate(
+        &model,
+        &args.dataset,
+        &args.references,
+        reading,
+        args.extractor,
+        &args.scoring,
+        args.top_k,
+        |name, term, features, label| {
+            rows += 1;
+            features::write_row(&mut output, name, term, features, label)
+        },
+    )?;
+    output.flush()?;
+    info!(rows, "exported features");
+    Ok(())
+}
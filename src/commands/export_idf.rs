@@ -0,0 +1,30 @@
+//! `export-idf`, exports the idf table of a saved model
+use crate::idf_table::IdfTable;
+use crate::model::Model;
+
+use clap::Args;
+
+use std::io;
+use std::path::PathBuf;
+
+#[derive(Debug, Args, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExportIdfArgs {
+    /// model file written by `eval --save-model`
+    #[arg(long)]
+    load_model: PathBuf,
+
+    /// JSON file the table is written to
+    #[arg(long, short)]
+    output: PathBuf,
+}
+
+/// writes the idf table of the model for ranking without the corpus,
+/// see `load_idf_table` and `rank_text` of the WASM build
+pub fn run(args: &ExportIdfArgs) -> io::Result<()> {
+    let model = Model::load(&args.load_model)?;
+    let table = IdfTable::new(&model);
+    table.save(&args.output)?;
+    info!(path = %args.output.display(), "saved idf table");
+    Ok(())
+}
@@ -0,0 +1,51 @@
+//! `export-matrix`, exports the tf-idf matrix of a saved model
+use crate::matrix;
+use crate::model::Model;
+
+use clap::Args;
+use lib_tfidf::{Document, Tfidf};
+
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+#[derive(Debug, Args, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExportMatrixArgs {
+    /// model file written by `eval --save-model`
+    #[arg(long)]
+    load_model: PathBuf,
+
+    /// directory the matrix, `vocabulary.txt` and `documents.txt` are written to
+    #[arg(long, short)]
+    output: PathBuf,
+
+    #[arg(long, value_enum, default_value_t = matrix::MatrixFormat::Mtx)]
+    format: matrix::MatrixFormat,
+}
+
+/// scores every document of the model against the model itself,
+/// one matrix row per document in corpus order
+pub fn run(args: &ExportMatrixArgs) -> io::Result<()> {
+    let model = Model::load(&args.load_model)?;
+    let docs = model.corpus.boxed();
+    let mut tfidf = Tfidf::new(docs.as_slice());
+    tfidf.fit_transform()?;
+    info!(documents = docs.len(), "fitted model");
+
+    let mut rows = vec![];
+    for (name, doc) in model.corpus.shared() {
+        let scores = tfidf.rank_tokens(&doc.get_content())?;
+        rows.push((name, scores.into_iter().collect()));
+    }
+    let (documents, vocabulary, csr) = matrix::build(rows);
+    fs::create_dir_all(&args.output)?;
+    matrix::write(&args.output, args.format, &documents, &vocabulary, &csr)?;
+    info!(
+        documents = documents.len(),
+        terms = vocabulary.len(),
+        output = %args.output.display(),
+        "exported matrix"
+    );
+    Ok(())
+}
@@ -0,0 +1,227 @@
+//! `gen-corpus`, writes a synthetic dataset with references for stress tests.
+//! the words of a document are drawn from a Zipf distribution over the
+//! vocabulary, its reference keyphrases are planted words found nowhere else
+use crate::rng::Rng;
+use crate::{HulthDocument, HulthToken, Sentence};
+
+use clap::Args;
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+const CONSONANTS: &[u8] = b"bdfgklmnprstvz";
+const VOWELS: &[u8] = b"aeiou";
+
+#[derive(Debug, Args, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GenCorpusArgs {
+    /// the directory the documents are written to as `<n>.json`
+    #[arg(long, short)]
+    output: PathBuf,
+
+    /// JSON file the planted keyphrases are written to, in the format of --references
+    #[arg(long)]
+    references: PathBuf,
+
+    /// number of documents
+    #[arg(long, default_value_t = 100)]
+    documents: usize,
+
+    /// sentences per document
+    #[arg(long, default_value_t = 10)]
+    sentences: usize,
+
+    /// words per sentence, not counting the planted keyphrases
+    #[arg(long, default_value_t = 20)]
+    sentence_length: usize,
+
+    /// number of distinct words the documents are drawn from
+    #[arg(long, default_value_t = 5000)]
+    vocabulary: usize,
+
+    /// exponent s of the Zipf distribution, the word of rank r is drawn
+    /// with a probability proportional to 1 / r^s, 0 draws all alike
+    #[arg(long, default_value_t = 1.0)]
+    zipf: f64,
+
+    /// keyphrases planted in every document
+    #[arg(long, default_value_t = 3)]
+    keywords: usize,
+
+    /// words per planted keyphrase
+    #[arg(long, default_value_t = 2)]
+    keyword_length: usize,
+
+    /// occurrences of every planted keyphrase in its document
+    #[arg(long, default_value_t = 3)]
+    repeats: usize,
+
+    /// the same seed and options write the same corpus
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+}
+
+/// draws word ranks by the cumulative probabilities of the Zipf distribution
+struct Zipf {
+    cumulative: Vec<f64>,
+}
+
+impl Zipf {
+    fn new(n: usize, s: f64) -> Self {
+        let mut sum = 0f64;
+        let cumulative = (1..=n)
+            .map(|rank| {
+                sum += 1f64 / (rank as f64).powf(s);
+                sum
+            })
+            .collect();
+        Zipf { cumulative }
+    }
+
+    fn sample(&self, rng: &mut Rng) -> usize {
+        let total = self.cumulative.last().copied().unwrap_or_default();
+        let u = rng.float() * total;
+        self.cumulative
+            .partition_point(|c| *c <= u)
+            .min(self.cumulative.len() - 1)
+    }
+}
+
+pub fn run(args: &GenCorpusArgs) -> io::Result<()> {
+    check(args)?;
+    fs::create_dir_all(&args.output)?;
+    let zipf = Zipf::new(args.vocabulary, args.zipf);
+    let mut rng = Rng::new(args.seed);
+    let mut references = BTreeMap::<_, Vec<Vec<String>>>::new();
+    let mut tokens = 0;
+    for n in 0..args.documents {
+        let name = (n + 1).to_string();
+        // planted words are numbered after the vocabulary, unique to their keyphrase
+        let first = args.vocabulary + n * args.keywords * args.keyword_length;
+        let keyphrases = (0..args.keywords)
+            .map(|k| {
+                (0..args.keyword_length)
+                    .map(|i| word(first + k * args.keyword_length + i))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let document = document(args, &zipf, &keyphrases, &mut rng);
+        tokens += document
+            .sentences
+            .iter()
+            .map(|s| s.tokens.len())
+            .sum::<usize>();
+        let path = args.output.join(format!("{}.json", name));
+        let mut w = BufWriter::new(fs::File::create(path)?);
+        serde_json::to_writer(&mut w, &document)?;
+        w.flush()?;
+        references.insert(name, vec![keyphrases.iter().map(|k| k.join(" ")).collect()]);
+    }
+    let mut w = BufWriter::new(fs::File::create(&args.references)?);
+    serde_json::to_writer(&mut w, &references)?;
+    w.flush()?;
+    info!(
+        documents = args.documents,
+        tokens,
+        output = %args.output.display(),
+        "generated corpus"
+    );
+    Ok(())
+}
+
+fn check(args: &GenCorpusArgs) -> io::Result<()> {
+    let problem = if args.vocabulary == 0 {
+        "--vocabulary must be at least 1"
+    } else if !(args.zipf.is_finite() && args.zipf >= 0f64) {
+        "--zipf must be a number of at least 0"
+    } else if args.keywords > 0 && (args.keyword_length == 0 || args.repeats == 0) {
+        "--keyword-length and --repeats must be at least 1 to plant keyphrases"
+    } else if args.keywords > 0 && args.sentences == 0 {
+        "--sentences must be at least 1 to plant keyphrases"
+    } else {
+        return Ok(());
+    };
+    Err(io::Error::new(io::ErrorKind::InvalidInput, problem))
+}
+
+/// the sentences of Zipf distributed words with every keyphrase inserted
+/// --repeats times at random places, each sentence ending in a full stop
+fn document(
+    args: &GenCorpusArgs,
+    zipf: &Zipf,
+    keyphrases: &[Vec<String>],
+    rng: &mut Rng,
+) -> HulthDocument {
+    let mut sentences = (0..args.sentences)
+        .map(|_| {
+            (0..args.sentence_length)
+                .map(|_| word(zipf.sample(rng)))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    for keyphrase in keyphrases {
+        for _ in 0..args.repeats {
+            let sentence = &mut sentences[rng.below(args.sentences)];
+            let at = rng.below(sentence.len() + 1);
+            sentence.splice(at..at, keyphrase.iter().cloned());
+        }
+    }
+    let mut offset = 0;
+    let sentences = sentences
+        .into_iter()
+        .map(|words| {
+            let mut tokens = words
+                .into_iter()
+                .map(|word| {
+                    let t = token(&word, "NN", offset);
+                    offset = t.offset_end + 1;
+                    t
+                })
+                .collect::<Vec<_>>();
+            // the full stop follows the last word without a space
+            let begin = if tokens.is_empty() {
+                offset
+            } else {
+                offset - 1
+            };
+            let stop = token(".", ".", begin);
+            offset = stop.offset_end + 1;
+            tokens.push(stop);
+            Sentence { tokens }
+        })
+        .collect();
+    HulthDocument { sentences }
+}
+
+fn token(word: &str, pos: &str, offset_begin: i64) -> HulthToken {
+    let word: Arc<str> = word.into();
+    HulthToken {
+        lemma: word.clone(),
+        offset_begin,
+        offset_end: offset_begin + word.len() as i64,
+        word,
+        pos: pos.into(),
+    }
+}
+
+/// a pronounceable word for every number, syllables of a consonant and a vowel
+/// as the digits of n, at least two of them
+fn word(n: usize) -> String {
+    let base = CONSONANTS.len() * VOWELS.len();
+    let mut syllables = vec![];
+    let mut n = n;
+    while n > 0 || syllables.len() < 2 {
+        let s = n % base;
+        syllables.push([CONSONANTS[s / VOWELS.len()], VOWELS[s % VOWELS.len()]]);
+        n /= base;
+    }
+    syllables
+        .iter()
+        .rev()
+        .flatten()
+        .map(|&b| b as char)
+        .collect()
+}
@@ -0,0 +1,69 @@
+//! `merge`, combines per-document result files
+use crate::report::Summary;
+use crate::results::{self, RunResult};
+
+use clap::Args;
+
+use std::collections::HashMap;
+use std::io::{self, IsTerminal};
+use std::path::PathBuf;
+
+#[derive(Debug, Args, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MergeArgs {
+    /// result files written by `eval --results`
+    #[arg(required = true)]
+    files: Vec<PathBuf>,
+
+    /// writes the merged per-document results to this file
+    #[arg(long)]
+    results: Option<PathBuf>,
+
+    /// writes the aggregate measures as JSON
+    #[arg(long)]
+    summary_out: Option<PathBuf>,
+
+    /// disables colors in the summary
+    #[arg(long)]
+    no_color: bool,
+}
+
+/// aggregates the per-document results of several runs.
+/// a document appearing in more than one file is an error,
+/// since its measures would be counted twice
+pub fn run(args: &MergeArgs) -> io::Result<()> {
+    let mut seen = HashMap::new();
+    let mut measures = vec![];
+    for path in &args.files {
+        for measure in results::read(path)? {
+            if let Some(previous) = seen.insert(measure.document.clone(), path) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "document {} is in both {} and {}",
+                        measure.document,
+                        previous.display(),
+                        path.display()
+                    ),
+                ));
+            }
+            measures.push(measure);
+        }
+    }
+    info!(
+        files = args.files.len(),
+        documents = measures.len(),
+        "merged results"
+    );
+
+    if let Some(path) = &args.results {
+        results::write(path, &measures)?;
+    }
+    let summary = Summary::from_measures(&measures);
+    let color = !args.no_color && io::stdout().is_terminal();
+    summary.print(io::stdout().lock(), None, color)?;
+    if let Some(path) = &args.summary_out {
+        RunResult::new(summary).save(path)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,103 @@
+//! `rank`, ranks a single document from stdin
+use crate::commands;
+use crate::corpus::Reading;
+use crate::granularity::{self, Granularity, Pooling};
+use crate::model::Model;
+use crate::scoring::{Extractor, Scoring};
+use crate::weighting::Weighting;
+use crate::{spans, text};
+
+use clap::Args;
+use lib_tfidf::Tfidf;
+
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Args, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RankArgs {
+    /// model file written by `eval --save-model`
+    #[arg(long)]
+    load_model: PathBuf,
+
+    /// number of keywords to print
+    #[arg(long, env = "HULTH_EVAL_TOP_K", default_value_t = 10)]
+    top_k: usize,
+
+    /// prints every occurrence of the keywords as a JSON character span per line
+    #[arg(long)]
+    spans: bool,
+
+    /// prints the keywords in their most frequent form in the document,
+    /// as written instead of lowercased or stemmed
+    #[arg(long, conflicts_with = "spans")]
+    surface: bool,
+
+    /// ranking variant
+    #[arg(long, value_enum, default_value_t = Extractor::Tfidf)]
+    extractor: Extractor,
+
+    #[command(flatten)]
+    #[serde(default)]
+    scoring: Scoring,
+
+    #[command(flatten)]
+    #[serde(default)]
+    weighting: Weighting,
+
+    /// the units the model is fitted on, as with `eval --granularity`
+    #[arg(long, value_enum, default_value_t = Granularity::Document)]
+    #[serde(default)]
+    granularity: Granularity,
+
+    /// how the sentence scores of a term are combined with --granularity sentence
+    #[arg(long, value_enum, default_value_t = Pooling::Sum)]
+    #[serde(default)]
+    pooling: Pooling,
+}
+
+/// ranks the document on stdin against the model and prints
+/// the top_k keywords as `term<TAB>score`, best first.
+/// Hulth JSON is checked for missing token fields like dataset files
+pub fn run(args: &RankArgs, reading: &Reading) -> io::Result<()> {
+    let mut input = String::new();
+    io::stdin().read_to_string(&mut input)?;
+    let original = reading.check(Path::new("stdin"), || text::parse_document(&input))?;
+
+    let model = Model::load(&args.load_model)?;
+    let normalizer = model.preprocessing.normalizer()?;
+    let doc = normalizer.apply(&original);
+    let (docs, frequencies) = commands::units(&model, args.granularity);
+    let mut tfidf = Tfidf::new(docs.as_slice());
+    tfidf.fit_transform()?;
+    info!(documents = docs.len(), "fitted model");
+    let scoring = args.scoring.for_extractor(args.extractor)?;
+    let reranker = scoring.reranker(args.extractor, || frequencies.clone().into_owned())?;
+
+    let scores = granularity::rank_tokens(&tfidf, &doc, args.granularity, args.pooling)?;
+    let scores = args.weighting.apply(&doc, scores, Some(&frequencies));
+    let scores = model
+        .preprocessing
+        .resolve(&doc, scores.into_iter().collect());
+    let mut ranked = scoring.rank(args.extractor, reranker.as_ref(), &doc, scores);
+    ranked.truncate(args.top_k);
+    if args.spans {
+        let source = Some(input.as_str()).filter(|input| !text::is_json(input));
+        let mut out = io::stdout().lock();
+        for span in spans::spans(&original, source, &doc, &ranked) {
+            serde_json::to_writer(&mut out, &span)?;
+            writeln!(out)?;
+        }
+        return Ok(());
+    }
+    let surfaces = if args.surface {
+        spans::surface_forms(&original, &doc)
+    } else {
+        HashMap::new()
+    };
+    for (term, score) in &ranked {
+        println!("{}\t{}", surfaces.get(term).unwrap_or(term), score);
+    }
+    Ok(())
+}
@@ -0,0 +1,123 @@
+//! `rank-batch`, ranks the documents of a JSONL file
+use crate::batch;
+use crate::commands;
+use crate::corpus::Reading;
+use crate::granularity::{self, Granularity, Pooling};
+use crate::model::Model;
+use crate::scoring::{Extractor, Scoring};
+use crate::spans;
+use crate::weighting::Weighting;
+
+use clap::Args;
+use lib_tfidf::Tfidf;
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::PathBuf;
+
+#[derive(Debug, Args, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RankBatchArgs {
+    /// model file written by `eval --save-model`
+    #[arg(long)]
+    load_model: PathBuf,
+
+    /// JSONL file with one document per line, `-` reads stdin
+    input: PathBuf,
+
+    /// JSONL file the extractions are written to, stdout if omitted
+    #[arg(long, short)]
+    output: Option<PathBuf>,
+
+    /// number of keywords per document
+    #[arg(long, env = "HULTH_EVAL_TOP_K", default_value_t = 10)]
+    top_k: usize,
+
+    /// adds every occurrence of the keywords as character spans to the extractions
+    #[arg(long)]
+    spans: bool,
+
+    /// adds the most frequent form of each keyword in the document
+    /// where it differs from the term, see `rank --surface`
+    #[arg(long)]
+    surface: bool,
+
+    /// ranking variant
+    #[arg(long, value_enum, default_value_t = Extractor::Tfidf)]
+    extractor: Extractor,
+
+    #[command(flatten)]
+    #[serde(default)]
+    scoring: Scoring,
+
+    #[command(flatten)]
+    #[serde(default)]
+    weighting: Weighting,
+
+    /// the units the model is fitted on, as with `eval --granularity`
+    #[arg(long, value_enum, default_value_t = Granularity::Document)]
+    #[serde(default)]
+    granularity: Granularity,
+
+    /// how the sentence scores of a term are combined with --granularity sentence
+    #[arg(long, value_enum, default_value_t = Pooling::Sum)]
+    #[serde(default)]
+    pooling: Pooling,
+}
+
+/// ranks every document of the input file and writes
+/// one extraction per line, in input order
+pub fn run(args: &RankBatchArgs, reading: &Reading) -> io::Result<()> {
+    let model = Model::load(&args.load_model)?;
+    let normalizer = model.preprocessing.normalizer()?;
+    let (docs, frequencies) = commands::units(&model, args.granularity);
+    let mut tfidf = Tfidf::new(docs.as_slice());
+    tfidf.fit_transform()?;
+    info!(documents = docs.len(), "fitted model");
+    let scoring = args.scoring.for_extractor(args.extractor)?;
+    let reranker = scoring.reranker(args.extractor, || frequencies.clone().into_owned())?;
+
+    let input: Box<dyn BufRead> = if args.input.as_os_str() == "-" {
+        Box::new(BufReader::new(io::stdin()))
+    } else {
+        Box::new(BufReader::new(fs::File::open(&args.input)?))
+    };
+    let mut output: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(BufWriter::new(fs::File::create(path)?)),
+        None => Box::new(BufWriter::new(io::stdout())),
+    };
+
+    batch::for_each_document(input, reading, |id, original, source| {
+        let doc = normalizer.apply(&original);
+        let scores = granularity::rank_tokens(&tfidf, &doc, args.granularity, args.pooling)?;
+        let scores = args.weighting.apply(&doc, scores, Some(&frequencies));
+        let scores = model
+            .preprocessing
+            .resolve(&doc, scores.into_iter().collect());
+        let mut ranked = scoring.rank(args.extractor, reranker.as_ref(), &doc, scores);
+        debug!(document = %id, ranked = ranked.len(), "ranked document");
+        ranked.truncate(args.top_k);
+        let spans = if args.spans {
+            Some(spans::spans(&original, source.as_deref(), &doc, &ranked))
+        } else {
+            None
+        };
+        let surfaces = if args.surface {
+            spans::surface_forms(&original, &doc)
+        } else {
+            HashMap::new()
+        };
+        let keywords = ranked
+            .into_iter()
+            .map(|(term, score)| batch::Keyword::new(term, score, &surfaces))
+            .collect();
+        let extraction = batch::Extraction {
+            id,
+            keywords,
+            spans,
+        };
+        batch::write_extraction(&mut output, &extraction)
+    })?;
+    output.flush()
+}
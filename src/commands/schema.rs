@@ -0,0 +1,24 @@
+//! `schema`, the JSON Schema of the dataset documents
+use crate::json_schema;
+
+use clap::Args;
+
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+#[derive(Debug, Args, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SchemaArgs {
+    /// file to write the schema to, stdout if omitted
+    #[arg(long, short)]
+    output: Option<PathBuf>,
+}
+
+pub fn run(args: &SchemaArgs) -> io::Result<()> {
+    let schema = serde_json::to_string_pretty(&json_schema::document())? + "\n";
+    match &args.output {
+        Some(path) => fs::write(path, schema),
+        None => io::stdout().write_all(schema.as_bytes()),
+    }
+}
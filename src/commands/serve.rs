@@ -0,0 +1,128 @@
+//! `serve`, ranks documents over HTTP
+use crate::model::Model;
+use crate::scoring::{Extractor, Scoring};
+use crate::{batch, prometheus, server, spans, text};
+
+use clap::Args;
+use lib_tfidf::{Document, Tfidf};
+
+use std::io;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Instant;
+
+#[derive(Debug, Args, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ServeArgs {
+    /// model file written by `eval --save-model`
+    #[arg(long)]
+    load_model: PathBuf,
+
+    /// address the server listens on
+    #[arg(long, env = "HULTH_EVAL_LISTEN", default_value = "127.0.0.1:8080")]
+    listen: String,
+
+    /// number of keywords per document
+    #[arg(long, env = "HULTH_EVAL_TOP_K", default_value_t = 10)]
+    top_k: usize,
+
+    /// ranking variant
+    #[arg(long, value_enum, default_value_t = Extractor::Tfidf)]
+    extractor: Extractor,
+
+    #[command(flatten)]
+    #[serde(default)]
+    scoring: Scoring,
+}
+
+/// serves `POST /rank` with a document as raw text or Hulth JSON in the body,
+/// `GET /metrics` for Prometheus and the `/healthz` and `/readyz` probes.
+/// requests are handled one at a time on this thread since the fitted model
+/// can't be shared across threads. SIGTERM stops the server after the current request
+pub fn run(args: &ServeArgs) -> io::Result<()> {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use tiny_http::{Method, Server};
+
+    let shutdown = server::shutdown_flag()?;
+    let http = Arc::new(Server::http(&args.listen).map_err(io::Error::other)?);
+    info!(listen = %args.listen, "listening");
+    let ready = Arc::new(AtomicBool::new(false));
+    let loading = server::answer_while_loading(http.clone(), ready.clone(), shutdown.clone());
+
+    let model = Model::load(&args.load_model)?;
+    let normalizer = model.preprocessing.normalizer()?;
+    let docs = model.corpus.boxed();
+    let started = Instant::now();
+    let mut tfidf = Tfidf::new(docs.as_slice());
+    tfidf.fit_transform()?;
+    let fit_seconds = started.elapsed().as_secs_f64();
+    info!(documents = docs.len(), "fitted model");
+    let scoring = args.scoring.for_extractor(args.extractor)?;
+    let reranker = scoring.reranker(args.extractor, || model.frequencies.clone())?;
+    let terms = model.frequencies.terms().count();
+    let mut metrics = prometheus::Metrics::new(docs.len(), terms, fit_seconds);
+
+    let rank = |input: &str| -> io::Result<Vec<batch::Keyword>> {
+        let original = text::parse_document(input)?;
+        let doc = normalizer.apply(&original);
+        let scores = tfidf.rank_tokens(&doc.get_content())?.into_iter().collect();
+        let scores = model.preprocessing.resolve(&doc, scores);
+        let mut ranked = scoring.rank(args.extractor, reranker.as_ref(), &doc, scores);
+        ranked.truncate(args.top_k);
+        let surfaces = spans::surface_forms(&original, &doc);
+        Ok(ranked
+            .into_iter()
+            .map(|(term, score)| batch::Keyword::new(term, score, &surfaces))
+            .collect())
+    };
+
+    ready.store(true, Ordering::Relaxed);
+    loading
+        .join()
+        .map_err(|_| io::Error::other("startup thread panicked"))?;
+    info!("ready");
+    while !shutdown.load(Ordering::Relaxed) {
+        let mut request = match http.recv_timeout(server::POLL)? {
+            Some(request) => request,
+            None => continue,
+        };
+        let started = Instant::now();
+        if let Some((status, body)) = server::probe(&request, true) {
+            server::respond(request, status, "text/plain", body.into());
+            continue;
+        }
+        let path = request.url().split('?').next().unwrap_or_default();
+        let (route, status, content_type, body) = match (request.method(), path) {
+            (Method::Post, "/rank") => {
+                let mut input = String::new();
+                let ranked = request
+                    .as_reader()
+                    .read_to_string(&mut input)
+                    .and_then(|_| rank(&input));
+                match ranked {
+                    Ok(keywords) => {
+                        metrics.ranked(keywords.len());
+                        let body = serde_json::json!({ "keywords": keywords });
+                        ("rank", 200, "application/json", body.to_string())
+                    }
+                    Err(e) => {
+                        debug!(error = %e, "rejected document");
+                        let body = serde_json::json!({ "error": e.to_string() });
+                        ("rank", 400, "application/json", body.to_string())
+                    }
+                }
+            }
+            (Method::Get, "/metrics") => (
+                "metrics",
+                200,
+                "text/plain; version=0.0.4",
+                metrics.render(),
+            ),
+            _ => ("other", 404, "text/plain", "not found\n".to_string()),
+        };
+        server::respond(request, status, content_type, body);
+        metrics.observe(route, status, started.elapsed().as_secs_f64());
+    }
+    info!("shutting down");
+    Ok(())
+}
@@ -0,0 +1,98 @@
+//! `serve-grpc`, ranks documents over gRPC
+use crate::grpc;
+use crate::model::Model;
+use crate::scoring::{Extractor, Scoring};
+use crate::text;
+
+use clap::Args;
+use lib_tfidf::{Document, Tfidf};
+
+use std::io;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+#[derive(Debug, Args, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ServeGrpcArgs {
+    /// model file written by `eval --save-model`
+    #[arg(long)]
+    load_model: PathBuf,
+
+    /// address the gRPC server listens on
+    #[arg(
+        long,
+        env = "HULTH_EVAL_GRPC_LISTEN",
+        default_value = "127.0.0.1:50051"
+    )]
+    listen: SocketAddr,
+
+    /// number of keywords for documents that don't set `top_k`
+    #[arg(long, env = "HULTH_EVAL_TOP_K", default_value_t = 10)]
+    top_k: usize,
+
+    /// ranking variant
+    #[arg(long, value_enum, default_value_t = Extractor::Tfidf)]
+    extractor: Extractor,
+
+    #[command(flatten)]
+    #[serde(default)]
+    scoring: Scoring,
+}
+
+/// serves the `hulth.Extractor` gRPC service of `proto/hulth.proto`, `Rank` for
+/// single documents and `RankBatch` for a stream of them. the service runs on a
+/// tokio runtime while this thread owns the fitted model and ranks the documents
+/// one at a time. SIGTERM stops the server once the open calls are answered
+pub fn run(args: &ServeGrpcArgs) -> io::Result<()> {
+    use grpc::proto::{Keyword, RankedKeywords};
+
+    let model = Model::load(&args.load_model)?;
+    let normalizer = model.preprocessing.normalizer()?;
+    let docs = model.corpus.boxed();
+    let mut tfidf = Tfidf::new(docs.as_slice());
+    tfidf.fit_transform()?;
+    info!(documents = docs.len(), "fitted model");
+    let scoring = args.scoring.for_extractor(args.extractor)?;
+    let reranker = scoring.reranker(args.extractor, || model.frequencies.clone())?;
+
+    let rank = |input: &str, top_k: usize| -> io::Result<Vec<Keyword>> {
+        let doc = normalizer.apply(&text::parse_document(input)?);
+        let scores = tfidf.rank_tokens(&doc.get_content())?.into_iter().collect();
+        let scores = model.preprocessing.resolve(&doc, scores);
+        let mut ranked = scoring.rank(args.extractor, reranker.as_ref(), &doc, scores);
+        ranked.truncate(top_k);
+        Ok(ranked
+            .into_iter()
+            .map(|(term, score)| Keyword { term, score })
+            .collect())
+    };
+
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?;
+    let (jobs, mut pending) = tokio::sync::mpsc::channel::<grpc::Job>(64);
+    let server = runtime.spawn(grpc::serve(args.listen, jobs));
+    info!(listen = %args.listen, "listening");
+    // the channel closes once the server stopped and dropped the service
+    while let Some(job) = pending.blocking_recv() {
+        let top_k = match job.document.top_k {
+            0 => args.top_k,
+            k => k as usize,
+        };
+        let ranked = match rank(&job.document.text, top_k) {
+            Ok(keywords) => Ok(RankedKeywords {
+                id: job.document.id,
+                keywords,
+            }),
+            Err(e) => {
+                debug!(error = %e, "rejected document");
+                Err(tonic::Status::invalid_argument(e.to_string()))
+            }
+        };
+        // the caller may have gone away in the meantime
+        let _ = job.reply.send(ranked);
+    }
+    runtime
+        .block_on(server)
+        .map_err(|_| io::Error::other("gRPC server panicked"))?
+}
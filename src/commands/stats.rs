@@ -0,0 +1,115 @@
+//! `stats`, describes a dataset and how its references cover it
+use crate::corpus::{self, Reading};
+use crate::preprocess::Preprocessing;
+use crate::{matching, HulthDocumentKeywords, DEFAULT_DATASET, DEFAULT_REFERENCES};
+
+use clap::Args;
+
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, BufReader, Write};
+use std::path::PathBuf;
+
+#[derive(Debug, Args, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StatsArgs {
+    /// directory containing the Hulth JSON documents
+    #[arg(long, env = "HULTH_EVAL_DATASET", default_value = DEFAULT_DATASET)]
+    dataset: PathBuf,
+
+    /// JSON file mapping document names to their reference keywords
+    #[arg(long, env = "HULTH_EVAL_REFERENCES", default_value = DEFAULT_REFERENCES)]
+    references: PathBuf,
+
+    /// the vocabulary is counted after this preprocessing
+    #[command(flatten)]
+    #[serde(default)]
+    preprocessing: Preprocessing,
+}
+
+#[derive(Debug, Default)]
+struct Stats {
+    documents: usize,
+    sentences: usize,
+    tokens: usize,
+    vocabulary: HashSet<String>,
+    /// documents with an entry in the references
+    referenced: usize,
+    keyphrases: usize,
+    /// keyphrases that never occur in their document
+    unreachable: usize,
+    /// references without a document in the dataset
+    orphaned: usize,
+}
+
+/// counts the documents, tokens and reference keyphrases of the dataset
+pub fn run(args: &StatsArgs, reading: &Reading) -> io::Result<()> {
+    let keywords: HulthDocumentKeywords =
+        serde_json::from_reader(BufReader::new(fs::File::open(&args.references)?))?;
+    let normalizer = args.preprocessing.normalizer()?;
+    let mut stats = Stats::default();
+    let mut names = HashSet::new();
+    for entry in corpus::stream_dir(&args.dataset, reading)? {
+        let (name, original) = entry?;
+        let doc = normalizer.apply(&original);
+        stats.documents += 1;
+        stats.sentences += doc.sentences.len();
+        for token in doc.sentences.iter().flat_map(|s| s.tokens.iter()) {
+            stats.tokens += 1;
+            if !stats.vocabulary.contains(&*token.word) {
+                stats.vocabulary.insert(token.word.to_string());
+            }
+        }
+        match keywords.get(&name) {
+            Some(groups) => {
+                stats.referenced += 1;
+                stats.keyphrases += groups.iter().map(Vec::len).sum::<usize>();
+                for keyphrase in matching::unreachable(&doc, groups, &normalizer) {
+                    debug!(document = %name, keyphrase = %keyphrase, "reference keyphrase never occurs");
+                    stats.unreachable += 1;
+                }
+            }
+            None => debug!(document = %name, "found no keywords"),
+        }
+        names.insert(name);
+    }
+    stats.orphaned = keywords.keys().filter(|k| !names.contains(*k)).count();
+    print(io::stdout().lock(), &stats)
+}
+
+fn print<W: Write>(mut w: W, stats: &Stats) -> io::Result<()> {
+    writeln!(w, "{:<28} {}", "documents", stats.documents)?;
+    writeln!(w, "{:<28} {}", "sentences", stats.sentences)?;
+    writeln!(w, "{:<28} {}", "tokens", stats.tokens)?;
+    writeln!(
+        w,
+        "{:<28} {:.1}",
+        "tokens per document",
+        stats.tokens as f64 / stats.documents.max(1) as f64
+    )?;
+    writeln!(w, "{:<28} {}", "vocabulary", stats.vocabulary.len())?;
+    writeln!(
+        w,
+        "{:<28} {}",
+        "documents with references", stats.referenced
+    )?;
+    writeln!(w, "{:<28} {}", "reference keyphrases", stats.keyphrases)?;
+    writeln!(
+        w,
+        "{:<28} {:.1}",
+        "keyphrases per document",
+        stats.keyphrases as f64 / stats.referenced.max(1) as f64
+    )?;
+    writeln!(
+        w,
+        "{:<28} {} ({:.1}%)",
+        "unreachable keyphrases",
+        stats.unreachable,
+        100.0 * stats.unreachable as f64 / stats.keyphrases.max(1) as f64
+    )?;
+    writeln!(
+        w,
+        "{:<28} {}",
+        "references without document", stats.orphaned
+    )
+}
@@ -0,0 +1,169 @@
+//! `sweep`, evaluates a grid of settings to pick the best on a training split
+use super::eval::{in_run_dir, run_dataset, DatasetSpec, EvalArgs};
+use crate::corpus::Reading;
+use crate::report::Summary;
+use crate::scoring::Extractor;
+use crate::textrank::TextRank;
+use crate::{manifest, preflight};
+
+use clap::Args;
+
+use std::io::{self, Write};
+
+#[derive(Debug, Args, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SweepArgs {
+    /// values of --top-k evaluated with every extractor
+    #[arg(long, value_delimiter = ',', default_values_t = [5, 10, 15, 20])]
+    k_values: Vec<usize>,
+
+    /// values of --textrank-window tried with the textrank extractor,
+    /// only the value of the options it starts from if omitted, like the ones below
+    #[arg(long, value_delimiter = ',')]
+    #[serde(default)]
+    textrank_windows: Vec<usize>,
+
+    /// values of --textrank-damping tried with the textrank extractor
+    #[arg(long, value_delimiter = ',')]
+    #[serde(default)]
+    textrank_dampings: Vec<f64>,
+
+    /// values of --textrank-iterations tried with the textrank extractor
+    #[arg(long, value_delimiter = ',')]
+    #[serde(default)]
+    textrank_iteration_limits: Vec<usize>,
+
+    /// values of --textrank-epsilon tried with the textrank extractor
+    #[arg(long, value_delimiter = ',')]
+    #[serde(default)]
+    textrank_epsilons: Vec<f64>,
+
+    /// the options every point of the grid starts from, `[sweep.eval]` in the config file
+    #[command(flatten)]
+    pub(crate) eval: EvalArgs,
+}
+
+impl SweepArgs {
+    /// the TextRank settings of the grid, every combination of the values given
+    fn textrank(&self) -> io::Result<Vec<TextRank>> {
+        let base = self
+            .eval
+            .scoring
+            .for_extractor(Extractor::TextRank)?
+            .textrank;
+        let mut grid = vec![];
+        for window in values(&self.textrank_windows, base.window) {
+            for damping in values(&self.textrank_dampings, base.damping) {
+                for iterations in values(&self.textrank_iteration_limits, base.iterations) {
+                    for epsilon in values(&self.textrank_epsilons, base.epsilon) {
+                        grid.push(TextRank {
+                            window,
+                            damping,
+                            iterations,
+                            epsilon,
+                        });
+                    }
+                }
+            }
+        }
+        Ok(grid)
+    }
+
+    /// the settings evaluated with extractor besides k
+    fn settings(&self, extractor: Extractor) -> io::Result<Vec<Option<TextRank>>> {
+        Ok(match extractor {
+            Extractor::TextRank => self.textrank()?.into_iter().map(Some).collect(),
+            _ => vec![None],
+        })
+    }
+}
+
+/// the values given, or just value
+fn values<T: Copy>(given: &[T], value: T) -> Vec<T> {
+    if given.is_empty() {
+        vec![value]
+    } else {
+        given.to_vec()
+    }
+}
+
+/// runs the evaluation once per extractor, k and the settings of the extractor
+/// and prints the measures of each, marking the best F1 of every extractor
+pub fn run(args: &SweepArgs, reading: &Reading) -> io::Result<()> {
+    if args.eval.dry_run {
+        let mut runs = 0;
+        for extractor in &args.eval.extractors {
+            runs += args.settings(*extractor)?.len() * args.k_values.len();
+        }
+        let spec = DatasetSpec::of(&args.eval);
+        return preflight::check(&args.eval, &[spec], reading, &args.eval.extractors, runs);
+    }
+    let eval = in_run_dir(&args.eval)?;
+    if let Some(path) = &eval.manifest {
+        manifest::write(path, "sweep", eval.scoring.seed, args)?;
+    }
+    let mut rows = vec![];
+    for extractor in &eval.extractors {
+        for setting in args.settings(*extractor)? {
+            for k in &args.k_values {
+                let mut variant = eval.clone();
+                // resolved first so `[extractors.textrank]` doesn't override the grid
+                variant.scoring = variant.scoring.for_extractor(*extractor)?;
+                variant.top_k = Some(*k);
+                let mut label = format!("{}.k{}", extractor, k);
+                let mut described = String::new();
+                if let Some(textrank) = &setting {
+                    variant.scoring.textrank = textrank.clone();
+                    label += &format!(
+                        ".w{}.d{}.i{}.e{}",
+                        textrank.window, textrank.damping, textrank.iterations, textrank.epsilon
+                    );
+                    described = format!(
+                        "window={} damping={} iterations={} epsilon={}",
+                        textrank.window, textrank.damping, textrank.iterations, textrank.epsilon
+                    );
+                }
+                info!(extractor = %extractor, top_k = k, settings = %described, "evaluating grid point");
+                let measures = run_dataset(
+                    &variant,
+                    &eval.dataset,
+                    &eval.references,
+                    reading,
+                    *extractor,
+                    Some(&label),
+                )?;
+                rows.push((
+                    extractor.to_string(),
+                    *k,
+                    described,
+                    Summary::from_measures(&measures),
+                ));
+            }
+        }
+    }
+
+    let mut out = io::stdout().lock();
+    writeln!(
+        out,
+        "{:<10} {:>6} {:>9} {:>9} {:>9}  settings",
+        "extractor", "top_k", "precision", "recall", "f1"
+    )?;
+    for (extractor, k, settings, summary) in &rows {
+        let best = rows
+            .iter()
+            .filter(|(e, _, _, _)| e == extractor)
+            .all(|(_, _, _, other)| other.f1 <= summary.f1);
+        let line = format!(
+            "{:<10} {:>6} {:>9.4} {:>9.4} {:>9.4}{}  {}",
+            extractor,
+            k,
+            summary.precision,
+            summary.recall,
+            summary.f1,
+            if best { " *" } else { "  " },
+            settings
+        );
+        writeln!(out, "{}", line.trim_end())?;
+    }
+    Ok(())
+}
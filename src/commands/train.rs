@@ -0,0 +1,85 @@
+//! `train`, trains the logistic reranker
+use super::for_each_candidate;
+use crate::corpus::Reading;
+use crate::logistic;
+use crate::model::Model;
+use crate::scoring::{Extractor, Scoring};
+use crate::{DEFAULT_DATASET, DEFAULT_REFERENCES};
+
+use clap::Args;
+
+use std::io;
+use std::path::PathBuf;
+
+#[derive(Debug, Args, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TrainArgs {
+    /// model file written by `eval --save-model` on the training split
+    #[arg(long)]
+    load_model: PathBuf,
+
+    /// directory containing the Hulth JSON documents of the training split
+    #[arg(long, env = "HULTH_EVAL_DATASET", default_value = DEFAULT_DATASET)]
+    dataset: PathBuf,
+
+    /// JSON file mapping document names to their reference keywords, the gold labels
+    #[arg(long, env = "HULTH_EVAL_REFERENCES", default_value = DEFAULT_REFERENCES)]
+    references: PathBuf,
+
+    /// JSON file the weights are written to, see --reranker
+    #[arg(long, short)]
+    output: PathBuf,
+
+    /// trains only on the best k tf-idf candidates of every document
+    #[arg(long)]
+    top_k: Option<usize>,
+
+    /// passes of gradient descent over the candidates
+    #[arg(long, default_value_t = 500)]
+    epochs: usize,
+
+    #[arg(long, default_value_t = 0.5)]
+    learning_rate: f64,
+
+    /// strength of the L2 penalty on the weights
+    #[arg(long, default_value_t = 0.0)]
+    l2: f64,
+
+    #[command(flatten)]
+    #[serde(default)]
+    scoring: Scoring,
+}
+
+/// fits the logistic reranker on the tf-idf candidates of the dataset
+/// and writes its weights for `--extractor logistic`
+pub fn run(args: &TrainArgs, reading: &Reading) -> io::Result<()> {
+    let model = Model::load(&args.load_model)?;
+    let mut samples = vec![];
+    for_each_candidate(
+        &model,
+        &args.dataset,
+        &args.references,
+        reading,
+        Extractor::Tfidf,
+        &args.scoring,
+        args.top_k,
+        |_, _, features, label| {
+            samples.push((features.values(), label));
+            Ok(())
+        },
+    )?;
+    let positive = samples.iter().filter(|(_, label)| *label).count();
+    info!(
+        candidates = samples.len(),
+        positive, "collected training candidates"
+    );
+    let training = logistic::Training {
+        epochs: args.epochs,
+        learning_rate: args.learning_rate,
+        l2: args.l2,
+    };
+    let weights = logistic::Weights::train(&samples, training);
+    weights.save(&args.output)?;
+    info!(path = %args.output.display(), "saved reranker");
+    Ok(())
+}
@@ -0,0 +1,72 @@
+//! `update-model`, adds documents to a saved model or removes them from it.
+//! the document frequencies stored with the model are updated by the counts of
+//! the documents added and removed, only the new documents are read and preprocessed
+use crate::corpus::{self, Reading};
+use crate::intern::Interner;
+use crate::model::Model;
+
+use clap::Args;
+
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+#[derive(Debug, Args, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct UpdateModelArgs {
+    /// model file written by `eval --save-model`
+    #[arg(long)]
+    load_model: PathBuf,
+
+    /// directory of Hulth JSON documents to add, preprocessed as the model's were
+    #[arg(long, required_unless_present = "remove")]
+    add: Option<PathBuf>,
+
+    /// file of the ids of the documents to remove, one per line. empty lines and
+    /// lines starting with `#` are skipped. removed before any are added, their
+    /// counts are subtracted from the document frequencies. fails if an id isn't
+    /// in the model
+    #[arg(long)]
+    remove: Option<PathBuf>,
+
+    /// file the updated model is written to, --load-model if omitted
+    #[arg(long, short)]
+    output: Option<PathBuf>,
+}
+
+pub fn run(args: &UpdateModelArgs, reading: &Reading) -> io::Result<()> {
+    let mut model = Model::load(&args.load_model)?;
+    let before = model.corpus.len();
+    let mut removed = 0;
+    if let Some(path) = &args.remove {
+        let text = fs::read_to_string(path)?;
+        let ids = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .collect::<HashSet<_>>();
+        removed = model.remove(&ids)?;
+    }
+    let (mut added, mut replaced) = (0, 0);
+    if let Some(dir) = &args.add {
+        let mut interner = Interner::new();
+        let normalizer = model.preprocessing.normalizer()?;
+        let documents = corpus::load_dir(dir, reading, &normalizer, &mut interner, false)?;
+        added = documents.len();
+        replaced = model.add(documents);
+        added -= replaced;
+    }
+    let output = args.output.as_ref().unwrap_or(&args.load_model);
+    model.save(output)?;
+    info!(
+        added,
+        replaced,
+        removed,
+        documents = model.corpus.len(),
+        before,
+        path = %output.display(),
+        "updated model"
+    );
+    Ok(())
+}
@@ -0,0 +1,50 @@
+//! `validate`, checks the documents of a dataset against the JSON Schema
+use crate::corpus::{self, Reading};
+use crate::{json_schema, DEFAULT_DATASET};
+
+use clap::Args;
+
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+#[derive(Debug, Args, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ValidateArgs {
+    /// directory containing the Hulth JSON documents
+    #[arg(long, env = "HULTH_EVAL_DATASET", default_value = DEFAULT_DATASET)]
+    dataset: PathBuf,
+}
+
+/// prints every violation as `<file>: <location>: <message>`,
+/// failing if any document does not conform
+pub fn run(args: &ValidateArgs, reading: &Reading) -> io::Result<()> {
+    let validator = json_schema::validator()?;
+    let mut out = io::stdout().lock();
+    let (mut checked, mut invalid) = (0, 0);
+    corpus::for_each_file(&args.dataset, reading, |path| {
+        checked += 1;
+        let violations = json_schema::check(&validator, &fs::read(path)?);
+        if !violations.is_empty() {
+            invalid += 1;
+        }
+        for violation in violations {
+            writeln!(
+                out,
+                "{}: {}: {}",
+                path.display(),
+                violation.location,
+                violation.message
+            )?;
+        }
+        Ok(())
+    })?;
+    info!(checked, invalid, "validated dataset");
+    if invalid > 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} of {} documents do not conform", invalid, checked),
+        ));
+    }
+    Ok(())
+}
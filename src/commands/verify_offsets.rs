@@ -0,0 +1,56 @@
+//! `verify-offsets`, checks token offsets against the original abstracts
+use crate::corpus::{self, Reading};
+use crate::{verify, DEFAULT_DATASET};
+
+use clap::Args;
+
+use std::io;
+use std::path::PathBuf;
+
+#[derive(Debug, Args, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct VerifyOffsetsArgs {
+    /// directory containing the Hulth JSON documents
+    #[arg(long, env = "HULTH_EVAL_DATASET", default_value = DEFAULT_DATASET)]
+    dataset: PathBuf,
+
+    /// directory containing `<document>.abstr` files, the dataset directory if omitted.
+    /// documents without one are skipped
+    #[arg(long)]
+    abstracts: Option<PathBuf>,
+}
+
+/// reports the documents whose offsets don't match their abstract,
+/// failing if there are any so scripts can stop before span-based exports
+pub fn run(args: &VerifyOffsetsArgs, reading: &Reading) -> io::Result<()> {
+    let abstracts = args.abstracts.as_ref().unwrap_or(&args.dataset);
+    let mut results = vec![];
+    let mut skipped = 0;
+    for entry in corpus::stream_dir(&args.dataset, reading)? {
+        let (name, doc) = entry?;
+        match verify::read_abstract(abstracts, &name)? {
+            Some(text) => results.push(verify::check(&name, &doc, &text)),
+            None => {
+                debug!(document = %name, "found no abstract");
+                skipped += 1;
+            }
+        }
+    }
+    verify::print(io::stdout().lock(), &results)?;
+    let inconsistent = results.iter().filter(|r| !r.mismatched.is_empty()).count();
+    info!(
+        checked = results.len(),
+        skipped, inconsistent, "verified offsets"
+    );
+    if inconsistent > 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "{} of {} documents have inconsistent offsets",
+                inconsistent,
+                results.len()
+            ),
+        ));
+    }
+    Ok(())
+}
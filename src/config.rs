@@ -0,0 +1,251 @@
+//! TOML run configuration, `--config run.toml`.
+//! top-level keys set global options, a table per subcommand
+//! (`[eval]`, `[rank]`, ...) sets that subcommand's options.
+//! keys are the option names with underscores, e.g. `save_model`,
+//! option groups such as `[eval.preprocessing]` are nested tables.
+//! `[extractors.<name>]` sets scoring options used only with that extractor,
+//! e.g. `window` in `[extractors.textrank]`, for every subcommand.
+//! options given on the command line or through their `HULTH_EVAL_*`
+//! environment variable take precedence
+use clap::parser::ValueSource;
+use clap::ArgMatches;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug)]
+pub struct Config {
+    path: PathBuf,
+    table: toml::Table,
+}
+
+impl Config {
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref();
+        let table = fs::read_to_string(path)?
+            .parse::<toml::Table>()
+            .map_err(|e| invalid(path, e))?;
+        Ok(Config {
+            path: path.into(),
+            table,
+        })
+    }
+
+    /// an empty config whose sections are given to `apply`,
+    /// named like the option setting them in errors
+    pub fn preset(name: &str) -> Self {
+        Config {
+            path: name.into(),
+            table: toml::Table::new(),
+        }
+    }
+
+    /// the top-level values, everything that is not a subcommand table
+    pub fn globals(&self) -> toml::Table {
+        self.table
+            .iter()
+            .filter(|(_, v)| !v.is_table())
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect()
+    }
+
+    /// the tables at the top level, which must be named after subcommands
+    pub fn check_sections(&self, subcommands: &[&str]) -> io::Result<()> {
+        for (name, value) in &self.table {
+            if value.is_table() && !subcommands.contains(&name.as_str()) {
+                return Err(invalid(
+                    &self.path,
+                    format!(
+                        "unknown section [{}], expected one of {:?}",
+                        name, subcommands
+                    ),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn section(&self, subcommand: &str) -> Option<&toml::Table> {
+        self.table.get(subcommand).and_then(|v| v.as_table())
+    }
+
+    /// `[extractors]`, a table of options per extractor name
+    pub fn extractors(&self) -> Option<&toml::Table> {
+        self.section("extractors")
+    }
+
+    /// e prefixed by the path of the config file
+    pub fn invalid<E: std::fmt::Display>(&self, e: E) -> io::Error {
+        invalid(&self.path, e)
+    }
+
+    /// overrides the options of args with values, except for those
+    /// given on the command line or in the environment according to matches
+    pub fn apply<T>(&self, args: T, values: &toml::Table, matches: &ArgMatches) -> io::Result<T>
+    where
+        T: Serialize + DeserializeOwned,
+    {
+        if values.is_empty() && self.extractors().is_none() {
+            return Ok(args);
+        }
+        let mut merged = serde_json::to_value(&args)?;
+        let object = merged
+            .as_object_mut()
+            .ok_or_else(|| io::Error::other("options must serialize to an object"))?;
+        merge(object, values, matches).map_err(|e| invalid(&self.path, e))?;
+        if let Some(extractors) = self.extractors() {
+            let extractors = extractors
+                .iter()
+                .filter_map(|(name, options)| {
+                    let options = options
+                        .as_table()?
+                        .iter()
+                        .filter(|(key, _)| !given_explicitly(matches, key))
+                        .map(|(k, v)| (k.clone(), v.clone()))
+                        .collect::<toml::Table>();
+                    Some((name.clone(), toml::Value::Table(options)))
+                })
+                .collect::<toml::Table>();
+            add_extractors(&mut merged, &serde_json::to_value(extractors)?);
+        }
+        serde_json::from_value(merged).map_err(|e| invalid(&self.path, e))
+    }
+}
+
+/// tables are merged into option groups key by key,
+/// since the options of flattened groups share the same matches
+fn merge(
+    object: &mut serde_json::Map<String, serde_json::Value>,
+    values: &toml::Table,
+    matches: &ArgMatches,
+) -> serde_json::Result<()> {
+    for (key, value) in values {
+        if let (toml::Value::Table(table), Some(serde_json::Value::Object(group))) =
+            (value, object.get_mut(key))
+        {
+            merge(group, table, matches)?;
+        } else if !given_explicitly(matches, key) {
+            object.insert(key.clone(), serde_json::to_value(value)?);
+        }
+    }
+    Ok(())
+}
+
+/// sets `extractors` of every scoring option group in value
+fn add_extractors(value: &mut serde_json::Value, extractors: &serde_json::Value) {
+    if let serde_json::Value::Object(object) = value {
+        for (key, value) in object.iter_mut() {
+            match value {
+                serde_json::Value::Object(scoring) if key == "scoring" => {
+                    scoring.insert("extractors".into(), extractors.clone());
+                }
+                _ => add_extractors(value, extractors),
+            }
+        }
+    }
+}
+
+fn given_explicitly(matches: &ArgMatches, id: &str) -> bool {
+    if matches.try_contains_id(id).is_err() {
+        return false;
+    }
+    matches!(
+        matches.value_source(id),
+        Some(ValueSource::CommandLine) | Some(ValueSource::EnvVariable)
+    )
+}
+
+fn invalid<E: std::fmt::Display>(path: &Path, e: E) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("{}: {}", path.display(), e),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::eval::EvalArgs;
+    use crate::scoring::Extractor;
+    use crate::{Cli, Command};
+
+    use clap::{CommandFactory, FromArgMatches};
+
+    use std::env;
+    use std::sync::Mutex;
+
+    /// held by every test parsing a command line, they share the environment
+    static ENVIRONMENT: Mutex<()> = Mutex::new(());
+
+    const CONFIG: &str = r#"
+        [eval]
+        dataset = "config"
+
+        [eval.preprocessing]
+        stem = true
+        max_phrase_len = 2
+
+        [extractors.textrank]
+        window = 4
+    "#;
+
+    /// the options of `eval args` with CONFIG applied
+    fn eval(args: &[&str]) -> EvalArgs {
+        let argv = ["hulth-eval", "eval"].iter().chain(args);
+        let matches = Cli::command().try_get_matches_from(argv).unwrap();
+        let cli = Cli::from_arg_matches(&matches).unwrap();
+        let config = Config {
+            path: "run.toml".into(),
+            table: CONFIG.parse().unwrap(),
+        };
+        let (_, matches) = matches.subcommand().unwrap();
+        match cli.command {
+            Some(Command::Eval(args)) => config
+                .apply(args, config.section("eval").unwrap(), matches)
+                .unwrap(),
+            command => panic!("parsed {:?}", command),
+        }
+    }
+
+    fn window(args: &EvalArgs) -> usize {
+        let scoring = args.scoring.for_extractor(Extractor::TextRank).unwrap();
+        scoring.textrank.window
+    }
+
+    #[test]
+    fn the_config_file_sets_options_not_given_otherwise() {
+        let _environment = ENVIRONMENT.lock().unwrap();
+        env::remove_var("HULTH_EVAL_DATASET");
+        let args = eval(&[]);
+        assert_eq!(args.dataset, Path::new("config"));
+        assert!(args.preprocessing.stem);
+        assert_eq!(args.preprocessing.max_phrase_len, 2);
+        assert!(!args.preprocessing.stopwords);
+        assert_eq!(window(&args), 4);
+    }
+
+    #[test]
+    fn the_environment_beats_the_config_file_and_the_command_line_both() {
+        let _environment = ENVIRONMENT.lock().unwrap();
+        env::set_var("HULTH_EVAL_DATASET", "environment");
+        let from_environment = eval(&[]);
+        let from_command_line = eval(&["--dataset", "command-line"]);
+        env::remove_var("HULTH_EVAL_DATASET");
+        assert_eq!(from_environment.dataset, Path::new("environment"));
+        assert_eq!(from_command_line.dataset, Path::new("command-line"));
+    }
+
+    #[test]
+    fn the_command_line_beats_nested_tables_option_by_option() {
+        let _environment = ENVIRONMENT.lock().unwrap();
+        env::remove_var("HULTH_EVAL_DATASET");
+        let args = eval(&["--max-phrase-len", "3", "--textrank-window", "5"]);
+        assert_eq!(args.preprocessing.max_phrase_len, 3);
+        // the rest of [eval.preprocessing] still applies
+        assert!(args.preprocessing.stem);
+        assert_eq!(window(&args), 5);
+    }
+}
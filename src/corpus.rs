@@ -0,0 +1,730 @@
+use crate::cache::{self, Cache};
+use crate::ids::Naming;
+use crate::intern::Interner;
+use crate::preprocess::Normalizer;
+use crate::source::{self, CorpusSource};
+use crate::spans;
+use crate::{HulthDocument, HulthToken};
+use lib_tfidf::Document;
+#[cfg(not(feature = "simd-json"))]
+use memmap2::Mmap;
+#[cfg(feature = "simd-json")]
+use memmap2::MmapOptions;
+
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+/// EIO, what network filesystems report for most failed reads
+#[cfg(unix)]
+const EIO: i32 = 5;
+
+thread_local! {
+    /// tokens without a lemma and without a tag parsed on this thread, see `check`
+    static MISSING: Cell<(usize, usize)> = const { Cell::new((0, 0)) };
+}
+
+/// how the files of a dataset are read during one run, with the number of files
+/// repaired or skipped so far. the default reads strictly, without retries
+#[derive(Debug, Default)]
+pub struct Reading {
+    /// `--lenient`, whether documents with missing token fields are repaired
+    /// and files unreadable after the retries skipped
+    lenient: bool,
+    /// `--io-retries`, reads retried after a transient error
+    retries: usize,
+    /// `--io-backoff`, the wait before the first retry
+    backoff: Duration,
+    /// `--ids` and `--id-map`
+    pub(crate) ids: Naming,
+    repaired: AtomicUsize,
+    skipped: AtomicUsize,
+}
+
+/// named documents of one concrete type.
+/// lib_tfidf only sees them through the boxed adapters of `boxed`,
+/// everything else in the harness works on the documents directly
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Corpus<D> {
+    entries: Vec<Entry<D>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Entry<D> {
+    name: String,
+    document: Arc<D>,
+    /// kept while loading for `eval --keywords`, not saved with models or corpora
+    #[serde(skip)]
+    surfaces: Option<Arc<Surfaces>>,
+}
+
+/// the most frequent surface form of every term of a preprocessed document,
+/// see `spans::surface_forms`
+pub type Surfaces = HashMap<String, String>;
+
+impl<D> Corpus<D> {
+    pub fn new() -> Self {
+        Corpus { entries: vec![] }
+    }
+
+    pub fn push(&mut self, name: String, document: D) {
+        self.push_with_surfaces(name, document, None);
+    }
+
+    pub fn push_with_surfaces(&mut self, name: String, document: D, surfaces: Option<Surfaces>) {
+        self.entries.push(Entry {
+            name,
+            document: Arc::new(document),
+            surfaces: surfaces.map(Arc::new),
+        });
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// adds the documents of other, replacing those of the same name in place,
+    /// and returns the documents replaced
+    pub fn merge(&mut self, other: Corpus<D>) -> Vec<Arc<D>> {
+        let mut index = self
+            .entries
+            .iter()
+            .enumerate()
+            .map(|(i, e)| (e.name.clone(), i))
+            .collect::<HashMap<_, _>>();
+        let mut replaced = vec![];
+        for entry in other.entries {
+            match index.get(&entry.name) {
+                Some(i) => {
+                    let old = std::mem::replace(&mut self.entries[*i], entry);
+                    replaced.push(old.document);
+                }
+                None => {
+                    index.insert(entry.name.clone(), self.entries.len());
+                    self.entries.push(entry);
+                }
+            }
+        }
+        replaced
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.entries.iter().map(|e| e.name.as_str())
+    }
+
+    /// drops the documents named in names and returns them
+    pub fn remove(&mut self, names: &HashSet<&str>) -> Vec<Arc<D>> {
+        let mut removed = vec![];
+        self.entries.retain(|e| {
+            if names.contains(e.name.as_str()) {
+                removed.push(e.document.clone());
+                false
+            } else {
+                true
+            }
+        });
+        removed
+    }
+
+    /// documents shared with a boxed corpus are copied on write
+    pub fn documents_mut(&mut self) -> impl Iterator<Item = &mut D>
+    where
+        D: Clone,
+    {
+        self.entries
+            .iter_mut()
+            .map(|e| Arc::make_mut(&mut e.document))
+    }
+
+    /// names and shared handles of the documents, for handing them to other threads
+    pub fn shared(&self) -> impl Iterator<Item = (String, Arc<D>)> + '_ {
+        self.entries
+            .iter()
+            .map(|e| (e.name.clone(), e.document.clone()))
+    }
+
+    /// like shared, with the surface forms of the documents if they were kept
+    pub fn shared_with_surfaces(
+        &self,
+    ) -> impl Iterator<Item = (String, Arc<D>, Option<Arc<Surfaces>>)> + '_ {
+        self.entries
+            .iter()
+            .map(|e| (e.name.clone(), e.document.clone(), e.surfaces.clone()))
+    }
+}
+
+impl<D> Corpus<D>
+where
+    D: Document<String, HulthToken> + 'static,
+{
+    /// the corpus as lib_tfidf expects it,
+    /// every box only shares the document with this corpus
+    pub fn boxed(&self) -> Vec<Box<dyn Document<String, HulthToken>>> {
+        self.entries
+            .iter()
+            .map(|e| Box::new(Shared(e.document.clone())) as Box<dyn Document<String, HulthToken>>)
+            .collect()
+    }
+}
+
+impl Corpus<HulthDocument> {
+    /// the corpus with every sentence as a document of its own,
+    /// each box shares the whole document and views one sentence
+    pub fn boxed_sentences(&self) -> Vec<Box<dyn Document<String, HulthToken>>> {
+        self.entries
+            .iter()
+            .flat_map(|e| {
+                (0..e.document.sentences.len()).map(move |sentence| {
+                    Box::new(SharedSentence {
+                        document: e.document.clone(),
+                        sentence,
+                    }) as Box<dyn Document<String, HulthToken>>
+                })
+            })
+            .collect()
+    }
+}
+
+impl<D> Default for Corpus<D> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+struct Shared<D>(Arc<D>);
+
+impl<D: Document<String, HulthToken>> Document<String, HulthToken> for Shared<D> {
+    fn get_id(&self) -> Box<String> {
+        self.0.get_id()
+    }
+
+    fn get_content(&self) -> Vec<Box<HulthToken>> {
+        self.0.get_content()
+    }
+}
+
+struct SharedSentence {
+    document: Arc<HulthDocument>,
+    sentence: usize,
+}
+
+impl Document<String, HulthToken> for SharedSentence {
+    fn get_id(&self) -> Box<String> {
+        Box::new("".into())
+    }
+
+    fn get_content(&self) -> Vec<Box<HulthToken>> {
+        self.document.sentences[self.sentence]
+            .tokens
+            .iter()
+            .cloned()
+            .map(Box::new)
+            .collect()
+    }
+}
+
+/// a document reduced to what lib_tfidf observes through the Token trait,
+/// the term and begin offset of every token
+#[derive(Debug)]
+pub struct CompactDocument {
+    tokens: Vec<(Arc<str>, usize)>,
+}
+
+impl CompactDocument {
+    pub fn new(doc: &HulthDocument) -> Self {
+        let tokens = doc
+            .sentences
+            .iter()
+            .flat_map(|s| s.tokens.iter())
+            .map(|t| (t.word.clone(), t.offset_begin as usize))
+            .collect();
+        CompactDocument { tokens }
+    }
+}
+
+impl Document<String, HulthToken> for CompactDocument {
+    fn get_id(&self) -> Box<String> {
+        Box::new("".into())
+    }
+
+    fn get_content(&self) -> Vec<Box<HulthToken>> {
+        let empty: Arc<str> = "".into();
+        self.tokens
+            .iter()
+            .map(|(word, offset)| {
+                Box::new(HulthToken {
+                    word: word.clone(),
+                    lemma: word.clone(),
+                    offset_begin: *offset as i64,
+                    offset_end: (offset + word.len()) as i64,
+                    pos: empty.clone(),
+                })
+            })
+            .collect()
+    }
+}
+
+/// lazily reads the Hulth JSON documents of a dataset, one per step
+pub struct DirStream<'a> {
+    source: Box<dyn CorpusSource>,
+    names: std::vec::IntoIter<String>,
+    reading: &'a Reading,
+}
+
+impl Iterator for DirStream<'_> {
+    type Item = io::Result<(String, HulthDocument)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for name in self.names.by_ref() {
+            let origin = self.source.origin(&name);
+            let read = self.source.read(&name, self.reading);
+            match self.reading.skip_unreadable(Path::new(&origin), read) {
+                Ok(Some(doc)) => {
+                    return Some(Ok((self.reading.named_in(&name, &origin, &doc), doc)))
+                }
+                Ok(None) => continue,
+                Err(e) => return Some(Err(e)),
+            }
+        }
+        None
+    }
+}
+
+/// the documents of dataset, a directory or any other `source`
+pub fn stream_dir<P: AsRef<Path>>(dataset: P, reading: &Reading) -> io::Result<DirStream<'_>> {
+    let source = source::open(dataset.as_ref())?;
+    let names = source.list(reading)?;
+    debug!(source = %source, documents = names.len(), "listed documents");
+    Ok(DirStream {
+        source,
+        names: names.into_iter(),
+        reading,
+    })
+}
+
+/// streams dir into compact documents, only one full document is in memory at a time
+pub fn load_compact<P: AsRef<Path>>(
+    dir: P,
+    reading: &Reading,
+    normalizer: &Normalizer,
+    interner: &mut Interner,
+) -> io::Result<Corpus<CompactDocument>> {
+    let mut corpus = Corpus::new();
+    for entry in stream_dir(dir, reading)? {
+        let (name, doc) = entry?;
+        let doc = prepare(&doc, normalizer, interner);
+        corpus.push(name, CompactDocument::new(&doc));
+    }
+    Ok(corpus)
+}
+
+/// reads and preprocesses every Hulth JSON document of dataset, interning its strings,
+/// keeping the surface forms of their terms if surfaces.
+/// documents are named after their file name without the `.json` extension
+pub fn load_dir<P: AsRef<Path>>(
+    dataset: P,
+    reading: &Reading,
+    normalizer: &Normalizer,
+    interner: &mut Interner,
+    surfaces: bool,
+) -> io::Result<Corpus<HulthDocument>> {
+    let mut corpus = Corpus::new();
+    for entry in stream_dir(dataset, reading)? {
+        let (name, original) = entry?;
+        let doc = prepare(&original, normalizer, interner);
+        let surfaces = surfaces.then(|| spans::surface_forms(&original, &doc));
+        corpus.push_with_surfaces(name, doc, surfaces);
+    }
+    Ok(corpus)
+}
+
+/// like load_dir, taking the preprocessed documents of unchanged files from cache,
+/// or the whole corpus if no file changed, and caching what was missing.
+/// corpora are cached without surface forms, with surfaces only documents are taken
+pub fn load_dir_cached<P: AsRef<Path>>(
+    dir: P,
+    reading: &Reading,
+    normalizer: &Normalizer,
+    interner: &mut Interner,
+    cache: &Cache,
+    surfaces: bool,
+) -> io::Result<Corpus<HulthDocument>> {
+    let mut files = vec![];
+    for_each_file(dir, reading, |path| {
+        let key = reading.retrying(path, || cache.document_key(path));
+        if let Some(key) = reading.skip_unreadable(path, key)? {
+            files.push((path.to_path_buf(), key));
+        }
+        Ok(())
+    })?;
+    let keys = files.iter().map(|(_, key)| key.clone()).collect::<Vec<_>>();
+    let corpus_key = cache.corpus_key(&keys);
+    let whole = if surfaces {
+        None
+    } else {
+        cache.get::<Corpus<HulthDocument>>(cache::CORPORA, &corpus_key)
+    };
+    if let Some(mut corpus) = whole {
+        for ((path, _), entry) in files.iter().zip(&corpus.entries) {
+            reading.ids.record(&entry.name, path.display());
+        }
+        for doc in corpus.documents_mut() {
+            interner.intern_document(doc);
+        }
+        info!(documents = corpus.len(), key = %corpus_key, "read corpus from cache");
+        return Ok(corpus);
+    }
+    let mut corpus = Corpus::new();
+    let mut cached = 0;
+    for (path, key) in &files {
+        let hit = cache
+            .get::<(String, HulthDocument, Option<Surfaces>)>(cache::DOCUMENTS, key)
+            .filter(|(_, _, kept)| kept.is_some() || !surfaces);
+        let (name, mut doc, kept) = match hit {
+            Some((name, doc, kept)) => {
+                reading.ids.record(&name, path.display());
+                cached += 1;
+                (name, doc, kept)
+            }
+            None => {
+                let original = match reading.skip_unreadable(path, reading.read_document(path))? {
+                    Some(doc) => doc,
+                    None => continue,
+                };
+                let doc = normalizer.apply(&original);
+                let kept = surfaces.then(|| spans::surface_forms(&original, &doc));
+                let entry = (reading.named(path, &original), doc, kept);
+                cache.put(cache::DOCUMENTS, key, &entry)?;
+                entry
+            }
+        };
+        interner.intern_document(&mut doc);
+        corpus.push_with_surfaces(name, doc, kept.filter(|_| surfaces));
+    }
+    cache.put(cache::CORPORA, &corpus_key, &corpus)?;
+    info!(
+        documents = corpus.len(),
+        cached,
+        key = %corpus_key,
+        "cached corpus"
+    );
+    Ok(corpus)
+}
+
+/// reads and parses the documents of dir concurrently on a tokio runtime,
+/// at most concurrency files are in flight. meant for datasets on network
+/// storage where sequential reads dominate; the corpus keeps directory order.
+/// the surface forms are kept if surfaces, like load_dir
+#[cfg(feature = "async")]
+pub fn load_dir_async<P: AsRef<Path>>(
+    dir: P,
+    reading: &Reading,
+    concurrency: usize,
+    normalizer: &Normalizer,
+    interner: &mut Interner,
+    surfaces: bool,
+) -> io::Result<Corpus<HulthDocument>> {
+    use futures::stream::{self, StreamExt, TryStreamExt};
+
+    let mut paths = vec![];
+    for_each_file(dir, reading, |path| {
+        paths.push(path.to_path_buf());
+        Ok(())
+    })?;
+
+    let runtime = tokio::runtime::Builder::new_multi_thread().build()?;
+    let docs: Vec<Option<(String, HulthDocument)>> = runtime.block_on(
+        stream::iter(paths)
+            .map(|path| async move {
+                let read = read_async(&path, reading).await;
+                let json = match reading.skip_unreadable(&path, read)? {
+                    Some(json) => json,
+                    None => return Ok(None),
+                };
+                let doc = reading.read_path_bytes(&path, &json)?;
+                Ok::<_, io::Error>(Some((reading.named(&path, &doc), doc)))
+            })
+            .buffered(concurrency.max(1))
+            .try_collect(),
+    )?;
+
+    let mut corpus = Corpus::new();
+    for (name, original) in docs.into_iter().flatten() {
+        let doc = prepare(&original, normalizer, interner);
+        let surfaces = surfaces.then(|| spans::surface_forms(&original, &doc));
+        corpus.push_with_surfaces(name, doc, surfaces);
+    }
+    Ok(corpus)
+}
+
+/// the preprocessed document with its strings interned
+pub fn prepare(
+    doc: &HulthDocument,
+    normalizer: &Normalizer,
+    interner: &mut Interner,
+) -> HulthDocument {
+    let mut doc = normalizer.apply(doc);
+    interner.intern_document(&mut doc);
+    doc
+}
+
+/// the file at path, retried like `retrying` without blocking the runtime
+#[cfg(feature = "async")]
+async fn read_async(path: &Path, reading: &Reading) -> io::Result<Vec<u8>> {
+    let mut attempt = 0;
+    loop {
+        match tokio::fs::read(path).await {
+            Err(e) => match reading.backoff(path, &e, attempt) {
+                Some(delay) => tokio::time::sleep(delay).await,
+                None => return Err(e),
+            },
+            read => return read,
+        }
+        attempt += 1;
+    }
+}
+
+impl Reading {
+    /// reads leniently if lenient, retrying reads failing with a transient error
+    /// up to retries times, waiting backoff before the first retry and twice
+    /// as long before every further one
+    pub fn new(lenient: bool, retries: usize, backoff: Duration, ids: Naming) -> Self {
+        Reading {
+            lenient,
+            retries,
+            backoff,
+            ids,
+            ..Reading::default()
+        }
+    }
+
+    pub fn lenient(&self) -> bool {
+        self.lenient
+    }
+
+    /// number of files whose missing token fields were filled in
+    pub fn repaired(&self) -> usize {
+        self.repaired.load(Ordering::Relaxed)
+    }
+
+    /// number of files skipped since they were unreadable after the retries
+    pub fn skipped(&self) -> usize {
+        self.skipped.load(Ordering::Relaxed)
+    }
+
+    /// parses the document straight from a memory map of the file.
+    /// the dataset must not be modified while it's being read
+    pub fn read_document(&self, path: &Path) -> io::Result<HulthDocument> {
+        self.retrying(path, || self.read_document_once(path))
+    }
+
+    fn read_document_once(&self, path: &Path) -> io::Result<HulthDocument> {
+        let file = fs::File::open(path)?;
+        if file.metadata()?.len() == 0 {
+            return Err(empty(path));
+        }
+        self.check(path, || parse_document(&file))
+    }
+
+    /// parses the document read from origin like the files of a dataset
+    pub fn read_bytes(&self, origin: &str, json: &[u8]) -> io::Result<HulthDocument> {
+        self.read_path_bytes(Path::new(origin), json)
+    }
+
+    fn read_path_bytes(&self, path: &Path, json: &[u8]) -> io::Result<HulthDocument> {
+        if json.is_empty() {
+            return Err(empty(path));
+        }
+        self.check(path, || parse_bytes(json))
+    }
+
+    /// the result of read, called again after a transient error, see `new`
+    pub fn retrying<T, F>(&self, path: &Path, mut read: F) -> io::Result<T>
+    where
+        F: FnMut() -> io::Result<T>,
+    {
+        let mut attempt = 0;
+        loop {
+            match read() {
+                Err(e) => match self.backoff(path, &e, attempt) {
+                    Some(delay) => thread::sleep(delay),
+                    None => return Err(e),
+                },
+                read => return read,
+            }
+            attempt += 1;
+        }
+    }
+
+    /// the wait before retrying a read that failed with e for the attempt-th time,
+    /// None if e is not transient or no retries are left
+    fn backoff(&self, path: &Path, e: &io::Error, attempt: usize) -> Option<Duration> {
+        if !transient(e) || attempt >= self.retries {
+            return None;
+        }
+        let delay = self.backoff * (1 << attempt.min(16));
+        warn!(file = %path.display(), error = %e, attempt = attempt + 1, ?delay, "read failed, retrying");
+        Some(delay)
+    }
+
+    /// None for a file still failing with a transient error after the retries
+    /// when lenient, the error otherwise
+    fn skip_unreadable<T>(&self, path: &Path, read: io::Result<T>) -> io::Result<Option<T>> {
+        match read {
+            Err(e) if transient(&e) && self.lenient => {
+                warn!(file = %path.display(), error = %e, "skipping unreadable file");
+                self.skipped.fetch_add(1, Ordering::Relaxed);
+                Ok(None)
+            }
+            read => read.map(Some),
+        }
+    }
+
+    /// the document parsed from path by parse, failing unless lenient if any of
+    /// its tokens lack a lemma or tag. those are filled in while parsing
+    pub fn check<T, F>(&self, path: &Path, parse: F) -> io::Result<T>
+    where
+        F: FnOnce() -> io::Result<T>,
+    {
+        MISSING.with(|missing| missing.set((0, 0)));
+        let doc = parse()?;
+        let (lemmas, tags) = MISSING.with(Cell::take);
+        if lemmas + tags == 0 {
+            return Ok(doc);
+        }
+        if !self.lenient {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "{}: {} tokens without a lemma and {} without a POS tag, see --lenient",
+                    path.display(),
+                    lemmas,
+                    tags
+                ),
+            ));
+        }
+        warn!(file = %path.display(), lemmas, tags, "filled in missing token fields");
+        self.repaired.fetch_add(1, Ordering::Relaxed);
+        Ok(doc)
+    }
+
+    /// the id of the document doc read from path, see `ids`
+    fn named(&self, path: &Path, doc: &HulthDocument) -> String {
+        self.ids.id(document_name(path), path.display(), doc)
+    }
+
+    /// the id of the document listed as name by a source, read from origin
+    fn named_in(&self, name: &str, origin: &str, doc: &HulthDocument) -> String {
+        self.ids.id(document_name(Path::new(name)), origin, doc)
+    }
+
+    /// the id of the document at path without keeping the document,
+    /// only read if ids are hashed
+    pub fn document_id(&self, path: &Path) -> io::Result<String> {
+        if !self.ids.hashed() {
+            return Ok(document_name(path));
+        }
+        Ok(self.named(path, &self.read_document(path)?))
+    }
+}
+
+fn empty(path: &Path) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("{}: empty document", path.display()),
+    )
+}
+
+/// errors of reads that may succeed when tried again, as on network filesystems
+fn transient(e: &io::Error) -> bool {
+    #[cfg(unix)]
+    if e.raw_os_error() == Some(EIO) {
+        return true;
+    }
+    matches!(
+        e.kind(),
+        io::ErrorKind::Interrupted
+            | io::ErrorKind::WouldBlock
+            | io::ErrorKind::TimedOut
+            | io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::NotConnected
+            | io::ErrorKind::BrokenPipe
+            | io::ErrorKind::ResourceBusy
+            | io::ErrorKind::StaleNetworkFileHandle
+    )
+}
+
+/// counts a token read without a lemma or without a tag
+pub fn count_missing(lemma: bool, pos: bool) {
+    MISSING.with(|missing| {
+        let (lemmas, tags) = missing.get();
+        missing.set((lemmas + usize::from(lemma), tags + usize::from(pos)));
+    });
+}
+
+#[cfg(not(feature = "simd-json"))]
+fn parse_document(file: &fs::File) -> io::Result<HulthDocument> {
+    // SAFETY: the map is only read while the file is open and unchanged
+    let json = unsafe { Mmap::map(file)? };
+    parse_bytes(&json)
+}
+
+/// parses a document the way files of a dataset are parsed
+#[cfg(not(feature = "simd-json"))]
+pub fn parse_bytes(json: &[u8]) -> io::Result<HulthDocument> {
+    Ok(serde_json::from_slice(json)?)
+}
+
+/// simd-json parses in place, so the map is private copy-on-write
+#[cfg(feature = "simd-json")]
+fn parse_document(file: &fs::File) -> io::Result<HulthDocument> {
+    // SAFETY: the map is only used while the file is open and unchanged,
+    // writes go to private pages and never reach the file
+    let mut json = unsafe { MmapOptions::new().map_copy(file)? };
+    simd_json::serde::from_slice(&mut json)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// parses a copy of json, simd-json needs to write to it
+#[cfg(feature = "simd-json")]
+pub fn parse_bytes(json: &[u8]) -> io::Result<HulthDocument> {
+    simd_json::serde::from_slice(&mut json.to_vec())
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// the name of the document at path, its file name without `.json`
+pub fn document_name(path: &Path) -> String {
+    path.file_name()
+        .unwrap_or_default()
+        .to_str()
+        .unwrap_or_default()
+        .replace(".json", "")
+}
+
+/// iterates over all files in directory non-recursively
+/// and applies f
+/// returns an Err on first Err returned from f
+pub fn for_each_file<P, F>(path: P, reading: &Reading, mut f: F) -> io::Result<()>
+where
+    P: AsRef<Path>,
+    F: FnMut(&Path) -> io::Result<()>,
+{
+    let path = path.as_ref();
+    for entry in reading.retrying(path, || fs::read_dir(path))? {
+        let entry = entry?;
+        let path = entry.path();
+        if path.is_dir() {
+            continue;
+        } else {
+            f(&path)?
+        }
+    }
+    Ok(())
+}
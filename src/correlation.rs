@@ -0,0 +1,252 @@
+//! rank correlations, between the rankings of two extractors
+//! and between a ranking and the gold relevance of its terms
+use crate::matching::References;
+use crate::metrics::{mean, Mean};
+use crate::results::DocumentResult;
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::sync::Arc;
+
+/// Kendall's tau-b of the paired values, None for fewer than two pairs
+/// or if either side is constant
+pub fn kendall(x: &[f64], y: &[f64]) -> Option<f64> {
+    let n = x.len().min(y.len());
+    let (mut concordant, mut discordant, mut ties_x, mut ties_y) = (0f64, 0f64, 0f64, 0f64);
+    for i in 0..n {
+        for j in i + 1..n {
+            let dx = x[i].partial_cmp(&x[j]).unwrap_or(Ordering::Equal);
+            let dy = y[i].partial_cmp(&y[j]).unwrap_or(Ordering::Equal);
+            match (dx, dy) {
+                (Ordering::Equal, Ordering::Equal) => {}
+                (Ordering::Equal, _) => ties_x += 1.0,
+                (_, Ordering::Equal) => ties_y += 1.0,
+                _ if dx == dy => concordant += 1.0,
+                _ => discordant += 1.0,
+            }
+        }
+    }
+    let denominator =
+        ((concordant + discordant + ties_x) * (concordant + discordant + ties_y)).sqrt();
+    if denominator == 0.0 {
+        return None;
+    }
+    Some((concordant - discordant) / denominator)
+}
+
+/// Spearman's rho of the paired values, the Pearson correlation of their ranks
+/// with tied values sharing their average rank
+pub fn spearman(x: &[f64], y: &[f64]) -> Option<f64> {
+    let n = x.len().min(y.len());
+    if n < 2 {
+        return None;
+    }
+    let (rx, ry) = (ranks(&x[..n]), ranks(&y[..n]));
+    let mean = (n as f64 + 1.0) / 2.0;
+    let (mut covariance, mut var_x, mut var_y) = (0.0, 0.0, 0.0);
+    for (a, b) in rx.iter().zip(&ry) {
+        covariance += (a - mean) * (b - mean);
+        var_x += (a - mean).powi(2);
+        var_y += (b - mean).powi(2);
+    }
+    if var_x == 0.0 || var_y == 0.0 {
+        return None;
+    }
+    Some(covariance / (var_x * var_y).sqrt())
+}
+
+/// 1-based ranks of values, ties get the average of their ranks
+fn ranks(values: &[f64]) -> Vec<f64> {
+    let mut order = (0..values.len()).collect::<Vec<_>>();
+    order.sort_by(|a, b| {
+        values[*a]
+            .partial_cmp(&values[*b])
+            .unwrap_or(Ordering::Equal)
+    });
+    let mut ranks = vec![0.0; values.len()];
+    let mut start = 0;
+    while start < order.len() {
+        let mut end = start + 1;
+        while end < order.len() && values[order[end]] == values[order[start]] {
+            end += 1;
+        }
+        let rank = (start + end + 1) as f64 / 2.0;
+        for i in &order[start..end] {
+            ranks[*i] = rank;
+        }
+        start = end;
+    }
+    ranks
+}
+
+/// Spearman's rho between the scores of the ranked terms and their relevance,
+/// 1 for terms matching a reference and 0 otherwise. None if all or none match
+pub fn relevance(ranked: &[(Arc<str>, f64)], reference: &References) -> Option<f64> {
+    let (scores, relevant): (Vec<f64>, Vec<f64>) = ranked
+        .iter()
+        .map(|(term, score)| (*score, reference.contains(term) as u8 as f64))
+        .unzip();
+    spearman(&scores, &relevant)
+}
+
+/// the mean correlation of scores and relevance over the documents it is defined for
+pub fn print_relevance<W: Write>(mut w: W, measures: &[DocumentResult]) -> io::Result<()> {
+    let correlations = measures
+        .iter()
+        .filter_map(|m| m.relevance)
+        .collect::<Vec<_>>();
+    writeln!(
+        w,
+        "{:<10}  {:>9}  {:>9}",
+        "relevance", "documents", "spearman"
+    )?;
+    writeln!(
+        w,
+        "{:<10}  {:>9}  {:>9}",
+        "scores",
+        correlations.len(),
+        mean(&correlations).format()
+    )
+}
+
+/// the mean correlations of the rankings two extractors gave the same documents
+#[derive(Debug)]
+pub struct Agreement {
+    pub(crate) dataset: String,
+    pub(crate) first: String,
+    pub(crate) second: String,
+    pub(crate) documents: usize,
+    pub(crate) kendall: Mean,
+    pub(crate) spearman: Mean,
+}
+
+/// compares the rankings of every document in both runs over the terms both
+/// ranked, top-k cutoffs leave only the terms kept by both. documents with
+/// fewer than two such terms or without any order among them are left out
+pub fn agreement(
+    dataset: &str,
+    (first, a): (&str, &[DocumentResult]),
+    (second, b): (&str, &[DocumentResult]),
+) -> Agreement {
+    let b = b
+        .iter()
+        .map(|m| (m.document.as_str(), m))
+        .collect::<HashMap<_, _>>();
+    let (mut kendalls, mut spearmans) = (vec![], vec![]);
+    for measure in a {
+        let other = match b.get(measure.document.as_str()) {
+            Some(other) => other,
+            None => continue,
+        };
+        let positions = other
+            .ranking
+            .iter()
+            .enumerate()
+            .map(|(i, term)| (&**term, i as f64))
+            .collect::<HashMap<_, _>>();
+        let (x, y): (Vec<f64>, Vec<f64>) = measure
+            .ranking
+            .iter()
+            .enumerate()
+            .filter_map(|(i, term)| positions.get(&**term).map(|j| (i as f64, *j)))
+            .unzip();
+        if let (Some(tau), Some(rho)) = (kendall(&x, &y), spearman(&x, &y)) {
+            kendalls.push(tau);
+            spearmans.push(rho);
+        }
+    }
+    Agreement {
+        dataset: dataset.into(),
+        first: first.into(),
+        second: second.into(),
+        documents: kendalls.len(),
+        kendall: mean(&kendalls),
+        spearman: mean(&spearmans),
+    }
+}
+
+pub fn print_agreements<W: Write>(mut w: W, agreements: &[Agreement]) -> io::Result<()> {
+    let width = |f: fn(&Agreement) -> &str, header: &str| {
+        agreements
+            .iter()
+            .map(|a| f(a).len())
+            .chain(Some(header.len()))
+            .max()
+            .unwrap_or_default()
+    };
+    let dw = width(|a| &a.dataset, "dataset");
+    let fw = width(|a| &a.first, "extractor");
+    let sw = width(|a| &a.second, "extractor");
+    writeln!(
+        w,
+        "{:<dw$}  {:<fw$}  {:<sw$}  {:>9}  {:>9}  {:>9}",
+        "dataset", "extractor", "extractor", "documents", "kendall", "spearman",
+    )?;
+    for a in agreements {
+        writeln!(
+            w,
+            "{:<dw$}  {:<fw$}  {:<sw$}  {:>9}  {:>9}  {:>9}",
+            a.dataset,
+            a.first,
+            a.second,
+            a.documents,
+            a.kendall.format(),
+            a.spearman.format(),
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: Option<f64>, b: f64) -> bool {
+        a.is_some_and(|a| (a - b).abs() < 1e-9)
+    }
+
+    #[test]
+    fn kendall_counts_concordant_and_discordant_pairs() {
+        // two concordant pairs and one discordant of three
+        assert!(close(
+            kendall(&[1.0, 2.0, 3.0], &[1.0, 3.0, 2.0]),
+            1.0 / 3.0
+        ));
+        assert!(close(kendall(&[1.0, 2.0, 3.0], &[3.0, 2.0, 1.0]), -1.0));
+    }
+
+    #[test]
+    fn kendall_corrects_for_ties() {
+        // five concordant pairs, one tied in x only: 5 / sqrt(6 * 5)
+        let tau = kendall(&[1.0, 2.0, 2.0, 3.0], &[1.0, 2.0, 3.0, 4.0]);
+        assert!(close(tau, 5.0 / 30f64.sqrt()));
+    }
+
+    #[test]
+    fn spearman_correlates_ranks() {
+        // rank differences 0, 1, 1: 1 - 6 * 2 / (3 * 8)
+        assert!(close(spearman(&[1.0, 2.0, 3.0], &[1.0, 3.0, 2.0]), 0.5));
+        assert!(close(spearman(&[1.0, 5.0, 9.0], &[10.0, 20.0, 300.0]), 1.0));
+    }
+
+    #[test]
+    fn spearman_averages_tied_ranks() {
+        // ranks 1, 2.5, 2.5, 4 against 1, 2, 3, 4: 4.5 / sqrt(4.5 * 5)
+        let rho = spearman(&[1.0, 2.0, 2.0, 3.0], &[1.0, 2.0, 3.0, 4.0]);
+        assert!(close(rho, 4.5 / 22.5f64.sqrt()));
+        assert_eq!(ranks(&[3.0, 1.0, 3.0, 3.0]), vec![3.0, 1.0, 3.0, 3.0]);
+    }
+
+    #[test]
+    fn constant_or_short_input_has_no_correlation() {
+        let constant = [2.0, 2.0, 2.0];
+        let varying = [1.0, 2.0, 3.0];
+        assert_eq!(kendall(&constant, &varying), None);
+        assert_eq!(kendall(&varying, &constant), None);
+        assert_eq!(spearman(&constant, &varying), None);
+        assert_eq!(spearman(&varying, &constant), None);
+        assert_eq!(kendall(&[1.0], &[1.0]), None);
+        assert_eq!(spearman(&[1.0], &[1.0]), None);
+    }
+}
@@ -0,0 +1,144 @@
+//! per-candidate features of ranked documents as CSV rows,
+//! for training rerankers on the candidates of the harness
+use crate::weighting::DocumentFrequencies;
+use crate::HulthDocument;
+
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Write};
+
+const HEADER: [&str; 10] = [
+    "document",
+    "term",
+    "tf",
+    "idf",
+    "tfidf",
+    "first_position",
+    "phrase_length",
+    "pos_pattern",
+    "in_title",
+    "label",
+];
+
+/// the names of the numeric features, in the order of `Features::values`
+pub const NUMERIC: [&str; 7] = [
+    "tf",
+    "idf",
+    "tfidf",
+    "first_position",
+    "phrase_length",
+    "noun_ratio",
+    "in_title",
+];
+
+/// the features of one candidate of a document
+#[derive(Debug)]
+pub struct Features {
+    pub(crate) tf: usize,
+    pub(crate) idf: f64,
+    pub(crate) tfidf: f64,
+    /// offset of the first occurrence relative to the document length
+    pub(crate) first_position: f64,
+    pub(crate) phrase_length: usize,
+    /// the tags of the first occurrence, separated by spaces
+    pub(crate) pos_pattern: String,
+    pub(crate) in_title: bool,
+}
+
+impl Features {
+    /// the features as numbers, named by `NUMERIC`.
+    /// the POS pattern becomes the share of its tags that are nouns
+    pub fn values(&self) -> [f64; 7] {
+        let tags = self.pos_pattern.split(' ').filter(|t| !t.is_empty());
+        let nouns = tags.clone().filter(|t| t.starts_with("NN")).count();
+        let noun_ratio = nouns as f64 / tags.count().max(1) as f64;
+        [
+            self.tf as f64,
+            self.idf,
+            self.tfidf,
+            self.first_position,
+            self.phrase_length as f64,
+            noun_ratio,
+            self.in_title as u8 as f64,
+        ]
+    }
+}
+
+/// the features of every ranked term of doc, in ranked order
+pub fn extract(
+    doc: &HulthDocument,
+    ranked: &[(String, f64)],
+    frequencies: &DocumentFrequencies,
+) -> Vec<Features> {
+    let tokens = doc.sentences.iter().flat_map(|s| s.tokens.iter());
+    let length = tokens
+        .clone()
+        .map(|t| t.offset_end)
+        .max()
+        .unwrap_or(0)
+        .max(1) as f64;
+    let mut occurrences = HashMap::new();
+    for token in tokens {
+        let entry = occurrences.entry(&*token.word).or_insert((0, token));
+        entry.0 += 1;
+    }
+    let title = doc
+        .sentences
+        .first()
+        .map(|s| s.tokens.iter().map(|t| &*t.word).collect::<HashSet<_>>())
+        .unwrap_or_default();
+
+    ranked
+        .iter()
+        .map(|(term, score)| {
+            let (tf, first) = match occurrences.get(term.as_str()) {
+                Some((tf, first)) => (*tf, Some(*first)),
+                None => (0, None),
+            };
+            Features {
+                tf,
+                idf: frequencies.idf(term),
+                tfidf: *score,
+                first_position: first.map_or(1.0, |t| t.offset_begin.max(0) as f64 / length),
+                phrase_length: term.split(' ').count(),
+                pos_pattern: first.map(|t| t.pos.to_string()).unwrap_or_default(),
+                in_title: title.contains(term.as_str()),
+            }
+        })
+        .collect()
+}
+
+pub fn write_header<W: Write>(mut w: W) -> io::Result<()> {
+    writeln!(w, "{}", HEADER.join(","))
+}
+
+pub fn write_row<W: Write>(
+    mut w: W,
+    document: &str,
+    term: &str,
+    features: &Features,
+    label: bool,
+) -> io::Result<()> {
+    writeln!(
+        w,
+        "{},{},{},{},{},{},{},{},{},{}",
+        quote(document),
+        quote(term),
+        features.tf,
+        features.idf,
+        features.tfidf,
+        features.first_position,
+        features.phrase_length,
+        quote(&features.pos_pattern),
+        features.in_title as u8,
+        label as u8
+    )
+}
+
+/// a CSV field, quoted if it contains a separator, quote or line break
+pub(crate) fn quote(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
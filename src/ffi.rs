@@ -0,0 +1,190 @@
+//! C API of the `ffi` feature for embedding the extractor, declared in
+//! `include/hulth_eval.h` which build.rs regenerates with cbindgen.
+//! failing calls return NULL and leave a message for `hulth_last_error`
+use crate::model::Model;
+use crate::preprocess::Normalizer;
+use crate::scoring::{Extractor, Scoring};
+use crate::{text, HulthToken};
+
+use lib_tfidf::{Document, Tfidf};
+
+use std::cell::RefCell;
+use std::ffi::{CStr, CString};
+use std::io;
+use std::mem::ManuallyDrop;
+use std::os::raw::c_char;
+use std::ptr;
+use std::slice;
+
+type Docs = [Box<dyn Document<String, HulthToken>>];
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// a saved model fitted for ranking, opaque to C
+pub struct HulthExtractor {
+    /// borrows docs, so it is dropped first
+    tfidf: ManuallyDrop<Tfidf<'static, String, HulthToken>>,
+    docs: *mut Docs,
+    model: Model,
+    normalizer: Normalizer,
+    scoring: Scoring,
+}
+
+impl Drop for HulthExtractor {
+    fn drop(&mut self) {
+        // SAFETY: docs came from Box::into_raw and tfidf, the only borrower, is gone
+        unsafe {
+            ManuallyDrop::drop(&mut self.tfidf);
+            drop(Box::from_raw(self.docs));
+        }
+    }
+}
+
+#[repr(C)]
+pub struct HulthKeyword {
+    /// NUL-terminated UTF-8
+    pub term: *mut c_char,
+    pub score: f64,
+}
+
+/// ranked keywords, best first
+#[repr(C)]
+pub struct HulthKeywords {
+    pub keywords: *mut HulthKeyword,
+    pub len: usize,
+}
+
+fn fail<T>(e: impl ToString) -> *mut T {
+    let message = CString::new(e.to_string().replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+    ptr::null_mut()
+}
+
+impl HulthExtractor {
+    fn load(path: &str) -> io::Result<Self> {
+        let model = Model::load(path)?;
+        let normalizer = model.preprocessing.normalizer()?;
+        let docs = Box::into_raw(model.corpus.boxed().into_boxed_slice());
+        // SAFETY: docs stays allocated until the extractor is dropped, after tfidf
+        let tfidf = Tfidf::new(unsafe { &*docs });
+        let mut extractor = HulthExtractor {
+            tfidf: ManuallyDrop::new(tfidf),
+            docs,
+            model,
+            normalizer,
+            scoring: Scoring::default(),
+        };
+        extractor.tfidf.fit_transform()?;
+        Ok(extractor)
+    }
+
+    fn rank(&self, input: &str, top_k: usize) -> io::Result<Vec<(String, f64)>> {
+        let doc = self.normalizer.apply(&text::parse_document(input)?);
+        let scores = self
+            .tfidf
+            .rank_tokens(&doc.get_content())?
+            .into_iter()
+            .collect();
+        let scores = self.model.preprocessing.resolve(&doc, scores);
+        let mut ranked = self.scoring.rank(Extractor::Tfidf, None, &doc, scores);
+        ranked.truncate(top_k);
+        Ok(ranked)
+    }
+}
+
+/// loads and fits the model written by `hulth-eval eval --save-model` to path,
+/// NULL on failure. free it with `hulth_extractor_free`
+///
+/// # Safety
+/// path must be a NUL-terminated string
+#[no_mangle]
+pub unsafe extern "C" fn hulth_extractor_load(path: *const c_char) -> *mut HulthExtractor {
+    if path.is_null() {
+        return fail("path is NULL");
+    }
+    let path = match CStr::from_ptr(path).to_str() {
+        Ok(path) => path,
+        Err(e) => return fail(e),
+    };
+    match HulthExtractor::load(path) {
+        Ok(extractor) => Box::into_raw(Box::new(extractor)),
+        Err(e) => fail(e),
+    }
+}
+
+/// # Safety
+/// extractor must come from `hulth_extractor_load` and not be used afterwards, NULL is ignored
+#[no_mangle]
+pub unsafe extern "C" fn hulth_extractor_free(extractor: *mut HulthExtractor) {
+    if !extractor.is_null() {
+        drop(Box::from_raw(extractor));
+    }
+}
+
+/// ranks the UTF-8 document of len bytes at text, raw or Hulth JSON, and returns
+/// its best top_k keywords, NULL on failure. free them with `hulth_keywords_free`
+///
+/// # Safety
+/// extractor must come from `hulth_extractor_load` and text point to len readable bytes
+#[no_mangle]
+pub unsafe extern "C" fn hulth_rank(
+    extractor: *const HulthExtractor,
+    text: *const u8,
+    len: usize,
+    top_k: usize,
+) -> *mut HulthKeywords {
+    if extractor.is_null() || (text.is_null() && len > 0) {
+        return fail("extractor or text is NULL");
+    }
+    let bytes = match len {
+        0 => &[][..],
+        _ => slice::from_raw_parts(text, len),
+    };
+    let input = match std::str::from_utf8(bytes) {
+        Ok(input) => input,
+        Err(e) => return fail(e),
+    };
+    let ranked = match (*extractor).rank(input, top_k) {
+        Ok(ranked) => ranked,
+        Err(e) => return fail(e),
+    };
+    let keywords = ranked
+        .into_iter()
+        .map(|(term, score)| HulthKeyword {
+            term: CString::new(term).unwrap_or_default().into_raw(),
+            score,
+        })
+        .collect::<Box<[_]>>();
+    let len = keywords.len();
+    let keywords = Box::into_raw(keywords) as *mut HulthKeyword;
+    Box::into_raw(Box::new(HulthKeywords { keywords, len }))
+}
+
+/// # Safety
+/// keywords must come from `hulth_rank` and not be used afterwards, NULL is ignored
+#[no_mangle]
+pub unsafe extern "C" fn hulth_keywords_free(keywords: *mut HulthKeywords) {
+    if keywords.is_null() {
+        return;
+    }
+    let keywords = Box::from_raw(keywords);
+    let entries = Box::from_raw(ptr::slice_from_raw_parts_mut(
+        keywords.keywords,
+        keywords.len,
+    ));
+    for keyword in entries.iter() {
+        drop(CString::from_raw(keyword.term));
+    }
+}
+
+/// the message of the last failed call on this thread, NULL if none.
+/// valid until the next failing call on the thread
+#[no_mangle]
+pub extern "C" fn hulth_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| match &*last.borrow() {
+        Some(message) => message.as_ptr(),
+        None => ptr::null(),
+    })
+}
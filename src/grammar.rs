@@ -0,0 +1,220 @@
+//! patterns over POS tags selecting the phrase candidates of the chunking stage.
+//! a pattern is a sequence of tags separated by spaces, `(a|b)` groups
+//! alternatives and `*`, `+` and `?` repeat the item before them.
+//! a tag matches every tag it is a prefix of, `NN` matches `NNS` and `NNP`,
+//! e.g. `(JJ|NN)* NN` are nouns preceded by any adjectives and nouns
+use std::collections::BTreeSet;
+use std::convert::TryFrom;
+use std::fmt;
+use std::iter::Peekable;
+use std::str::{CharIndices, FromStr};
+
+pub const DEFAULT_PATTERN: &str = "(JJ|NN)* NN";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+pub struct Pattern {
+    source: String,
+    node: Node,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Node {
+    Tag(String),
+    Seq(Vec<Node>),
+    Alt(Vec<Node>),
+    Repeat(Box<Node>, Quantifier),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Quantifier {
+    Star,
+    Plus,
+    Optional,
+}
+
+impl Pattern {
+    /// the end of the longest match of tags starting at start
+    /// of at most max_len tags, if it is not empty
+    pub fn longest_match(&self, tags: &[&str], start: usize, max_len: usize) -> Option<usize> {
+        self.node
+            .ends(tags, start)
+            .into_iter()
+            .rev()
+            .find(|end| *end - start <= max_len)
+            .filter(|end| *end > start)
+    }
+}
+
+impl Node {
+    /// every position a match starting at start can end at
+    fn ends(&self, tags: &[&str], start: usize) -> BTreeSet<usize> {
+        match self {
+            Node::Tag(tag) => tags
+                .get(start)
+                .filter(|t| t.starts_with(tag.as_str()))
+                .map(|_| start + 1)
+                .into_iter()
+                .collect(),
+            Node::Seq(nodes) => nodes
+                .iter()
+                .fold(Some(start).into_iter().collect(), |at, node| {
+                    at.iter().flat_map(|p| node.ends(tags, *p)).collect()
+                }),
+            Node::Alt(nodes) => nodes.iter().flat_map(|n| n.ends(tags, start)).collect(),
+            Node::Repeat(node, quantifier) => {
+                let mut ends = BTreeSet::new();
+                if *quantifier != Quantifier::Plus {
+                    ends.insert(start);
+                }
+                let mut seen = BTreeSet::new();
+                seen.insert(start);
+                let mut frontier = vec![start];
+                while let Some(p) = frontier.pop() {
+                    for end in node.ends(tags, p) {
+                        ends.insert(end);
+                        if *quantifier != Quantifier::Optional && seen.insert(end) {
+                            frontier.push(end);
+                        }
+                    }
+                }
+                ends
+            }
+        }
+    }
+}
+
+impl FromStr for Pattern {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser {
+            source: s,
+            chars: s.char_indices().peekable(),
+        };
+        let node = parser.alt()?;
+        match parser.peek() {
+            None => Ok(Pattern {
+                source: s.into(),
+                node,
+            }),
+            Some((i, c)) => Err(parser.error(i, &format!("unexpected {}", c))),
+        }
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+impl TryFrom<String> for Pattern {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<Pattern> for String {
+    fn from(pattern: Pattern) -> String {
+        pattern.source
+    }
+}
+
+/// recursive descent over the characters of a pattern
+struct Parser<'a> {
+    source: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+}
+
+impl Parser<'_> {
+    /// the next character that is not whitespace
+    fn peek(&mut self) -> Option<(usize, char)> {
+        while let Some((_, c)) = self.chars.peek() {
+            if !c.is_whitespace() {
+                break;
+            }
+            self.chars.next();
+        }
+        self.chars.peek().copied()
+    }
+
+    fn alt(&mut self) -> Result<Node, String> {
+        let mut alternatives = vec![self.seq()?];
+        while let Some((_, '|')) = self.peek() {
+            self.chars.next();
+            alternatives.push(self.seq()?);
+        }
+        Ok(match alternatives.len() {
+            1 => alternatives.remove(0),
+            _ => Node::Alt(alternatives),
+        })
+    }
+
+    fn seq(&mut self) -> Result<Node, String> {
+        let mut items = vec![];
+        while let Some((i, c)) = self.peek() {
+            if c == '|' || c == ')' {
+                break;
+            }
+            items.push(self.item(i, c)?);
+        }
+        match items.len() {
+            0 => {
+                let at = self.position();
+                Err(self.error(at, "expected a tag or group"))
+            }
+            1 => Ok(items.remove(0)),
+            _ => Ok(Node::Seq(items)),
+        }
+    }
+
+    fn item(&mut self, i: usize, c: char) -> Result<Node, String> {
+        let atom = match c {
+            '(' => {
+                self.chars.next();
+                let group = self.alt()?;
+                match self.peek() {
+                    Some((_, ')')) => {
+                        self.chars.next();
+                        group
+                    }
+                    _ => return Err(self.error(i, "unclosed group")),
+                }
+            }
+            c if is_tag_char(c) => {
+                let mut tag = String::new();
+                while let Some((_, c)) = self.chars.peek().copied().filter(|(_, c)| is_tag_char(*c))
+                {
+                    tag.push(c);
+                    self.chars.next();
+                }
+                Node::Tag(tag)
+            }
+            c => return Err(self.error(i, &format!("unexpected {}", c))),
+        };
+        let quantifier = match self.chars.peek() {
+            Some((_, '*')) => Quantifier::Star,
+            Some((_, '+')) => Quantifier::Plus,
+            Some((_, '?')) => Quantifier::Optional,
+            _ => return Ok(atom),
+        };
+        self.chars.next();
+        Ok(Node::Repeat(Box::new(atom), quantifier))
+    }
+
+    fn position(&mut self) -> usize {
+        self.peek().map_or(self.source.len(), |(i, _)| i)
+    }
+
+    fn error(&self, at: usize, message: &str) -> String {
+        format!("pattern {:?} at {}: {}", self.source, at, message)
+    }
+}
+
+/// Penn Treebank tags are upper case letters, with `$` as in `PRP$`
+fn is_tag_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '$' || c == '-' || c == '_'
+}
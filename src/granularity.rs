@@ -0,0 +1,63 @@
+//! the units the corpus is fitted on. with sentences as documents the idf
+//! counts sentences containing a term and a document is scored sentence by sentence
+use crate::{HulthDocument, HulthToken};
+
+use clap::ValueEnum;
+use lib_tfidf::{Document, Tfidf};
+
+use std::collections::HashMap;
+use std::io;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Granularity {
+    /// every document of the dataset is a document of the corpus
+    #[default]
+    Document,
+    /// every sentence of the dataset is a document of the corpus
+    Sentence,
+}
+
+/// how the scores a term gets in the sentences of a document are combined
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Pooling {
+    /// the sum, for raw tf weighting the term frequency of the document times the idf
+    #[default]
+    Sum,
+    /// the best score of the term in any sentence
+    Max,
+}
+
+/// the scores of the terms of doc, for sentence granularity
+/// the sentence scores pooled per term
+pub fn rank_tokens(
+    tfidf: &Tfidf<String, HulthToken>,
+    doc: &HulthDocument,
+    granularity: Granularity,
+    pooling: Pooling,
+) -> io::Result<HashMap<String, f64>> {
+    if granularity == Granularity::Document {
+        return Ok(tfidf.rank_tokens(&doc.get_content())?);
+    }
+    let mut scores = HashMap::new();
+    for sentence in &doc.sentences {
+        let tokens = sentence
+            .tokens
+            .iter()
+            .cloned()
+            .map(Box::new)
+            .collect::<Vec<_>>();
+        for (term, score) in tfidf.rank_tokens(&tokens)? {
+            let pooled = scores.entry(term).or_insert(match pooling {
+                Pooling::Sum => 0f64,
+                Pooling::Max => score,
+            });
+            match pooling {
+                Pooling::Sum => *pooled += score,
+                Pooling::Max => *pooled = pooled.max(score),
+            }
+        }
+    }
+    Ok(scores)
+}
@@ -0,0 +1,98 @@
+//! gRPC service of the `serve-grpc` subcommand, generated from `proto/hulth.proto`.
+//! the fitted model can't be shared across threads, so the service hands every
+//! document to the thread owning it as a Job and awaits the reply
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::sync::{mpsc, oneshot};
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::transport::Server;
+use tonic::{Request, Response, Status, Streaming};
+
+use std::io;
+use std::net::SocketAddr;
+
+pub mod proto {
+    tonic::include_proto!("hulth");
+}
+
+use proto::extractor_server::{Extractor, ExtractorServer};
+use proto::{Document, RankedKeywords};
+
+/// a document to rank and where to send its keywords
+#[derive(Debug)]
+pub struct Job {
+    pub document: Document,
+    pub reply: oneshot::Sender<Result<RankedKeywords, Status>>,
+}
+
+#[derive(Debug)]
+struct Service {
+    jobs: mpsc::Sender<Job>,
+}
+
+impl Service {
+    async fn submit(
+        jobs: &mpsc::Sender<Job>,
+        document: Document,
+    ) -> Result<RankedKeywords, Status> {
+        let (reply, ranked) = oneshot::channel();
+        jobs.send(Job { document, reply })
+            .await
+            .map_err(|_| Status::unavailable("shutting down"))?;
+        ranked
+            .await
+            .map_err(|_| Status::internal("document was dropped"))?
+    }
+}
+
+#[tonic::async_trait]
+impl Extractor for Service {
+    async fn rank(&self, request: Request<Document>) -> Result<Response<RankedKeywords>, Status> {
+        Service::submit(&self.jobs, request.into_inner())
+            .await
+            .map(Response::new)
+    }
+
+    type RankBatchStream = ReceiverStream<Result<RankedKeywords, Status>>;
+
+    async fn rank_batch(
+        &self,
+        request: Request<Streaming<Document>>,
+    ) -> Result<Response<Self::RankBatchStream>, Status> {
+        let mut documents = request.into_inner();
+        let jobs = self.jobs.clone();
+        let (replies, stream) = mpsc::channel(16);
+        tokio::spawn(async move {
+            loop {
+                let reply = match documents.message().await {
+                    Ok(Some(document)) => Service::submit(&jobs, document).await,
+                    Ok(None) => break,
+                    Err(status) => Err(status),
+                };
+                let failed = reply.is_err();
+                // the client hung up or the stream broke, either way nothing more to send
+                if replies.send(reply).await.is_err() || failed {
+                    break;
+                }
+            }
+        });
+        Ok(Response::new(ReceiverStream::new(stream)))
+    }
+}
+
+/// serves the extractor on addr, sending documents to jobs until SIGTERM or SIGINT
+pub async fn serve(addr: SocketAddr, jobs: mpsc::Sender<Job>) -> io::Result<()> {
+    let mut terminate = signal(SignalKind::terminate())?;
+    let mut interrupt = signal(SignalKind::interrupt())?;
+    let shutdown = async move {
+        tokio::select! {
+            _ = terminate.recv() => {}
+            _ = interrupt.recv() => {}
+        }
+        info!("shutting down");
+    };
+    Server::builder()
+        .add_service(ExtractorServer::new(Service { jobs }))
+        .serve_with_shutdown(addr, shutdown)
+        .await
+        .map_err(io::Error::other)
+}
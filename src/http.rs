@@ -0,0 +1,137 @@
+//! a minimal HTTP/1.0 client without TLS, for lemmatizers and corpus
+//! indexes running next to the evaluation
+use std::io::{self, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+/// the wait for a connection to an address of the host
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// the wait for every read and write once connected
+const IO_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// the body of the answer to a GET of url
+pub fn get(url: &str) -> io::Result<Vec<u8>> {
+    request("GET", url, None)
+}
+
+/// the body of the answer to a POST of a JSON body to url
+pub fn post_json(url: &str, body: &[u8]) -> io::Result<Vec<u8>> {
+    request("POST", url, Some(body))
+}
+
+fn request(method: &str, url: &str, body: Option<&[u8]>) -> io::Result<Vec<u8>> {
+    let rest = url.strip_prefix("http://").ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{}: only http:// URLs are supported", url),
+        )
+    })?;
+    let (host, path) = match rest.find('/') {
+        Some(i) => (&rest[..i], &rest[i..]),
+        None => (rest, "/"),
+    };
+    let address = if host.contains(':') {
+        host.to_string()
+    } else {
+        format!("{}:80", host)
+    };
+    let mut stream = connect(&address)?;
+    stream.set_read_timeout(Some(IO_TIMEOUT))?;
+    stream.set_write_timeout(Some(IO_TIMEOUT))?;
+    write!(stream, "{} {} HTTP/1.0\r\nHost: {}\r\n", method, path, host)?;
+    if let Some(body) = body {
+        write!(
+            stream,
+            "Content-Type: application/json\r\nContent-Length: {}\r\n",
+            body.len()
+        )?;
+    }
+    stream.write_all(b"\r\n")?;
+    stream.write_all(body.unwrap_or_default())?;
+    let mut response = vec![];
+    stream.read_to_end(&mut response)?;
+    let split = response
+        .windows(4)
+        .position(|w| w == b"\r\n\r\n")
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "malformed HTTP response"))?;
+    let head = String::from_utf8_lossy(&response[..split]);
+    let status = head.lines().next().unwrap_or_default();
+    let code = status.split_whitespace().nth(1).unwrap_or_default();
+    if code != "200" {
+        return Err(io::Error::new(
+            status_kind(code),
+            format!("{} answered {}", url, status),
+        ));
+    }
+    Ok(response.split_off(split + 4))
+}
+
+/// the first address of host:port accepting a connection within the timeout
+fn connect(address: &str) -> io::Result<TcpStream> {
+    let mut last = None;
+    for address in address.to_socket_addrs()? {
+        match TcpStream::connect_timeout(&address, CONNECT_TIMEOUT) {
+            Ok(stream) => return Ok(stream),
+            Err(e) => last = Some(e),
+        }
+    }
+    Err(last.unwrap_or_else(|| {
+        io::Error::new(io::ErrorKind::NotFound, format!("{}: no address", address))
+    }))
+}
+
+/// overloaded and failing servers answer with an error kind reads are retried after
+fn status_kind(code: &str) -> io::ErrorKind {
+    if code == "429" || code.starts_with('5') {
+        io::ErrorKind::ResourceBusy
+    } else {
+        io::ErrorKind::Other
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::net::TcpListener;
+    use std::thread;
+
+    /// the url of a server answering a single request with response
+    fn serve(response: String) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/documents", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            // reads the whole head, closing with some of it unread resets the connection
+            let mut request = Vec::new();
+            let mut buf = [0; 1024];
+            while !request.ends_with(b"\r\n\r\n") {
+                match stream.read(&mut buf) {
+                    Ok(0) | Err(_) => break,
+                    Ok(n) => request.extend_from_slice(&buf[..n]),
+                }
+            }
+            stream.write_all(response.as_bytes()).unwrap();
+        });
+        url
+    }
+
+    #[test]
+    fn bodies_follow_the_head() {
+        let url = serve("HTTP/1.0 200 OK\r\nContent-Length: 2\r\n\r\n[]".into());
+        assert_eq!(get(&url).unwrap(), b"[]");
+    }
+
+    #[test]
+    fn overloaded_and_failing_servers_are_transient() {
+        for (status, kind) in [
+            ("429 Too Many Requests", io::ErrorKind::ResourceBusy),
+            ("503 Service Unavailable", io::ErrorKind::ResourceBusy),
+            ("500 Internal Server Error", io::ErrorKind::ResourceBusy),
+            ("404 Not Found", io::ErrorKind::Other),
+        ] {
+            let err = get(&serve(format!("HTTP/1.0 {}\r\n\r\n", status))).unwrap_err();
+            assert_eq!(err.kind(), kind, "{}", status);
+        }
+    }
+}
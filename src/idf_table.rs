@@ -0,0 +1,38 @@
+//! a precomputed idf table for ranking documents without refitting the corpus,
+//! written by `export-idf` and loaded by the WASM build
+use crate::model::Model;
+use crate::preprocess::Preprocessing;
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+/// the idf of every corpus term and the preprocessing documents need
+/// to be ranked against it
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IdfTable {
+    pub(crate) preprocessing: Preprocessing,
+    pub(crate) documents: usize,
+    pub(crate) idf: BTreeMap<String, f64>,
+}
+
+impl IdfTable {
+    pub fn new(model: &Model) -> Self {
+        let frequencies = &model.frequencies;
+        IdfTable {
+            preprocessing: model.preprocessing.clone(),
+            documents: frequencies.documents(),
+            idf: frequencies
+                .terms()
+                .map(|term| (term.to_string(), frequencies.idf(term)))
+                .collect(),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut w = BufWriter::new(File::create(path)?);
+        serde_json::to_writer(&mut w, self)?;
+        w.flush()
+    }
+}
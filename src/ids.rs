@@ -0,0 +1,207 @@
+//! document ids, by default the file name or line number a document was read from.
+//! corpora whose sources don't match the keys of their references, such as JSON
+//! lines or extracted archives, are named by a hash of their words instead
+use crate::fnv::Fnv;
+use crate::HulthDocument;
+
+use clap::ValueEnum;
+
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::Mutex;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Ids {
+    /// the file name without `.json`, or the line number of a JSON line without an id
+    Source,
+    /// a hash of the words of the document, the same in every format it is converted to
+    Hash,
+}
+
+#[derive(Serialize)]
+struct Entry<'a> {
+    id: &'a str,
+    source: &'a str,
+}
+
+/// how the documents of one run are named, by default by their source
+#[derive(Debug, Default)]
+pub struct Naming {
+    /// `--ids hash`
+    hashed: bool,
+    /// the source of every id given so far, None without `--id-map`
+    sources: Option<Mutex<BTreeMap<String, String>>>,
+}
+
+impl Naming {
+    /// names documents by ids, keeping their sources if map
+    pub fn new(ids: Ids, map: bool) -> Self {
+        Naming {
+            hashed: ids == Ids::Hash,
+            sources: map.then(Mutex::default),
+        }
+    }
+
+    pub fn hashed(&self) -> bool {
+        self.hashed
+    }
+
+    /// the id of doc, name unless hashed, read from source
+    pub fn id<S: Display>(&self, name: String, source: S, doc: &HulthDocument) -> String {
+        let id = if self.hashed { hash(doc) } else { name };
+        self.record(&id, source);
+        id
+    }
+
+    /// keeps the source of id for `--id-map`, for ids known without reading the document
+    pub fn record<S: Display>(&self, id: &str, source: S) {
+        if let Some(sources) = &self.sources {
+            let mut sources = sources.lock().unwrap_or_else(|e| e.into_inner());
+            let source = source.to_string();
+            match sources.get(id) {
+                Some(known) if *known != source => {
+                    warn!(id = %id, first = %known, second = %source, "documents share an id")
+                }
+                Some(_) => {}
+                None => {
+                    sources.insert(id.to_string(), source);
+                }
+            }
+        }
+    }
+
+    /// writes the ids given so far with their sources as JSON lines, sorted by id
+    pub fn write_map<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut w = BufWriter::new(File::create(path)?);
+        if let Some(sources) = &self.sources {
+            let sources = sources.lock().unwrap_or_else(|e| e.into_inner());
+            for (id, source) in sources.iter() {
+                serde_json::to_writer(&mut w, &Entry { id, source })?;
+                writeln!(w)?;
+            }
+        }
+        w.flush()
+    }
+}
+
+/// 16 hex digits of the 64 bit FNV-1a hash of the words of doc,
+/// stable across platforms and releases unlike the hashers of std
+pub fn hash(doc: &HulthDocument) -> String {
+    let mut hash = Fnv::new();
+    for sentence in &doc.sentences {
+        for token in &sentence.tokens {
+            hash.write(token.word.as_bytes());
+            // separators keep `ab c` and `a bc` apart
+            hash.write(&[0]);
+        }
+        hash.write(&[1]);
+    }
+    hash.hex()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{HulthToken, Sentence};
+
+    use std::fs;
+    use std::sync::Arc;
+
+    fn document(sentences: &[&[&str]]) -> HulthDocument {
+        let sentences = sentences
+            .iter()
+            .map(|words| Sentence {
+                tokens: words
+                    .iter()
+                    .map(|word| HulthToken {
+                        word: (*word).into(),
+                        lemma: (*word).into(),
+                        offset_begin: 0,
+                        offset_end: 0,
+                        pos: "".into(),
+                    })
+                    .collect(),
+            })
+            .collect();
+        HulthDocument { sentences }
+    }
+
+    /// a writer for log lines kept in memory
+    #[derive(Clone, Default)]
+    struct Captured(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Captured {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn hashed_ids_are_stable() {
+        // FNV-1a of "tf\0idf\0\x01", the same in every run and on every platform
+        assert_eq!(hash(&document(&[&["tf", "idf"]])), "75e1994fc4cbf15d");
+        let naming = Naming::new(Ids::Hash, false);
+        let doc = document(&[&["tf", "idf"]]);
+        assert_eq!(
+            naming.id("1.abstr".into(), "1.abstr.json", &doc),
+            hash(&doc)
+        );
+        assert_eq!(naming.id("2".into(), "line 2", &doc), hash(&doc));
+        // names are kept with the default ids
+        let naming = Naming::new(Ids::Source, false);
+        assert_eq!(naming.id("1.abstr".into(), "1.abstr.json", &doc), "1.abstr");
+    }
+
+    #[test]
+    fn word_and_sentence_boundaries_change_the_hash() {
+        assert_ne!(
+            hash(&document(&[&["ab", "c"]])),
+            hash(&document(&[&["a", "bc"]]))
+        );
+        assert_ne!(
+            hash(&document(&[&["a"], &["b"]])),
+            hash(&document(&[&["a", "b"]]))
+        );
+    }
+
+    #[test]
+    fn colliding_ids_are_reported_and_keep_the_first_source() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ids.jsonl");
+        let naming = Naming::new(Ids::Hash, true);
+        let doc = document(&[&["tf", "idf"]]);
+        let log = Captured::default();
+        let writer = log.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_writer(move || writer.clone())
+            .with_ansi(false)
+            .finish();
+        tracing::subscriber::with_default(subscriber, || {
+            naming.id("1".into(), "a.jsonl:1", &doc);
+            naming.id("1".into(), "a.jsonl:1", &doc);
+            naming.id("7".into(), "b.jsonl:7", &doc);
+        });
+        let log = String::from_utf8(log.0.lock().unwrap().clone()).unwrap();
+        assert_eq!(log.matches("documents share an id").count(), 1, "{}", log);
+        assert!(
+            log.contains("a.jsonl:1") && log.contains("b.jsonl:7"),
+            "{}",
+            log
+        );
+
+        naming.write_map(&path).unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "{\"id\":\"75e1994fc4cbf15d\",\"source\":\"a.jsonl:1\"}\n"
+        );
+    }
+}
@@ -0,0 +1,39 @@
+use crate::HulthDocument;
+
+use std::collections::HashSet;
+use std::sync::Arc;
+
+/// pool of shared strings, equal strings interned by the same pool
+/// are the same allocation and can be compared with Arc::ptr_eq
+#[derive(Debug, Default)]
+pub struct Interner {
+    pool: HashSet<Arc<str>>,
+}
+
+impl Interner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn intern(&mut self, s: &str) -> Arc<str> {
+        if let Some(existing) = self.pool.get(s) {
+            return existing.clone();
+        }
+        let s: Arc<str> = s.into();
+        self.pool.insert(s.clone());
+        s
+    }
+
+    /// replaces every token string of doc with its pooled version
+    pub fn intern_document(&mut self, doc: &mut HulthDocument) {
+        for token in doc.sentences.iter_mut().flat_map(|s| s.tokens.iter_mut()) {
+            token.word = self.intern(&token.word);
+            token.lemma = self.intern(&token.lemma);
+            token.pos = self.intern(&token.pos);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.pool.len()
+    }
+}
@@ -0,0 +1,31 @@
+//! Ctrl-C during `eval`: the first stops feeding documents, those in flight are
+//! finished and written and the summary covers them, marked as a partial run.
+//! a second Ctrl-C exits right away
+use signal_hook::consts::SIGINT;
+
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
+
+/// exit status of a second Ctrl-C, that of a process killed by SIGINT
+const EXIT: i32 = 130;
+
+static FLAG: OnceLock<Arc<AtomicBool>> = OnceLock::new();
+
+/// traps Ctrl-C, until then it ends the process as usual
+pub fn install() -> io::Result<()> {
+    if FLAG.get().is_some() {
+        return Ok(());
+    }
+    let flag = Arc::new(AtomicBool::new(false));
+    // runs before the flag is set, so it only exits on the second Ctrl-C
+    signal_hook::flag::register_conditional_shutdown(SIGINT, EXIT, flag.clone())?;
+    signal_hook::flag::register(SIGINT, flag.clone())?;
+    let _ = FLAG.set(flag);
+    Ok(())
+}
+
+/// whether Ctrl-C was pressed since install
+pub fn requested() -> bool {
+    FLAG.get().is_some_and(|flag| flag.load(Ordering::Relaxed))
+}
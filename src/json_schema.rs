@@ -0,0 +1,48 @@
+//! the JSON Schema of the dataset documents, derived from `HulthDocument`
+use crate::HulthDocument;
+
+use jsonschema::Validator;
+use serde_json::Value;
+
+use std::io;
+
+/// where a document breaks the schema and how
+#[derive(Debug)]
+pub struct Violation {
+    /// a JSON pointer such as `/sentences/0/tokens/3/pos`,
+    /// or the line and column of a syntax error
+    pub(crate) location: String,
+    pub(crate) message: String,
+}
+
+/// the schema of a Hulth JSON document
+pub fn document() -> Value {
+    serde_json::to_value(schemars::schema_for!(HulthDocument)).expect("schema is JSON")
+}
+
+pub fn validator() -> io::Result<Validator> {
+    jsonschema::validator_for(&document()).map_err(|e| io::Error::other(e.to_string()))
+}
+
+/// every violation of the schema by the document json
+pub fn check(validator: &Validator, json: &[u8]) -> Vec<Violation> {
+    let value: Value = match serde_json::from_slice(json) {
+        Ok(value) => value,
+        Err(e) => {
+            // the message of e ends in the location again
+            let message = e.to_string();
+            let message = message.split(" at line ").next().unwrap_or_default();
+            return vec![Violation {
+                location: format!("line {}, column {}", e.line(), e.column()),
+                message: message.into(),
+            }];
+        }
+    };
+    validator
+        .iter_errors(&value)
+        .map(|e| Violation {
+            location: e.instance_path().to_string(),
+            message: e.to_string(),
+        })
+        .collect()
+}
@@ -0,0 +1,160 @@
+//! KP-Miner, the tf-idf candidates seen often enough before a cutoff position,
+//! with the scores of multi-word candidates boosted by how rare they are
+use crate::phrases;
+use crate::HulthDocument;
+
+use clap::Args;
+
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, PartialEq, Args, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct KpMiner {
+    /// least allowable seen frequency, the fewest occurrences of a candidate.
+    /// KP-Miner uses 3 on full papers, abstracts repeat less
+    #[arg(long = "kp-lasf", default_value_t = 2)]
+    pub lasf: usize,
+
+    /// candidates first occurring after this many words are dropped
+    #[arg(long = "kp-cutoff", default_value_t = 400)]
+    pub cutoff: usize,
+
+    /// the scores of multi-word candidates are multiplied by
+    /// candidates / (alpha * multi-word candidates), at most --kp-sigma
+    #[arg(long = "kp-alpha", default_value_t = 2.3)]
+    pub alpha: f64,
+
+    /// the largest boost of multi-word candidates
+    #[arg(long = "kp-sigma", default_value_t = 3.0)]
+    pub sigma: f64,
+}
+
+impl Default for KpMiner {
+    fn default() -> Self {
+        KpMiner {
+            lasf: 2,
+            cutoff: 400,
+            alpha: 2.3,
+            sigma: 3.0,
+        }
+    }
+}
+
+impl KpMiner {
+    /// the candidates seen often and early enough, multi-word candidates boosted.
+    /// positions are counted in words, a phrase is at the position of its first word
+    pub fn rescore(&self, doc: &HulthDocument, scores: Vec<(String, f64)>) -> Vec<(String, f64)> {
+        let tokens = doc.sentences.iter().flat_map(|s| s.tokens.iter());
+        let mut offsets = tokens
+            .clone()
+            .filter(|t| !phrases::is_phrase(&t.word))
+            .map(|t| t.offset_begin)
+            .collect::<Vec<_>>();
+        offsets.sort_unstable();
+        // the count and first offset of every term
+        let mut seen = HashMap::<_, (usize, i64)>::new();
+        for token in tokens {
+            let (count, first) = seen.entry(&*token.word).or_insert((0, token.offset_begin));
+            *count += 1;
+            *first = (*first).min(token.offset_begin);
+        }
+        let candidates = scores
+            .into_iter()
+            .filter(|(term, _)| match seen.get(term.as_str()) {
+                Some((count, first)) => {
+                    *count >= self.lasf && offsets.partition_point(|o| o < first) < self.cutoff
+                }
+                None => false,
+            })
+            .collect::<Vec<_>>();
+        let compound = candidates
+            .iter()
+            .filter(|(term, _)| phrases::is_phrase(term))
+            .count();
+        if compound == 0 {
+            return candidates;
+        }
+        let boost = (candidates.len() as f64 / (self.alpha * compound as f64)).min(self.sigma);
+        candidates
+            .into_iter()
+            .map(|(term, score)| {
+                if phrases::is_phrase(&term) {
+                    (term, score * boost)
+                } else {
+                    (term, score)
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{HulthToken, Sentence};
+
+    /// "graph model graph model tree tree edge", the words 5 characters apart,
+    /// with a phrase token for each "graph model"
+    fn document() -> HulthDocument {
+        let words = ["graph", "model", "graph", "model", "tree", "tree", "edge"];
+        let token = |word: &str, begin: i64, end: i64| HulthToken {
+            word: word.into(),
+            lemma: word.into(),
+            offset_begin: begin,
+            offset_end: end,
+            pos: "".into(),
+        };
+        let mut tokens = words
+            .iter()
+            .enumerate()
+            .map(|(i, word)| token(word, 6 * i as i64, 6 * i as i64 + 5))
+            .collect::<Vec<_>>();
+        tokens.push(token("graph model", 0, 11));
+        tokens.push(token("graph model", 12, 23));
+        HulthDocument {
+            sentences: vec![Sentence { tokens }],
+        }
+    }
+
+    fn scores(terms: &[&str]) -> Vec<(String, f64)> {
+        terms.iter().map(|t| (t.to_string(), 1.0)).collect()
+    }
+
+    fn miner(cutoff: usize, alpha: f64, sigma: f64) -> KpMiner {
+        KpMiner {
+            lasf: 2,
+            cutoff,
+            alpha,
+            sigma,
+        }
+    }
+
+    #[test]
+    fn candidates_after_the_cutoff_or_seen_rarely_are_dropped() {
+        let terms = ["graph", "model", "tree", "edge", "graph model", "unseen"];
+        // tree first occurs as the fifth word, edge only once
+        let rescored = miner(4, 1.0, 10.0).rescore(&document(), scores(&terms));
+        assert_eq!(
+            rescored,
+            scores(&["graph", "model"])
+                .into_iter()
+                .chain([("graph model".to_string(), 3.0)])
+                .collect::<Vec<_>>()
+        );
+        let rescored = miner(5, 1.0, 10.0).rescore(&document(), scores(&terms));
+        assert_eq!(rescored.len(), 4);
+        assert_eq!(rescored[2].0, "tree");
+        assert_eq!(rescored[3], ("graph model".to_string(), 4.0));
+    }
+
+    #[test]
+    fn the_boost_of_multi_word_candidates_is_capped_by_sigma() {
+        let terms = scores(&["graph", "model", "graph model"]);
+        let boost = |miner: KpMiner| miner.rescore(&document(), terms.clone())[2].1;
+        // 3 candidates, 1 of them multi-word
+        assert_eq!(boost(miner(400, 1.0, 2.0)), 2.0);
+        assert!((boost(KpMiner::default()) - 3.0 / 2.3).abs() < 1e-9);
+        let single = miner(400, 1.0, 2.0).rescore(&document(), scores(&["graph", "model"]));
+        assert_eq!(single, scores(&["graph", "model"]));
+    }
+}
@@ -0,0 +1,325 @@
+//! the lemmatization stage, replacing words by their lemmas before the other stages.
+//! lemmas come from the `lemma` of the dataset tokens, a `form<TAB>lemma` dictionary,
+//! or an external lemmatizer asked for all uncached words of a document at once
+use crate::http;
+use crate::HulthToken;
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+/// where `--lemmatize` takes the lemmas from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+pub enum Lemmatizer {
+    /// `dataset`, the `lemma` of the document tokens. references have no lemmas
+    /// and keep their words, so they should be lemmatized already
+    Dataset,
+    /// `dict:<path>`, lines of `form<TAB>lemma`, words not listed are kept
+    Dictionary(PathBuf),
+    /// `command:<command line>`, run by `sh -c`, reading words one per line from
+    /// stdin and writing their lemmas one per line to stdout
+    Command(String),
+    /// `http://host[:port]/path`, POSTed a JSON array of words,
+    /// answering with the JSON array of their lemmas
+    Http(String),
+}
+
+impl Lemmatizer {
+    /// whether the lemmas come from a command or a server outside the harness
+    pub fn is_external(&self) -> bool {
+        matches!(self, Lemmatizer::Command(_) | Lemmatizer::Http(_))
+    }
+}
+
+impl FromStr for Lemmatizer {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "dataset" {
+            Ok(Lemmatizer::Dataset)
+        } else if let Some(path) = s.strip_prefix("dict:") {
+            Ok(Lemmatizer::Dictionary(path.into()))
+        } else if let Some(command) = s.strip_prefix("command:") {
+            Ok(Lemmatizer::Command(command.into()))
+        } else if s.starts_with("http://") {
+            Ok(Lemmatizer::Http(s.into()))
+        } else {
+            Err(format!(
+                "expected dataset, dict:<path>, command:<command> or http://<host>/<path>, got {}",
+                s
+            ))
+        }
+    }
+}
+
+impl fmt::Display for Lemmatizer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Lemmatizer::Dataset => f.write_str("dataset"),
+            Lemmatizer::Dictionary(path) => write!(f, "dict:{}", path.display()),
+            Lemmatizer::Command(command) => write!(f, "command:{}", command),
+            Lemmatizer::Http(url) => f.write_str(url),
+        }
+    }
+}
+
+impl TryFrom<String> for Lemmatizer {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<Lemmatizer> for String {
+    fn from(lemmatizer: Lemmatizer) -> String {
+        lemmatizer.to_string()
+    }
+}
+
+/// the lemmas of one run, shared by its normalizers, caching those of external lemmatizers
+#[derive(Debug, Default)]
+pub struct Lemmas {
+    lemmatizer: Option<Lemmatizer>,
+    dictionary: HashMap<String, String>,
+    cache: Mutex<HashMap<String, String>>,
+    /// a lemmatizer failure was logged, later ones are not
+    failed: AtomicBool,
+}
+
+impl Lemmas {
+    /// reads the dictionary of a `dict:` lemmatizer, failing if it can't be read
+    pub fn new(lemmatizer: Option<&Lemmatizer>) -> io::Result<Self> {
+        let dictionary = match lemmatizer {
+            Some(Lemmatizer::Dictionary(path)) => {
+                let dictionary = read_dictionary(path)
+                    .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))?;
+                debug!(path = %path.display(), forms = dictionary.len(), "read lemma dictionary");
+                dictionary
+            }
+            _ => HashMap::new(),
+        };
+        Ok(Lemmas {
+            lemmatizer: lemmatizer.cloned(),
+            dictionary,
+            ..Lemmas::default()
+        })
+    }
+
+    /// the lemma of token, its word unless lemmas come from the dataset
+    pub fn of_token<'a>(&self, token: &'a HulthToken) -> Cow<'a, str> {
+        match self.lemmatizer {
+            Some(Lemmatizer::Dataset) if !token.lemma.is_empty() => Cow::Borrowed(&token.lemma),
+            _ => self.of(&token.word),
+        }
+    }
+
+    /// the lemma of word, word itself if it has none
+    pub fn of<'a>(&self, word: &'a str) -> Cow<'a, str> {
+        match &self.lemmatizer {
+            None | Some(Lemmatizer::Dataset) => Cow::Borrowed(word),
+            Some(Lemmatizer::Dictionary(_)) => {
+                let lemma = self
+                    .dictionary
+                    .get(word)
+                    .or_else(|| self.dictionary.get(&word.to_lowercase()));
+                match lemma {
+                    Some(lemma) => Cow::Owned(lemma.clone()),
+                    None => Cow::Borrowed(word),
+                }
+            }
+            Some(_) => {
+                self.prefetch([word]);
+                match self.cache().get(word) {
+                    Some(lemma) => Cow::Owned(lemma.clone()),
+                    None => Cow::Borrowed(word),
+                }
+            }
+        }
+    }
+
+    /// asks an external lemmatizer for the words not cached yet in a single call,
+    /// the first failure of a run is logged and the words are kept as their own lemmas
+    pub fn prefetch<'a, I: IntoIterator<Item = &'a str>>(&self, words: I) {
+        let lemmatizer = match &self.lemmatizer {
+            Some(l @ Lemmatizer::Command(_)) | Some(l @ Lemmatizer::Http(_)) => l,
+            _ => return,
+        };
+        let mut missing = {
+            let cache = self.cache();
+            words
+                .into_iter()
+                .filter(|word| !cache.contains_key(*word))
+                .map(String::from)
+                .collect::<Vec<_>>()
+        };
+        missing.sort_unstable();
+        missing.dedup();
+        if missing.is_empty() {
+            return;
+        }
+        let lemmas = match lemmatizer {
+            Lemmatizer::Command(command) => run_command(command, &missing),
+            Lemmatizer::Http(url) => post(url, &missing),
+            _ => unreachable!(),
+        };
+        let lemmas = lemmas.and_then(|lemmas| {
+            if lemmas.len() == missing.len() {
+                Ok(lemmas)
+            } else {
+                Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{} lemmas for {} words", lemmas.len(), missing.len()),
+                ))
+            }
+        });
+        let lemmas = match lemmas {
+            Ok(lemmas) => lemmas,
+            Err(e) => {
+                if !self.failed.swap(true, Ordering::Relaxed) {
+                    error!(lemmatizer = %lemmatizer, error = %e, "lemmatizer failed, keeping words");
+                }
+                missing.clone()
+            }
+        };
+        self.cache().extend(missing.into_iter().zip(lemmas));
+    }
+
+    fn cache(&self) -> std::sync::MutexGuard<'_, HashMap<String, String>> {
+        self.cache.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+fn read_dictionary(path: &Path) -> io::Result<HashMap<String, String>> {
+    parse_dictionary(BufReader::new(fs::File::open(path)?))
+}
+
+/// lines of `form<TAB>lemma`, empty lines and lines starting with `#` are skipped
+fn parse_dictionary<R: BufRead>(reader: R) -> io::Result<HashMap<String, String>> {
+    let mut dictionary = HashMap::new();
+    for (n, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match line.split_once('\t') {
+            Some((form, lemma)) => {
+                dictionary.insert(form.to_string(), lemma.trim_end().to_string());
+            }
+            None => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("line {}: expected form<TAB>lemma", n + 1),
+                ))
+            }
+        }
+    }
+    Ok(dictionary)
+}
+
+fn run_command(command: &str, words: &[String]) -> io::Result<Vec<String>> {
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()?;
+    let mut stdin = child.stdin.take().expect("piped stdin");
+    let input = words.join("\n") + "\n";
+    // written on its own thread so a lemmatizer answering as it reads can't block
+    let writer = thread::spawn(move || stdin.write_all(input.as_bytes()));
+    let mut output = String::new();
+    child
+        .stdout
+        .take()
+        .expect("piped stdout")
+        .read_to_string(&mut output)?;
+    writer
+        .join()
+        .unwrap_or_else(|_| Err(io::Error::other("writing to the lemmatizer panicked")))?;
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(io::Error::other(format!("exited with {}", status)));
+    }
+    Ok(output.lines().map(String::from).collect())
+}
+
+/// the lemmas answered by the lemmatizer at url
+fn post(url: &str, words: &[String]) -> io::Result<Vec<String>> {
+    let body = serde_json::to_vec(words)?;
+    Ok(serde_json::from_slice(&http::post_json(url, &body)?)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dictionaries_skip_comments_and_blank_lines() {
+        let text = "# form\tlemma\n\nnetworks\tnetwork\nran\trun  \n   \nmice\tmouse\n";
+        let dictionary = parse_dictionary(text.as_bytes()).unwrap();
+        assert_eq!(dictionary.len(), 3);
+        assert_eq!(dictionary["networks"], "network");
+        // trailing whitespace is trimmed off the lemma
+        assert_eq!(dictionary["ran"], "run");
+    }
+
+    #[test]
+    fn dictionary_lines_without_a_tab_are_rejected() {
+        let err = parse_dictionary("networks\tnetwork\nmice mouse\n".as_bytes()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().starts_with("line 2:"), "{}", err);
+    }
+
+    #[test]
+    fn dictionary_lemmas_fall_back_to_the_lowercase_form() {
+        let lemmas = Lemmas {
+            lemmatizer: Some(Lemmatizer::Dictionary("unused".into())),
+            dictionary: parse_dictionary("networks\tnetwork\n".as_bytes()).unwrap(),
+            ..Lemmas::default()
+        };
+        assert_eq!(lemmas.of("networks"), "network");
+        assert_eq!(lemmas.of("Networks"), "network");
+        assert_eq!(lemmas.of("graphs"), "graphs");
+    }
+
+    #[test]
+    fn missing_dictionaries_fail() {
+        let lemmatizer = Lemmatizer::Dictionary("/nonexistent/lemmas.tsv".into());
+        let err = Lemmas::new(Some(&lemmatizer)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn command_lemmas_are_mapped_back_to_their_words() {
+        let lemmatizer = Lemmatizer::Command("sed 's/s$//'".into());
+        let lemmas = Lemmas::new(Some(&lemmatizer)).unwrap();
+        lemmas.prefetch(["trees", "graph", "nodes", "trees"]);
+        assert_eq!(lemmas.of("trees"), "tree");
+        assert_eq!(lemmas.of("graph"), "graph");
+        assert_eq!(lemmas.of("nodes"), "node");
+        assert_eq!(lemmas.cache().len(), 3);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn commands_answering_too_few_lemmas_keep_the_words() {
+        let lemmatizer = Lemmatizer::Command("head -n 1".into());
+        let lemmas = Lemmas::new(Some(&lemmatizer)).unwrap();
+        lemmas.prefetch(["trees", "nodes"]);
+        assert_eq!(lemmas.of("trees"), "trees");
+        assert_eq!(lemmas.of("nodes"), "nodes");
+        assert!(lemmas.failed.load(Ordering::Relaxed));
+    }
+}
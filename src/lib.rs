@@ -0,0 +1,709 @@
+#[macro_use]
+extern crate serde_derive;
+extern crate bincode;
+extern crate clap;
+extern crate crossbeam_channel;
+extern crate lib_tfidf;
+extern crate rust_stemmers;
+extern crate serde;
+extern crate serde_json;
+extern crate toml;
+#[macro_use]
+extern crate tracing;
+extern crate tracing_subscriber;
+
+mod alloc_stats;
+mod batch;
+mod bio;
+mod brat;
+mod breakdown;
+mod cache;
+#[cfg(feature = "parquet")]
+mod columnar;
+mod commands;
+mod config;
+mod corpus;
+mod correlation;
+mod features;
+#[cfg(feature = "ffi")]
+mod ffi;
+mod fnv;
+mod grammar;
+mod granularity;
+#[cfg(feature = "grpc")]
+mod grpc;
+mod http;
+mod idf_table;
+mod ids;
+mod intern;
+mod interrupt;
+mod json_schema;
+mod kp_miner;
+mod language;
+mod lemmatize;
+mod logging;
+mod logistic;
+mod manifest;
+mod matching;
+mod matrix;
+mod metrics;
+mod model;
+#[cfg(feature = "onnx")]
+mod onnx;
+mod phrases;
+mod pipeline;
+mod preflight;
+mod preprocess;
+#[cfg(feature = "profile")]
+mod profile;
+#[cfg(feature = "serve")]
+mod prometheus;
+#[cfg(test)]
+mod proptests;
+mod protocol;
+#[cfg(feature = "python")]
+mod python;
+mod report;
+mod rerank;
+mod results;
+mod rng;
+mod rouge;
+mod run_dir;
+mod scoring;
+#[cfg(feature = "serve")]
+mod server;
+mod shard;
+mod significance;
+mod source;
+mod spans;
+mod synonyms;
+mod text;
+mod textrank;
+#[cfg(feature = "tui")]
+mod tui;
+mod vectors;
+mod verify;
+#[cfg(feature = "wasm")]
+mod wasm;
+mod watch;
+mod weighting;
+
+use clap::{ArgAction, ArgMatches, Args, CommandFactory, FromArgMatches, Parser, Subcommand};
+#[cfg(feature = "serve")]
+use commands::serve;
+#[cfg(feature = "grpc")]
+use commands::serve_grpc;
+use commands::{
+    ablate, compare, completions, convert, convert_vectors, eval, export_brat, export_features,
+    export_idf, export_matrix, gen_corpus, merge, rank, rank_batch, schema, stats, sweep, train,
+    update_model, validate, verify_offsets,
+};
+use config::Config;
+use intern::Interner;
+use lib_tfidf::{Document, Token};
+use matching::{References, SynonymMatch};
+use preprocess::Normalizer;
+use results::{DocumentResult, MetricSet};
+use schemars::JsonSchema;
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::io;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+const DEFAULT_DATASET: &str = "dataset/testJSON";
+const DEFAULT_REFERENCES: &str = "dataset/references/test.uncontr.json";
+
+pub type HulthDocumentKeywords = HashMap<String, Vec<Vec<String>>>;
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct HulthDocument {
+    #[serde(rename = "sentences")]
+    pub(crate) sentences: Vec<Sentence>,
+}
+
+impl HulthDocument {
+    /// lib_tfidf wants owned boxed tokens for a 'static corpus, so borrowed
+    /// views are not possible here. cloning a token only bumps the
+    /// reference counts of its strings, leaving the box as the one allocation
+    #[allow(clippy::vec_box)]
+    fn get_flat_tokens(&self) -> Vec<Box<HulthToken>> {
+        self.sentences
+            .iter()
+            .flat_map(|s| s.tokens.iter())
+            .cloned()
+            .map(Box::new)
+            .collect()
+    }
+}
+
+impl Document<String, HulthToken> for HulthDocument {
+    fn get_id(&self) -> Box<String> {
+        Box::new("".into())
+    }
+
+    fn get_content(&self) -> Vec<Box<HulthToken>> {
+        self.get_flat_tokens()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Sentence {
+    #[serde(rename = "tokens")]
+    pub(crate) tokens: Vec<HulthToken>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename = "token", from = "RawToken", into = "RawToken")]
+pub struct HulthToken {
+    #[serde(rename = "word")]
+    pub(crate) word: Arc<str>,
+
+    #[serde(rename = "lemma")]
+    pub(crate) lemma: Arc<str>,
+
+    #[serde(rename = "offsetBegin")]
+    pub(crate) offset_begin: i64,
+
+    #[serde(rename = "offsetEnd")]
+    pub(crate) offset_end: i64,
+
+    #[serde(rename = "pos")]
+    pub(crate) pos: Arc<str>,
+}
+
+/// a token of a sentence. lemma and pos are null or missing in some exports,
+/// only `--lenient` reads those
+// a missing Option is None without a serde default. tokens are written as
+// RawToken too, formats like bincode read back only what they wrote
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[schemars(rename = "token")]
+struct RawToken {
+    word: Arc<str>,
+    #[schemars(required)]
+    lemma: Option<Arc<str>>,
+    #[serde(rename = "offsetBegin")]
+    offset_begin: i64,
+    #[serde(rename = "offsetEnd")]
+    offset_end: i64,
+    /// a Penn Treebank tag
+    #[schemars(required)]
+    pos: Option<Arc<str>>,
+}
+
+impl From<RawToken> for HulthToken {
+    /// a missing lemma becomes the lowercased word and a missing tag the empty
+    /// tag of untagged tokens, both counted for `--lenient`
+    fn from(raw: RawToken) -> Self {
+        corpus::count_missing(raw.lemma.is_none(), raw.pos.is_none());
+        let word = raw.word;
+        HulthToken {
+            lemma: raw.lemma.unwrap_or_else(|| word.to_lowercase().into()),
+            word,
+            offset_begin: raw.offset_begin,
+            offset_end: raw.offset_end,
+            pos: raw.pos.unwrap_or_else(|| "".into()),
+        }
+    }
+}
+
+impl From<HulthToken> for RawToken {
+    fn from(token: HulthToken) -> Self {
+        RawToken {
+            word: token.word,
+            lemma: Some(token.lemma),
+            offset_begin: token.offset_begin,
+            offset_end: token.offset_end,
+            pos: Some(token.pos),
+        }
+    }
+}
+
+impl Token for HulthToken {
+    fn get_term(&self) -> String {
+        self.word.to_string()
+    }
+    fn get_offset_begin(&self) -> usize {
+        self.offset_begin as usize
+    }
+    fn get_pos(&self) -> Option<String> {
+        None
+    }
+}
+
+/// compares the ranked terms of the named document against its references
+fn score_document(
+    name: &str,
+    doc: &HulthDocument,
+    ranked: Vec<(String, f64)>,
+    top_k: Option<usize>,
+    keywords: &HulthDocumentKeywords,
+    normalizer: &Normalizer,
+    interner: &mut Interner,
+) -> io::Result<DocumentResult> {
+    let mut ranked = ranked
+        .into_iter()
+        .map(|(term, score)| (interner.intern(&term), score))
+        .collect::<Vec<_>>();
+    let keyphrases = match keywords.get(name) {
+        Some(keyphrases) => keyphrases,
+        None => {
+            error!(document = %name, "found no keywords");
+            return Err(io::Error::other("found no keywords"));
+        }
+    };
+    let unreachable = matching::unreachable(doc, keyphrases, normalizer);
+    for keyphrase in &unreachable {
+        debug!(document = %name, keyphrase = %keyphrase, "reference keyphrase never occurs");
+    }
+    let reference = References::new(keyphrases, normalizer, interner);
+    let mut hits = reference.hits(&ranked.iter().map(|(term, _)| &**term).collect::<Vec<_>>());
+    let adaptive = breakdown::Adaptive::of(&hits, reference.len());
+    if let Some(k) = top_k {
+        ranked.truncate(k);
+        hits.truncate(k);
+    }
+    let relevant = matching::relevant(&ranked, &reference);
+    trace!(
+        document = %name,
+        ranked = ranked.len(),
+        relevant = relevant.len(),
+        "matched references"
+    );
+    let degenerate = Degenerate::of(doc, &ranked, &reference);
+    if let Some(reason) = degenerate {
+        warn!(document = %name, reason = %reason, "skipping degenerate document");
+    }
+    let precision = metrics::precision(&hits);
+    let recall = metrics::recall(&hits, reference.len());
+    let (present, absent) =
+        breakdown::presence(&ranked, keyphrases, &unreachable, normalizer, interner);
+    Ok(DocumentResult {
+        schema: results::SCHEMA,
+        document: name.into(),
+        metrics: MetricSet {
+            precision,
+            recall,
+            f1: metrics::f1(precision, recall),
+            average_precision: metrics::average_precision(&hits, reference.len()),
+            ndcg: metrics::ndcg(&hits, reference.len()),
+            coverage: reference
+                .coverage(&ranked.iter().map(|(term, _)| &**term).collect::<Vec<_>>()),
+        },
+        adaptive,
+        degenerate,
+        pos: breakdown::pos_counts(doc, &ranked, &reference),
+        tokens: doc.sentences.iter().map(|s| s.tokens.len()).sum(),
+        keyphrases: keyphrases.iter().map(Vec::len).sum(),
+        unreachable: unreachable.len(),
+        positions: breakdown::positions(doc, &relevant),
+        bio: bio::counts(doc, &ranked, keyphrases, normalizer),
+        rouge: rouge::of(&ranked, keyphrases, normalizer),
+        relevance: correlation::relevance(&ranked, &reference),
+        groups: breakdown::groups(&ranked, keyphrases, normalizer, interner),
+        present,
+        absent,
+        candidates: ranked
+            .iter()
+            .map(|(term, score)| (*score, reference.contains(term)))
+            .collect(),
+        synonyms: ranked
+            .iter()
+            .filter_map(|(term, _)| {
+                let matched = reference.via_synonym(term)?;
+                Some(SynonymMatch {
+                    term: term.to_string(),
+                    reference: matched.to_string(),
+                })
+            })
+            .collect(),
+        ranking: ranked.into_iter().map(|(term, _)| term).collect(),
+    })
+}
+
+/// why a document can't be evaluated
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum Degenerate {
+    /// the document has no sentences
+    Empty,
+    /// it has no tokens, or preprocessing filtered out all of them
+    Tokenless,
+    /// nothing was ranked, so precision is undefined
+    Unranked,
+    /// the references have no terms after normalization, so recall is undefined
+    Unreferenced,
+}
+
+impl Degenerate {
+    fn of(doc: &HulthDocument, ranked: &[(Arc<str>, f64)], reference: &References) -> Option<Self> {
+        if doc.sentences.is_empty() {
+            Some(Degenerate::Empty)
+        } else if doc.sentences.iter().all(|s| s.tokens.is_empty()) {
+            Some(Degenerate::Tokenless)
+        } else if ranked.is_empty() {
+            Some(Degenerate::Unranked)
+        } else if reference.len() == 0 {
+            Some(Degenerate::Unreferenced)
+        } else {
+            None
+        }
+    }
+}
+
+impl std::fmt::Display for Degenerate {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let name = match self {
+            Degenerate::Empty => "no sentences",
+            Degenerate::Tokenless => "no tokens",
+            Degenerate::Unranked => "no candidates",
+            Degenerate::Unreferenced => "no references",
+        };
+        f.write_str(name)
+    }
+}
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "hulth-eval",
+    about = "tf-idf keyword extraction on the Hulth 2003 dataset"
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// TOML file with options for the run, options on the command line take precedence
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+
+    #[command(flatten)]
+    global: GlobalArgs,
+}
+
+#[derive(Debug, Args, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct GlobalArgs {
+    /// increases log verbosity, -v for progress and -vv for per-document diagnostics
+    #[arg(short, long, action = ArgAction::Count, global = true)]
+    verbose: u8,
+
+    /// format of the log lines written to stderr
+    #[arg(long, value_enum, default_value_t = logging::LogFormat::Text, global = true)]
+    log_format: logging::LogFormat,
+
+    /// fills in the lemmas and POS tags missing from dataset tokens instead of
+    /// failing, with a warning for every file that needed it. dataset files still
+    /// unreadable after --io-retries are skipped
+    #[arg(long, global = true)]
+    lenient: bool,
+
+    /// reads of dataset files failing with a transient error, as on network
+    /// filesystems, are retried this many times
+    #[arg(long, default_value_t = 3, global = true)]
+    io_retries: usize,
+
+    /// milliseconds before the first retry of a read, doubling with every further one
+    #[arg(long, default_value_t = 100, global = true)]
+    io_backoff: u64,
+
+    /// how documents are named, `hash` for corpora whose file names or
+    /// line numbers don't match the keys of their references
+    #[arg(long, value_enum, default_value_t = ids::Ids::Source, global = true)]
+    ids: ids::Ids,
+
+    /// writes the id of every document read with the file or line it came from
+    /// as JSON lines
+    #[arg(long, global = true)]
+    id_map: Option<PathBuf>,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// fits the corpus and evaluates the ranked keywords against the references
+    Eval(eval::EvalArgs),
+    /// ranks a single document read from stdin against a saved model
+    Rank(rank::RankArgs),
+    /// ranks every document of a JSONL file against a saved model
+    RankBatch(rank_batch::RankBatchArgs),
+    /// combines per-document result files, e.g. of sharded runs, into one summary
+    Merge(merge::MergeArgs),
+    /// compares the per-document results of two runs and lists the documents that changed most
+    Compare(compare::CompareArgs),
+    /// evaluates once per preprocessing stage with that stage toggled
+    /// and reports the effect of each stage on F1
+    Ablate(eval::EvalArgs),
+    /// evaluates every extractor at several values of --top-k and marks the best
+    Sweep(sweep::SweepArgs),
+    /// writes a completion script for bash, zsh, fish, elvish or PowerShell
+    Completions(completions::CompletionsArgs),
+    /// converts a dataset between a directory of Hulth JSON files and JSON lines
+    Convert(convert::ConvertArgs),
+    /// converts GloVe or word2vec vectors into a compact store that is memory-mapped when read
+    ConvertVectors(convert_vectors::ConvertVectorsArgs),
+    /// writes the keywords of every dataset document as BRAT standoff annotations
+    ExportBrat(export_brat::ExportBratArgs),
+    /// writes the features and gold label of every ranked candidate of the dataset as CSV
+    ExportFeatures(export_features::ExportFeaturesArgs),
+    /// writes the idf of every term of a saved model as JSON, for the WASM build
+    ExportIdf(export_idf::ExportIdfArgs),
+    /// writes the document-term tf-idf matrix of a saved model as a sparse matrix
+    ExportMatrix(export_matrix::ExportMatrixArgs),
+    /// writes a synthetic dataset of Zipf distributed words with planted keyphrases as references
+    GenCorpus(gen_corpus::GenCorpusArgs),
+    /// ranks documents posted to `/rank` over HTTP against a saved model
+    #[cfg(feature = "serve")]
+    Serve(serve::ServeArgs),
+    /// ranks documents sent to the `hulth.Extractor` gRPC service against a saved model
+    #[cfg(feature = "grpc")]
+    ServeGrpc(serve_grpc::ServeGrpcArgs),
+    /// writes the JSON Schema of the dataset documents
+    Schema(schema::SchemaArgs),
+    /// prints the size of a dataset and of its references
+    Stats(stats::StatsArgs),
+    /// trains the logistic reranker on the candidate features of a training split
+    Train(train::TrainArgs),
+    /// adds the documents of a dataset to a saved model, replacing those of the same name,
+    /// or removes documents by id, without reading the dataset the model was fitted on again.
+    /// only reading and preprocessing are skipped, the model stores no fitted statistics
+    /// and is refitted on every load
+    UpdateModel(update_model::UpdateModelArgs),
+    /// checks every dataset document against the JSON Schema and lists where it breaks it
+    Validate(validate::ValidateArgs),
+    /// checks the token offsets of the dataset against the original `.abstr` texts
+    VerifyOffsets(verify_offsets::VerifyOffsetsArgs),
+}
+
+/// parses a Hulth JSON document like the dataset loader, for the fuzz targets in fuzz/
+pub fn parse_document(json: &[u8]) -> io::Result<HulthDocument> {
+    corpus::parse_bytes(json)
+}
+
+/// parses the lines of a `rank-batch` input file with their ids, for the fuzz targets
+pub fn parse_batch(jsonl: &[u8]) -> io::Result<Vec<(String, HulthDocument)>> {
+    let mut documents = vec![];
+    batch::for_each_document(jsonl, &corpus::Reading::default(), |id, document, _| {
+        documents.push((id, document));
+        Ok(())
+    })?;
+    Ok(documents)
+}
+
+/// the `hulth-eval` command line, parsing the arguments of the process
+pub fn run() -> io::Result<()> {
+    let matches = Cli::command().get_matches();
+    let mut cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    let config = match &cli.config {
+        Some(path) => Some(Config::load(path)?),
+        None => None,
+    };
+    if let Some(config) = &config {
+        config.check_sections(&[
+            "eval",
+            "rank",
+            "rank-batch",
+            "merge",
+            "compare",
+            "ablate",
+            "sweep",
+            "convert",
+            "convert-vectors",
+            "export-brat",
+            "export-features",
+            "export-idf",
+            "export-matrix",
+            "gen-corpus",
+            "serve",
+            "serve-grpc",
+            "schema",
+            "stats",
+            "train",
+            "update-model",
+            "validate",
+            "verify-offsets",
+            "extractors",
+        ])?;
+        if let Some(extractors) = config.extractors() {
+            scoring::check_extractors(extractors).map_err(|e| config.invalid(e))?;
+        }
+        cli.global = config.apply(cli.global, &config.globals(), &matches)?;
+    }
+    logging::init(cli.global.verbose, cli.global.log_format);
+    let reading = corpus::Reading::new(
+        cli.global.lenient,
+        cli.global.io_retries,
+        Duration::from_millis(cli.global.io_backoff),
+        ids::Naming::new(cli.global.ids, cli.global.id_map.is_some()),
+    );
+
+    let (command, matches) = match cli.command {
+        Some(command) => {
+            let matches = matches
+                .subcommand()
+                .map(|(_, m)| m.clone())
+                .unwrap_or_default();
+            (command, matches)
+        }
+        None => default_command(),
+    };
+    let command = match &config {
+        Some(config) => apply_config(config, command, &matches)?,
+        None => command,
+    };
+    let command = match command {
+        Command::Eval(args) => Command::Eval(protocol::apply(args, &matches)?),
+        Command::Ablate(args) => Command::Ablate(protocol::apply(args, &matches)?),
+        Command::Sweep(mut args) => {
+            args.eval = protocol::apply(args.eval, &matches)?;
+            Command::Sweep(args)
+        }
+        command => command,
+    };
+    let result = match command {
+        Command::Eval(args) => eval::run(&args, &reading),
+        Command::Rank(args) => rank::run(&args, &reading),
+        Command::RankBatch(args) => rank_batch::run(&args, &reading),
+        Command::Merge(args) => merge::run(&args),
+        Command::Compare(args) => compare::run(&args),
+        Command::Ablate(args) => ablate::run(&args, &reading),
+        Command::Sweep(args) => sweep::run(&args, &reading),
+        Command::Completions(args) => completions::run(&args, config.as_ref()),
+        Command::Convert(args) => convert::run(&args, &reading),
+        Command::ConvertVectors(args) => convert_vectors::run(&args),
+        Command::ExportBrat(args) => export_brat::run(&args, &reading),
+        Command::ExportFeatures(args) => export_features::run(&args, &reading),
+        Command::ExportIdf(args) => export_idf::run(&args),
+        Command::ExportMatrix(args) => export_matrix::run(&args),
+        Command::GenCorpus(args) => gen_corpus::run(&args),
+        #[cfg(feature = "serve")]
+        Command::Serve(args) => serve::run(&args),
+        #[cfg(feature = "grpc")]
+        Command::ServeGrpc(args) => serve_grpc::run(&args),
+        Command::Schema(args) => schema::run(&args),
+        Command::Stats(args) => stats::run(&args, &reading),
+        Command::Train(args) => train::run(&args, &reading),
+        Command::UpdateModel(args) => update_model::run(&args, &reading),
+        Command::Validate(args) => validate::run(&args, &reading),
+        Command::VerifyOffsets(args) => verify_offsets::run(&args, &reading),
+    };
+    if let Some(path) = &cli.global.id_map {
+        reading.ids.write_map(path)?;
+    }
+    let repaired = reading.repaired();
+    if repaired > 0 {
+        warn!(
+            files = repaired,
+            "filled in missing lemmas or POS tags, see the warnings above"
+        );
+    }
+    let skipped = reading.skipped();
+    if skipped > 0 {
+        warn!(
+            files = skipped,
+            "skipped dataset files unreadable after retries, see the warnings above"
+        );
+    }
+    result
+}
+
+/// eval with its defaults and environment, used when no subcommand is given
+fn default_command() -> (Command, ArgMatches) {
+    let matches =
+        eval::EvalArgs::augment_args(clap::Command::new("eval")).get_matches_from(["eval"]);
+    let args = eval::EvalArgs::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    (Command::Eval(args), matches)
+}
+
+/// applies the config section of the subcommand to its options,
+/// matches are those of the subcommand
+fn apply_config(config: &Config, command: Command, matches: &ArgMatches) -> io::Result<Command> {
+    let name = match &command {
+        Command::Eval(_) => "eval",
+        Command::Rank(_) => "rank",
+        Command::RankBatch(_) => "rank-batch",
+        Command::Merge(_) => "merge",
+        Command::Compare(_) => "compare",
+        Command::Ablate(_) => "ablate",
+        Command::Sweep(_) => "sweep",
+        // the shell is the only option, nothing to configure
+        Command::Completions(_) => return Ok(command),
+        Command::Convert(_) => "convert",
+        Command::ConvertVectors(_) => "convert-vectors",
+        Command::ExportBrat(_) => "export-brat",
+        Command::ExportFeatures(_) => "export-features",
+        Command::ExportIdf(_) => "export-idf",
+        Command::ExportMatrix(_) => "export-matrix",
+        Command::GenCorpus(_) => "gen-corpus",
+        #[cfg(feature = "serve")]
+        Command::Serve(_) => "serve",
+        #[cfg(feature = "grpc")]
+        Command::ServeGrpc(_) => "serve-grpc",
+        Command::Schema(_) => "schema",
+        Command::Stats(_) => "stats",
+        Command::Train(_) => "train",
+        Command::UpdateModel(_) => "update-model",
+        Command::Validate(_) => "validate",
+        Command::VerifyOffsets(_) => "verify-offsets",
+    };
+    let empty = toml::Table::new();
+    // applied without a section too, for the `[extractors]` of the scoring options
+    let values = config.section(name).unwrap_or(&empty);
+    Ok(match command {
+        Command::Eval(args) => Command::Eval(config.apply(args, values, matches)?),
+        Command::Rank(args) => Command::Rank(config.apply(args, values, matches)?),
+        Command::RankBatch(args) => Command::RankBatch(config.apply(args, values, matches)?),
+        Command::Merge(args) => Command::Merge(config.apply(args, values, matches)?),
+        Command::Compare(args) => Command::Compare(config.apply(args, values, matches)?),
+        Command::Ablate(args) => Command::Ablate(config.apply(args, values, matches)?),
+        Command::Sweep(args) => Command::Sweep(config.apply(args, values, matches)?),
+        Command::Completions(args) => Command::Completions(args),
+        Command::Convert(args) => Command::Convert(config.apply(args, values, matches)?),
+        Command::ConvertVectors(args) => {
+            Command::ConvertVectors(config.apply(args, values, matches)?)
+        }
+        Command::ExportBrat(args) => Command::ExportBrat(config.apply(args, values, matches)?),
+        Command::ExportFeatures(args) => {
+            Command::ExportFeatures(config.apply(args, values, matches)?)
+        }
+        Command::ExportIdf(args) => Command::ExportIdf(config.apply(args, values, matches)?),
+        Command::ExportMatrix(args) => Command::ExportMatrix(config.apply(args, values, matches)?),
+        Command::GenCorpus(args) => Command::GenCorpus(config.apply(args, values, matches)?),
+        #[cfg(feature = "serve")]
+        Command::Serve(args) => Command::Serve(config.apply(args, values, matches)?),
+        #[cfg(feature = "grpc")]
+        Command::ServeGrpc(args) => Command::ServeGrpc(config.apply(args, values, matches)?),
+        Command::Schema(args) => Command::Schema(config.apply(args, values, matches)?),
+        Command::Stats(args) => Command::Stats(config.apply(args, values, matches)?),
+        Command::Train(args) => Command::Train(config.apply(args, values, matches)?),
+        Command::UpdateModel(args) => Command::UpdateModel(config.apply(args, values, matches)?),
+        Command::Validate(args) => Command::Validate(config.apply(args, values, matches)?),
+        Command::VerifyOffsets(args) => {
+            Command::VerifyOffsets(config.apply(args, values, matches)?)
+        }
+    })
+}
+
+/// orders ranked terms by descending score, terms of the same score alphabetically
+/// so rankings don't depend on the order of the scores
+fn sort_ranking<R: IntoIterator<Item = (String, f64)>>(ranked: R) -> Vec<(String, f64)> {
+    let mut ranked = ranked.into_iter().collect::<Vec<_>>();
+    ranked.sort_by(|a, b| cmp_f64(a.1, b.1).then_with(|| a.0.cmp(&b.0)));
+    ranked
+}
+
+#[allow(clippy::comparison_chain)]
+fn cmp_f64(a: f64, b: f64) -> Ordering {
+    if a.is_nan() {
+        return Ordering::Less;
+    }
+    if b.is_nan() {
+        return Ordering::Greater;
+    }
+    if a < b {
+        return Ordering::Greater;
+    } else if a > b {
+        return Ordering::Less;
+    }
+    Ordering::Equal
+}
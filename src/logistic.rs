@@ -0,0 +1,184 @@
+//! supervised reranking of the candidates by logistic regression
+//! over their features, see `features` and the `train` subcommand,
+//! or by an externally trained ONNX model with the `onnx` feature.
+//! the embed-rank extractor rescores by word vectors instead of features
+use crate::features::{self, NUMERIC};
+#[cfg(feature = "onnx")]
+use crate::onnx::OnnxModel;
+use crate::rerank;
+use crate::vectors::Vectors;
+use crate::weighting::DocumentFrequencies;
+use crate::{sort_ranking, HulthDocument};
+
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter};
+use std::path::Path;
+
+/// a trained model as written by `train`. features are standardized
+/// with the means and scales of the training set before weighting
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Weights {
+    features: Vec<String>,
+    means: Vec<f64>,
+    scales: Vec<f64>,
+    weights: Vec<f64>,
+    bias: f64,
+}
+
+/// options of the gradient descent
+#[derive(Debug, Clone, Copy)]
+pub struct Training {
+    pub epochs: usize,
+    pub learning_rate: f64,
+    pub l2: f64,
+}
+
+impl Weights {
+    /// fits the weights by batch gradient descent on the log loss
+    pub fn train(samples: &[([f64; 7], bool)], training: Training) -> Self {
+        let n = samples.len().max(1) as f64;
+        let mut means = vec![0.0; NUMERIC.len()];
+        for (x, _) in samples {
+            for (m, v) in means.iter_mut().zip(x) {
+                *m += v / n;
+            }
+        }
+        let mut scales = vec![0.0; NUMERIC.len()];
+        for (x, _) in samples {
+            for ((s, v), m) in scales.iter_mut().zip(x).zip(&means) {
+                *s += (v - m).powi(2) / n;
+            }
+        }
+        for s in &mut scales {
+            *s = if *s > 0.0 { s.sqrt() } else { 1.0 };
+        }
+
+        let mut model = Weights {
+            features: NUMERIC.iter().map(|f| f.to_string()).collect(),
+            means,
+            scales,
+            weights: vec![0.0; NUMERIC.len()],
+            bias: 0.0,
+        };
+        let standardized = samples
+            .iter()
+            .map(|(x, label)| (model.standardize(x), *label as u8 as f64))
+            .collect::<Vec<_>>();
+        for epoch in 0..training.epochs {
+            let mut gradient = vec![0.0; NUMERIC.len()];
+            let mut bias = 0.0;
+            let mut loss = 0.0;
+            for (x, y) in &standardized {
+                let p = sigmoid(model.linear(x));
+                let error = p - y;
+                for (g, v) in gradient.iter_mut().zip(x) {
+                    *g += error * v / n;
+                }
+                bias += error / n;
+                loss -= (y * p.max(1e-12).ln() + (1.0 - y) * (1.0 - p).max(1e-12).ln()) / n;
+            }
+            for (w, g) in model.weights.iter_mut().zip(&gradient) {
+                *w -= training.learning_rate * (g + training.l2 * *w);
+            }
+            model.bias -= training.learning_rate * bias;
+            trace!(epoch, loss, "trained epoch");
+        }
+        model
+    }
+
+    /// the probability of a candidate with features x being a keyphrase
+    pub fn probability(&self, x: &[f64; 7]) -> f64 {
+        sigmoid(self.linear(&self.standardize(x)))
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer_pretty(writer, self)?;
+        Ok(())
+    }
+
+    /// reads weights, failing if they were trained on other features
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let reader = BufReader::new(File::open(path)?);
+        let weights: Weights = serde_json::from_reader(reader)?;
+        let expected = NUMERIC.iter().map(|f| f.to_string()).collect::<Vec<_>>();
+        let lengths = [&weights.means, &weights.scales, &weights.weights];
+        if weights.features != expected || lengths.iter().any(|v| v.len() != NUMERIC.len()) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("reranker weights are not for the features {:?}", NUMERIC),
+            ));
+        }
+        Ok(weights)
+    }
+
+    fn standardize(&self, x: &[f64; 7]) -> Vec<f64> {
+        x.iter()
+            .zip(&self.means)
+            .zip(&self.scales)
+            .map(|((v, m), s)| (v - m) / s)
+            .collect()
+    }
+
+    fn linear(&self, x: &[f64]) -> f64 {
+        self.bias + self.weights.iter().zip(x).map(|(w, v)| w * v).sum::<f64>()
+    }
+}
+
+/// what maps the features of a candidate to its score
+#[derive(Debug)]
+pub enum Scorer {
+    Logistic(Weights),
+    #[cfg(feature = "onnx")]
+    Onnx(OnnxModel),
+    /// scores whole documents, see `rerank::embed_rank`
+    Embedding(Vectors),
+}
+
+impl Scorer {
+    fn score(&self, x: &[f64; 7]) -> f64 {
+        match self {
+            Scorer::Logistic(weights) => weights.probability(x),
+            #[cfg(feature = "onnx")]
+            Scorer::Onnx(model) => model.score(x).unwrap_or_else(|e| {
+                warn!(error = %e, "failed to score candidate");
+                f64::NEG_INFINITY
+            }),
+            Scorer::Embedding(_) => unreachable!("embeddings rescore whole documents"),
+        }
+    }
+}
+
+/// a scorer with the document frequencies of the corpus ranked against
+#[derive(Debug)]
+pub struct Reranker {
+    scorer: Scorer,
+    frequencies: DocumentFrequencies,
+}
+
+impl Reranker {
+    pub fn new(scorer: Scorer, frequencies: DocumentFrequencies) -> Self {
+        Reranker {
+            scorer,
+            frequencies,
+        }
+    }
+
+    /// the candidates of doc scored by the scorer, best first
+    pub fn rescore(&self, doc: &HulthDocument, ranked: Vec<(String, f64)>) -> Vec<(String, f64)> {
+        if let Scorer::Embedding(vectors) = &self.scorer {
+            return rerank::embed_rank(vectors, doc, ranked);
+        }
+        let features = features::extract(doc, &ranked, &self.frequencies);
+        sort_ranking(
+            ranked
+                .into_iter()
+                .zip(features)
+                .map(|((term, _), f)| (term, self.scorer.score(&f.values()))),
+        )
+    }
+}
+
+fn sigmoid(x: f64) -> f64 {
+    1.0 / (1.0 + (-x).exp())
+}
@@ -0,0 +1,35 @@
+//! the configuration of a run, written next to its results so
+//! the numbers can be traced back to the options that produced them
+use serde::Serialize;
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+#[derive(Debug, Serialize)]
+struct Manifest<'a, O> {
+    version: &'static str,
+    command: &'a str,
+    /// the --seed of every random choice, repeating it with the options repeats the run
+    seed: u64,
+    options: &'a O,
+}
+
+/// writes the effective options of command, after the config file
+/// and the environment were applied
+pub fn write<P: AsRef<Path>, O: Serialize>(
+    path: P,
+    command: &str,
+    seed: u64,
+    options: &O,
+) -> io::Result<()> {
+    let manifest = Manifest {
+        version: env!("CARGO_PKG_VERSION"),
+        command,
+        seed,
+        options,
+    };
+    let mut writer = BufWriter::new(File::create(path)?);
+    serde_json::to_writer_pretty(&mut writer, &manifest)?;
+    writeln!(writer)
+}
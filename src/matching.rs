@@ -0,0 +1,271 @@
+use crate::intern::Interner;
+use crate::preprocess::Normalizer;
+use crate::synonyms::SynonymFile;
+use crate::{metrics, phrases, HulthDocument};
+
+use clap::{Args, ValueEnum};
+
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// how reference keyphrases are split into the terms they are matched as
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Args, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ReferenceTokenization {
+    /// where reference keyphrases are split into terms
+    #[arg(long = "reference-split", value_enum, default_value_t = Split::Space)]
+    pub split: Split,
+
+    /// drops the parenthesized parts of reference keyphrases such as `(SVM)`
+    #[arg(long = "reference-strip-parens")]
+    pub strip_parens: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Split {
+    /// on single spaces, the words of the Hulth keyphrases
+    #[default]
+    Space,
+    /// on whitespace and punctuation, `real-time` becomes `real` and `time`
+    Punctuation,
+    /// not at all, every keyphrase is one term to match phrase candidates
+    Phrase,
+}
+
+impl ReferenceTokenization {
+    /// the words of phrase, a phrase of --reference-split phrase is
+    /// split on spaces as its words are still needed to match tokens
+    pub fn words(self, phrase: &str) -> Vec<String> {
+        let phrase = if self.strip_parens {
+            strip_parens(phrase)
+        } else {
+            phrase.into()
+        };
+        let words: Vec<&str> = match self.split {
+            Split::Space | Split::Phrase => phrase.split(' ').collect(),
+            Split::Punctuation => phrase
+                .split(|c: char| c.is_whitespace() || c.is_ascii_punctuation())
+                .collect(),
+        };
+        words
+            .into_iter()
+            .filter(|w| !w.is_empty())
+            .map(String::from)
+            .collect()
+    }
+}
+
+/// how ranked terms are matched against the reference terms
+#[derive(Debug, Clone, Default, PartialEq, Eq, Args, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Matching {
+    /// how ranked terms are matched against the references
+    #[arg(long, value_enum, default_value_t = MatchMode::Exact)]
+    pub match_mode: MatchMode,
+
+    /// synonym classes of --match-mode synonym, a TSV file of one class per line
+    /// or the `wn_s.pl` of the WordNet Prolog export
+    #[arg(long, required_if_eq("match_mode", "synonym"))]
+    pub synonyms: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchMode {
+    /// a ranked term matches the reference term it equals after normalization
+    #[default]
+    Exact,
+    /// a ranked term also matches the reference terms it shares a class of --synonyms with
+    Synonym,
+}
+
+impl Matching {
+    /// the synonym file if synonyms are matched
+    pub fn synonym_file(&self) -> io::Result<Option<SynonymFile>> {
+        match (self.match_mode, &self.synonyms) {
+            (MatchMode::Synonym, Some(path)) => SynonymFile::read(path).map(Some),
+            (MatchMode::Synonym, None) => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "--match-mode synonym needs --synonyms",
+            )),
+            (MatchMode::Exact, _) => Ok(None),
+        }
+    }
+}
+
+/// a ranked term that matched a reference term through a synonym
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SynonymMatch {
+    pub term: String,
+    pub reference: String,
+}
+
+/// phrase without the text between parentheses, the parentheses included
+fn strip_parens(phrase: &str) -> String {
+    let mut depth = 0usize;
+    let mut stripped = String::with_capacity(phrase.len());
+    for c in phrase.chars() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            c if depth == 0 => stripped.push(c),
+            _ => {}
+        }
+    }
+    stripped
+}
+
+/// the reference keywords of one document, split into single tokens
+/// normalized like the candidates, plus their phrases of up to
+/// the maximum phrase length when phrases are candidates
+#[derive(Debug)]
+pub struct References {
+    /// each token with its number of occurrences
+    tokens: HashMap<Arc<str>, usize>,
+    /// number of reference tokens including repeats,
+    /// the denominator of recall
+    total: usize,
+    /// the synonyms of the tokens that aren't tokens themselves, with the token they match
+    synonyms: HashMap<Arc<str>, Arc<str>>,
+}
+
+impl References {
+    /// splits every keyphrase of every keyword group as --reference-split says
+    pub fn new(keywords: &[Vec<String>], normalizer: &Normalizer, interner: &mut Interner) -> Self {
+        let mut tokens = HashMap::new();
+        let mut total = 0;
+        for phrase in keywords.iter().flat_map(|group| group.iter()) {
+            let terms = normalizer.reference_terms(phrase);
+            for term in terms.iter().flatten() {
+                *tokens.entry(interner.intern(term)).or_insert(0) += 1;
+                total += 1;
+            }
+            if normalizer.ngrams() || normalizer.chunks() {
+                let mut phrases = HashSet::new();
+                for n in 2..=normalizer.max_phrase_len() {
+                    for window in terms.windows(n) {
+                        if let Some(words) = window.iter().cloned().collect::<Option<Vec<_>>>() {
+                            phrases.insert(words.join(" "));
+                        }
+                    }
+                }
+                for phrase in phrases {
+                    *tokens.entry(interner.intern(&phrase)).or_insert(0) += 1;
+                    total += 1;
+                }
+            }
+        }
+        let mut synonyms = HashMap::new();
+        if let Some(classes) = normalizer.synonyms() {
+            let mut terms = tokens.keys().cloned().collect::<Vec<_>>();
+            // a synonym of several tokens matches the first of them
+            terms.sort_unstable();
+            for term in terms {
+                for synonym in classes.of(&term) {
+                    if !tokens.contains_key(synonym) && !synonyms.contains_key(synonym) {
+                        synonyms.insert(interner.intern(synonym), term.clone());
+                    }
+                }
+            }
+        }
+        References {
+            tokens,
+            total,
+            synonyms,
+        }
+    }
+
+    pub fn contains(&self, term: &str) -> bool {
+        self.tokens.contains_key(term) || self.synonyms.contains_key(term)
+    }
+
+    /// the token term matches through a synonym, None if it is a token or no match
+    pub fn via_synonym(&self, term: &str) -> Option<&Arc<str>> {
+        self.synonyms.get(term)
+    }
+
+    /// whether each ranked term is relevant, see `metrics::hits`.
+    /// a synonym counts as the token it matches, so ranking both is one hit
+    pub fn hits(&self, terms: &[&str]) -> Vec<bool> {
+        let terms = terms
+            .iter()
+            .map(|term| self.via_synonym(term).map_or(*term, |token| &**token))
+            .collect::<Vec<_>>();
+        metrics::hits(&terms, |term| self.contains(term))
+    }
+
+    /// the share of the distinct words of the tokens found among the words of terms,
+    /// see `metrics::coverage`. a synonym counts as the words of the token it matches
+    pub fn coverage(&self, terms: &[&str]) -> f64 {
+        let reference = self
+            .tokens
+            .keys()
+            .flat_map(|token| token.split(' '))
+            .collect::<HashSet<_>>();
+        let predicted = terms
+            .iter()
+            .map(|term| self.via_synonym(term).map_or(*term, |token| &**token))
+            .flat_map(|term| term.split(' '))
+            .collect::<HashSet<_>>();
+        metrics::coverage(&reference, &predicted)
+    }
+
+    /// the distinct tokens with their number of occurrences
+    pub fn iter(&self) -> impl Iterator<Item = (&Arc<str>, usize)> {
+        self.tokens.iter().map(|(token, n)| (token, *n))
+    }
+
+    pub fn len(&self) -> usize {
+        self.total
+    }
+}
+
+/// the keyphrases whose words never occur one after another in a sentence of doc,
+/// compared by their stems. no extractor can find them, so they bound the recall.
+/// doc and the words of the keyphrases are normalized the same way by normalizer
+pub fn unreachable<'a>(
+    doc: &HulthDocument,
+    keyphrases: &'a [Vec<String>],
+    normalizer: &Normalizer,
+) -> Vec<&'a str> {
+    let sentences = doc
+        .sentences
+        .iter()
+        .map(|sentence| {
+            sentence
+                .tokens
+                .iter()
+                .filter(|t| !phrases::is_phrase(&t.word))
+                .map(|t| normalizer.stem(&t.word))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    keyphrases
+        .iter()
+        .flatten()
+        .filter(|phrase| {
+            let words = normalizer
+                .reference_words(phrase)
+                .iter()
+                .map(|word| normalizer.stem(word))
+                .collect::<Vec<_>>();
+            !words.is_empty()
+                && !sentences
+                    .iter()
+                    .any(|stems| stems.windows(words.len()).any(|window| window == words))
+        })
+        .map(String::as_str)
+        .collect()
+}
+
+/// the ranked terms found in references, in rank order
+pub fn relevant<'a>(ranked: &'a [(Arc<str>, f64)], references: &References) -> Vec<&'a Arc<str>> {
+    ranked
+        .iter()
+        .map(|(term, _)| term)
+        .filter(|term| references.contains(term))
+        .collect()
+}
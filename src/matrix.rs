@@ -0,0 +1,99 @@
+//! the document-term tf-idf matrix of a corpus as a sparse matrix,
+//! with the vocabulary and document names as line-per-entry text files
+use clap::ValueEnum;
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MatrixFormat {
+    /// `matrix.mtx`, Matrix Market coordinate format with 1-based indices
+    Mtx,
+    /// `matrix.json`, compressed sparse rows as for scipy.sparse.csr_matrix
+    CsrJson,
+}
+
+/// compressed sparse rows, columns sorted within each row
+#[derive(Debug, Default, Serialize)]
+pub struct Csr {
+    shape: (usize, usize),
+    indptr: Vec<usize>,
+    indices: Vec<usize>,
+    data: Vec<f64>,
+}
+
+/// the rows of named documents over the vocabulary of all their terms,
+/// columns ordered by term
+pub fn build(rows: Vec<(String, Vec<(String, f64)>)>) -> (Vec<String>, Vec<String>, Csr) {
+    let mut vocabulary = BTreeMap::new();
+    for (_, scores) in &rows {
+        for (term, _) in scores {
+            vocabulary.entry(term.clone()).or_insert(0);
+        }
+    }
+    for (column, index) in vocabulary.values_mut().enumerate() {
+        *index = column;
+    }
+
+    let mut csr = Csr {
+        shape: (rows.len(), vocabulary.len()),
+        indptr: vec![0],
+        ..Csr::default()
+    };
+    let mut documents = Vec::with_capacity(rows.len());
+    for (name, scores) in rows {
+        let mut row = scores
+            .into_iter()
+            .map(|(term, score)| (vocabulary[&term], score))
+            .collect::<Vec<_>>();
+        row.sort_by_key(|(column, _)| *column);
+        for (column, score) in row {
+            csr.indices.push(column);
+            csr.data.push(score);
+        }
+        csr.indptr.push(csr.indices.len());
+        documents.push(name);
+    }
+    (documents, vocabulary.into_keys().collect(), csr)
+}
+
+/// writes the matrix in format and `vocabulary.txt` and `documents.txt` to dir
+pub fn write(
+    dir: &Path,
+    format: MatrixFormat,
+    documents: &[String],
+    vocabulary: &[String],
+    csr: &Csr,
+) -> io::Result<()> {
+    write_lines(&dir.join("documents.txt"), documents)?;
+    write_lines(&dir.join("vocabulary.txt"), vocabulary)?;
+    match format {
+        MatrixFormat::Mtx => {
+            let mut w = BufWriter::new(File::create(dir.join("matrix.mtx"))?);
+            writeln!(w, "%%MatrixMarket matrix coordinate real general")?;
+            writeln!(w, "{} {} {}", csr.shape.0, csr.shape.1, csr.data.len())?;
+            for (row, range) in csr.indptr.windows(2).enumerate() {
+                for i in range[0]..range[1] {
+                    writeln!(w, "{} {} {}", row + 1, csr.indices[i] + 1, csr.data[i])?;
+                }
+            }
+            w.flush()
+        }
+        MatrixFormat::CsrJson => {
+            let mut w = BufWriter::new(File::create(dir.join("matrix.json"))?);
+            serde_json::to_writer(&mut w, csr)?;
+            w.flush()
+        }
+    }
+}
+
+fn write_lines(path: &Path, lines: &[String]) -> io::Result<()> {
+    let mut w = BufWriter::new(File::create(path)?);
+    for line in lines {
+        writeln!(w, "{}", line)?;
+    }
+    w.flush()
+}
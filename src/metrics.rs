@@ -0,0 +1,259 @@
+//! the measures of a ranking against its references and their means over documents.
+//! a ranking is given as its hits, whether each ranked term is relevant, best first.
+//! terms are compared as they are, normalizing them is up to the caller.
+//! measures without a defined value, such as precision without predictions, are 0
+use std::collections::HashSet;
+use std::hash::Hash;
+
+/// whether each term of ranked is relevant, in rank order.
+/// a term ranked more than once is only a hit the first time
+pub fn hits<T, F>(ranked: &[T], is_relevant: F) -> Vec<bool>
+where
+    T: Eq + Hash,
+    F: Fn(&T) -> bool,
+{
+    let mut seen = HashSet::new();
+    ranked
+        .iter()
+        .map(|term| seen.insert(term) && is_relevant(term))
+        .collect()
+}
+
+/// the share of relevant terms among the ranked terms
+pub fn precision(hits: &[bool]) -> f64 {
+    ratio(count(hits), hits.len())
+}
+
+/// the share of the reference terms that were ranked,
+/// reference is their number including repeats
+pub fn recall(hits: &[bool], reference: usize) -> f64 {
+    ratio(count(hits), reference)
+}
+
+/// the harmonic mean of precision and recall
+pub fn f1(precision: f64, recall: f64) -> f64 {
+    if precision == 0f64 || recall == 0f64 {
+        return 0f64;
+    }
+    let tmp = (precision * recall) / (precision + recall);
+    2f64 * tmp
+}
+
+/// the share of the distinct reference words among the predicted words, a softer
+/// recall that gives credit for the words of partially found keyphrases
+pub fn coverage<T: Eq + Hash>(reference: &HashSet<T>, predicted: &HashSet<T>) -> f64 {
+    ratio(reference.intersection(predicted).count(), reference.len())
+}
+
+/// the mean of the precisions at the rank of every hit over the reference terms,
+/// the mean of this over documents is the MAP
+pub fn average_precision(hits: &[bool], reference: usize) -> f64 {
+    let mut found = 0;
+    let mut sum = 0f64;
+    for (i, hit) in hits.iter().enumerate() {
+        if *hit {
+            found += 1;
+            sum += found as f64 / (i + 1) as f64;
+        }
+    }
+    if reference == 0 {
+        return 0f64;
+    }
+    sum / reference as f64
+}
+
+/// the discounted cumulative gain of binary relevance over that of the ideal
+/// ranking of the same length, which ranks min(reference, hits.len()) hits first
+pub fn ndcg(hits: &[bool], reference: usize) -> f64 {
+    let gain = |i: usize| 1f64 / (i as f64 + 2f64).log2();
+    let dcg = hits
+        .iter()
+        .enumerate()
+        .filter(|(_, hit)| **hit)
+        .map(|(i, _)| gain(i))
+        .sum::<f64>();
+    let ideal = (0..reference.min(hits.len())).map(gain).sum::<f64>();
+    if ideal == 0f64 {
+        return 0f64;
+    }
+    dcg / ideal
+}
+
+/// a mean over the finite values only
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Mean {
+    /// 0 without finite values
+    pub value: f64,
+    /// number of finite values
+    pub valid: usize,
+    /// number of NaN or infinite values left out
+    pub invalid: usize,
+}
+
+impl Mean {
+    /// the value with four decimals, `n/a` without finite values
+    pub fn format(&self) -> String {
+        match self.valid {
+            0 => "n/a".into(),
+            _ => format!("{:.4}", self.value),
+        }
+    }
+}
+
+pub fn mean(v: &[f64]) -> Mean {
+    let mut running = Running::default();
+    for x in v {
+        running.push(*x);
+    }
+    running.mean()
+}
+
+/// a mean taken value by value without keeping them,
+/// equal to `mean` of the values in the same order
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Running {
+    sum: f64,
+    valid: usize,
+    invalid: usize,
+}
+
+impl Running {
+    pub fn push(&mut self, x: f64) {
+        if x.is_finite() {
+            self.sum += x;
+            self.valid += 1;
+        } else {
+            self.invalid += 1;
+        }
+    }
+
+    pub fn mean(&self) -> Mean {
+        Mean {
+            value: if self.valid == 0 {
+                0f64
+            } else {
+                self.sum / self.valid as f64
+            },
+            valid: self.valid,
+            invalid: self.invalid,
+        }
+    }
+}
+
+fn count(hits: &[bool]) -> usize {
+    hits.iter().filter(|hit| **hit).count()
+}
+
+fn ratio(part: usize, whole: usize) -> f64 {
+    if whole == 0 {
+        return 0f64;
+    }
+    part as f64 / whole as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64) {
+        assert!((a - b).abs() < 1e-9, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn hits_count_a_repeated_term_once() {
+        let reference = ["tfidf", "keyword"];
+        let ranked = ["tfidf", "the", "tfidf", "keyword"];
+        let hits = hits(&ranked, |t| reference.contains(t));
+        assert_eq!(hits, [true, false, false, true]);
+    }
+
+    #[test]
+    fn precision_recall_f1() {
+        // 2 of 4 ranked terms are among 5 reference terms
+        let hits = [true, false, true, false];
+        close(precision(&hits), 0.5);
+        close(recall(&hits, 5), 0.4);
+        close(f1(0.5, 0.4), 2.0 * 0.2 / 0.9);
+    }
+
+    #[test]
+    fn perfect_ranking() {
+        let hits = [true, true, true];
+        close(precision(&hits), 1.0);
+        close(recall(&hits, 3), 1.0);
+        close(f1(1.0, 1.0), 1.0);
+        close(average_precision(&hits, 3), 1.0);
+        close(ndcg(&hits, 3), 1.0);
+    }
+
+    #[test]
+    fn average_precision_by_hand() {
+        // hits at ranks 1 and 3: (1/1 + 2/3) / 4 reference terms
+        let hits = [true, false, true, false];
+        close(average_precision(&hits, 4), (1.0 + 2.0 / 3.0) / 4.0);
+    }
+
+    #[test]
+    fn ndcg_by_hand() {
+        // hits at ranks 2 and 3 of 3, ideal ranks 2 hits first
+        let hits = [false, true, true];
+        let dcg = 1.0 / 3f64.log2() + 1.0 / 4f64.log2();
+        let ideal = 1.0 + 1.0 / 3f64.log2();
+        close(ndcg(&hits, 2), dcg / ideal);
+    }
+
+    #[test]
+    fn ndcg_of_more_references_than_ranked_terms() {
+        // a top-2 ranking of two hits is ideal even with 10 reference terms
+        close(ndcg(&[true, true], 10), 1.0);
+    }
+
+    #[test]
+    fn empty_predictions() {
+        let hits: [bool; 0] = [];
+        close(precision(&hits), 0.0);
+        close(recall(&hits, 3), 0.0);
+        close(f1(0.0, 0.0), 0.0);
+        close(average_precision(&hits, 3), 0.0);
+        close(ndcg(&hits, 3), 0.0);
+    }
+
+    #[test]
+    fn empty_references() {
+        let hits = [false, false];
+        close(precision(&hits), 0.0);
+        close(recall(&hits, 0), 0.0);
+        close(average_precision(&hits, 0), 0.0);
+        close(ndcg(&hits, 0), 0.0);
+    }
+
+    #[test]
+    fn coverage_counts_distinct_words() {
+        let reference = HashSet::from(["keyword", "extraction", "tfidf"]);
+        let predicted = HashSet::from(["keyword", "the", "tfidf"]);
+        close(coverage(&reference, &predicted), 2.0 / 3.0);
+        close(coverage(&HashSet::new(), &predicted), 0.0);
+    }
+
+    #[test]
+    fn f1_is_zero_if_either_side_is() {
+        close(f1(0.0, 1.0), 0.0);
+        close(f1(1.0, 0.0), 0.0);
+    }
+
+    #[test]
+    fn mean_leaves_out_non_finite_values() {
+        let m = mean(&[0.5, f64::NAN, 1.0, f64::INFINITY]);
+        close(m.value, 0.75);
+        assert_eq!((m.valid, m.invalid), (2, 2));
+        assert_eq!(m.format(), "0.7500");
+    }
+
+    #[test]
+    fn mean_of_nothing_is_not_available() {
+        let m = mean(&[]);
+        assert_eq!((m.valid, m.invalid), (0, 0));
+        assert_eq!(m.format(), "n/a");
+        assert_eq!(mean(&[f64::NAN]).format(), "n/a");
+    }
+}
@@ -0,0 +1,160 @@
+use crate::corpus::Corpus;
+use crate::intern::Interner;
+use crate::preprocess::Preprocessing;
+use crate::weighting::DocumentFrequencies;
+use crate::HulthDocument;
+
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::Path;
+
+/// a fitted corpus as written by `eval --save-model`.
+/// the document frequencies are stored with the documents and kept up to date
+/// by `update-model`, the statistics the harness computes are read from them.
+/// lib_tfidf has no serialized form of its own and takes no frequencies,
+/// its scores still need the documents.
+/// documents ranked against the model need the same preprocessing
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Model {
+    pub(crate) corpus: Corpus<HulthDocument>,
+    pub(crate) preprocessing: Preprocessing,
+    pub(crate) frequencies: DocumentFrequencies,
+}
+
+impl Model {
+    pub fn new(corpus: Corpus<HulthDocument>, preprocessing: Preprocessing) -> Self {
+        let frequencies = DocumentFrequencies::new(&corpus.boxed());
+        Model {
+            corpus,
+            preprocessing,
+            frequencies,
+        }
+    }
+
+    /// adds the documents of corpus and their counts, replacing the documents
+    /// of the same name, and returns the number replaced
+    pub fn add(&mut self, corpus: Corpus<HulthDocument>) -> usize {
+        for (_, doc) in corpus.shared() {
+            self.frequencies.add(&*doc);
+        }
+        let replaced = self.corpus.merge(corpus);
+        for old in &replaced {
+            self.frequencies.subtract(&**old);
+        }
+        replaced.len()
+    }
+
+    /// removes the documents named in ids and subtracts their counts.
+    /// fails without removing any if an id isn't in the model
+    pub fn remove(&mut self, ids: &HashSet<&str>) -> io::Result<usize> {
+        let names = self.corpus.names().collect::<HashSet<_>>();
+        let mut unknown = ids.difference(&names).copied().collect::<Vec<_>>();
+        if !unknown.is_empty() {
+            unknown.sort_unstable();
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("not in the model: {}", unknown.join(", ")),
+            ));
+        }
+        let removed = self.corpus.remove(ids);
+        for doc in &removed {
+            self.frequencies.subtract(&**doc);
+        }
+        Ok(removed.len())
+    }
+
+    /// writes the model through a temporary file, so a failed write
+    /// leaves a model already at path intact
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        let partial = path.with_extension("partial");
+        let mut writer = BufWriter::new(File::create(&partial)?);
+        bincode::serialize_into(&mut writer, self).map_err(io::Error::other)?;
+        writer.flush()?;
+        fs::rename(partial, path)
+    }
+
+    /// reads a model, interning the strings of its documents
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let reader = BufReader::new(File::open(path)?);
+        let mut model: Model = bincode::deserialize_from(reader).map_err(io::Error::other)?;
+        let mut interner = Interner::new();
+        for doc in model.corpus.documents_mut() {
+            interner.intern_document(doc);
+        }
+        Ok(model)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{HulthToken, Sentence};
+
+    /// a document of one sentence of words
+    fn document(words: &[&str]) -> HulthDocument {
+        let tokens = words
+            .iter()
+            .map(|word| HulthToken {
+                word: (*word).into(),
+                lemma: (*word).into(),
+                offset_begin: 0,
+                offset_end: 0,
+                pos: "".into(),
+            })
+            .collect();
+        HulthDocument {
+            sentences: vec![Sentence { tokens }],
+        }
+    }
+
+    fn corpus(docs: &[(&str, &[&str])]) -> Corpus<HulthDocument> {
+        let mut corpus = Corpus::new();
+        for (name, words) in docs {
+            corpus.push(name.to_string(), document(words));
+        }
+        corpus
+    }
+
+    #[test]
+    fn removing_documents_leaves_the_frequencies_of_the_rest() {
+        let all = [
+            ("a", &["tree", "graph", "tree"][..]),
+            ("b", &["graph", "node"][..]),
+            ("c", &["node", "edge"][..]),
+        ];
+        let mut model = Model::new(corpus(&all), Preprocessing::default());
+        let removed = model.remove(&HashSet::from(["b"])).unwrap();
+        assert_eq!(removed, 1);
+        let rest = Model::new(corpus(&[all[0], all[2]]), Preprocessing::default());
+        assert_eq!(model.frequencies, rest.frequencies);
+        assert_eq!(model.frequencies.documents(), 2);
+    }
+
+    #[test]
+    fn replacing_a_document_swaps_its_counts() {
+        let mut model = Model::new(
+            corpus(&[("a", &["tree"][..]), ("b", &["graph"][..])]),
+            Preprocessing::default(),
+        );
+        let replaced = model.add(corpus(&[("b", &["node"][..]), ("c", &["edge"][..])]));
+        assert_eq!(replaced, 1);
+        let expected = corpus(&[
+            ("a", &["tree"][..]),
+            ("b", &["node"][..]),
+            ("c", &["edge"][..]),
+        ]);
+        let expected = Model::new(expected, Preprocessing::default());
+        assert_eq!(model.frequencies, expected.frequencies);
+    }
+
+    #[test]
+    fn removing_an_unknown_id_fails_and_keeps_the_model() {
+        let mut model = Model::new(corpus(&[("a", &["tree"][..])]), Preprocessing::default());
+        let err = model.remove(&HashSet::from(["a", "z"])).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(model.corpus.len(), 1);
+        assert_eq!(model.frequencies.documents(), 1);
+    }
+}
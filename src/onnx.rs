@@ -0,0 +1,57 @@
+//! externally trained rerankers as ONNX models, run with tract.
+//! a model takes a `1 x 7` f32 tensor of the features named by
+//! `features::NUMERIC` and returns the score of the candidate as its first output
+use crate::features::NUMERIC;
+
+use tract_onnx::prelude::*;
+
+use std::io;
+use std::path::Path;
+
+type Plan = SimplePlan<TypedFact, Box<dyn TypedOp>, Graph<TypedFact, Box<dyn TypedOp>>>;
+
+#[derive(Debug)]
+pub struct OnnxModel {
+    plan: Plan,
+}
+
+impl OnnxModel {
+    /// reads and optimizes the model, failing if it does not
+    /// score a feature vector as described above
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let plan = tract_onnx::onnx()
+            .model_for_path(path)
+            .and_then(|m| m.with_input_fact(0, f32::fact([1, NUMERIC.len()]).into()))
+            .and_then(|m| m.into_optimized())
+            .and_then(|m| m.into_runnable())
+            .map_err(invalid)?;
+        let model = OnnxModel { plan };
+        model.score(&[0.0; 7])?;
+        Ok(model)
+    }
+
+    pub fn score(&self, x: &[f64; 7]) -> io::Result<f64> {
+        let input = x.iter().map(|v| *v as f32).collect::<Vec<_>>();
+        let input = Tensor::from_shape(&[1, NUMERIC.len()], &input).map_err(invalid)?;
+        let outputs = self.plan.run(tvec!(input.into())).map_err(invalid)?;
+        let output = outputs
+            .first()
+            .ok_or_else(|| invalid("the model has no output"))?
+            .cast_to::<f32>()
+            .map_err(invalid)?;
+        let score = output
+            .as_slice::<f32>()
+            .map_err(invalid)?
+            .first()
+            .copied()
+            .ok_or_else(|| invalid("the output of the model is empty"))?;
+        Ok(score as f64)
+    }
+}
+
+fn invalid<E: ToString>(e: E) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("onnx reranker: {}", e.to_string()),
+    )
+}
@@ -0,0 +1,114 @@
+//! phrase candidates, tokens spanning several words of a sentence,
+//! and how overlapping ones are resolved
+use crate::{HulthDocument, HulthToken};
+
+use clap::ValueEnum;
+
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+
+/// which of two phrase candidates overlapping in a document is kept
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Overlap {
+    /// both, every phrase stays a candidate
+    #[default]
+    KeepBoth,
+    /// the phrase of more words, decided while generating the candidates
+    Longest,
+    /// the phrase of higher score, decided after scoring
+    Highest,
+}
+
+/// a token spanning parts, each a token and the word it contributes
+pub fn phrase<'a, I>(parts: I) -> HulthToken
+where
+    I: IntoIterator<Item = (&'a HulthToken, &'a str)>,
+{
+    let parts = parts.into_iter().collect::<Vec<_>>();
+    let words = parts.iter().map(|(_, word)| *word).collect::<Vec<_>>();
+    let lemmas = parts.iter().map(|(t, _)| &*t.lemma).collect::<Vec<_>>();
+    let tags = parts.iter().map(|(t, _)| &*t.pos).collect::<Vec<_>>();
+    HulthToken {
+        word: words.join(" ").into(),
+        lemma: lemmas.join(" ").into(),
+        offset_begin: parts.first().map_or(0, |(t, _)| t.offset_begin),
+        offset_end: parts.last().map_or(0, |(t, _)| t.offset_end),
+        pos: tags.join(" ").into(),
+    }
+}
+
+pub fn is_phrase(term: &str) -> bool {
+    term.contains(' ')
+}
+
+/// drops the phrase tokens overlapping a longer phrase token,
+/// of equally long ones the first is kept
+pub fn keep_longest(tokens: Vec<HulthToken>) -> Vec<HulthToken> {
+    let words = |t: &HulthToken| t.word.split(' ').count();
+    let mut phrases = tokens
+        .iter()
+        .enumerate()
+        .filter(|(_, t)| is_phrase(&t.word))
+        .collect::<Vec<_>>();
+    phrases.sort_by(|(i, a), (j, b)| words(b).cmp(&words(a)).then(i.cmp(j)));
+    let dropped = greedy(
+        phrases
+            .iter()
+            .map(|(i, t)| (*i, t.offset_begin, t.offset_end)),
+    );
+    tokens
+        .into_iter()
+        .enumerate()
+        .filter(|(i, _)| !dropped.contains(i))
+        .map(|(_, t)| t)
+        .collect()
+}
+
+/// drops the phrases of scores whose every occurrence in doc
+/// overlaps an occurrence of a higher scoring phrase
+pub fn keep_highest(doc: &HulthDocument, scores: Vec<(String, f64)>) -> Vec<(String, f64)> {
+    let score = scores
+        .iter()
+        .filter(|(term, _)| is_phrase(term))
+        .map(|(term, score)| (term.as_str(), *score))
+        .collect::<HashMap<_, _>>();
+    let mut occurrences = doc
+        .sentences
+        .iter()
+        .flat_map(|s| s.tokens.iter())
+        .filter_map(|t| score.get(&*t.word).map(|score| (t, *score)))
+        .collect::<Vec<_>>();
+    occurrences.sort_by(|(_, a), (_, b)| b.partial_cmp(a).unwrap_or(Ordering::Equal));
+    let dropped = greedy(
+        occurrences
+            .iter()
+            .enumerate()
+            .map(|(i, (t, _))| (i, t.offset_begin, t.offset_end)),
+    );
+    let kept = occurrences
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| !dropped.contains(i))
+        .map(|(_, (t, _))| &*t.word)
+        .collect::<HashSet<_>>();
+    scores
+        .into_iter()
+        .filter(|(term, _)| !is_phrase(term) || kept.contains(term.as_str()))
+        .collect()
+}
+
+/// takes the spans in order of preference and returns the ids
+/// of those overlapping a span taken before them
+fn greedy<I: IntoIterator<Item = (usize, i64, i64)>>(spans: I) -> HashSet<usize> {
+    let mut taken: Vec<(i64, i64)> = vec![];
+    let mut dropped = HashSet::new();
+    for (id, begin, end) in spans {
+        if taken.iter().any(|(b, e)| begin < *e && *b < end) {
+            dropped.insert(id);
+        } else {
+            taken.push((begin, end));
+        }
+    }
+    dropped
+}
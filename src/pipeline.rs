@@ -0,0 +1,118 @@
+//! eval as a pipeline of threads connected by bounded channels:
+//! a loader reads documents, the calling thread ranks them since the fitted
+//! model borrows a corpus that can't be shared across threads, scoring workers
+//! compare the rankings against the references and a collector aggregates
+use crate::intern::Interner;
+use crate::preprocess::Normalizer;
+use crate::results::DocumentResult;
+use crate::{score_document, HulthDocument, HulthDocumentKeywords};
+
+use crossbeam_channel::bounded;
+
+use std::collections::BTreeMap;
+use std::io;
+use std::sync::Arc;
+use std::thread;
+
+/// documents or rankings in flight per worker
+const QUEUE_PER_JOB: usize = 4;
+
+/// runs every document of source through rank and the scoring workers,
+/// calling collect for each measure in source order with what source gave along
+/// with its document. every worker compares with its own normalizer, made by
+/// normalizer, the best top_k of the ranking. returns the measures in source
+/// order, none unless keep
+#[allow(clippy::too_many_arguments)]
+pub fn run<S, E, R, N, C>(
+    source: S,
+    mut rank: R,
+    keywords: &HulthDocumentKeywords,
+    top_k: Option<usize>,
+    normalizer: N,
+    jobs: usize,
+    keep: bool,
+    mut collect: C,
+) -> io::Result<Vec<DocumentResult>>
+where
+    S: Iterator<Item = io::Result<(String, Arc<HulthDocument>, E)>> + Send,
+    E: Send,
+    R: FnMut(&HulthDocument) -> io::Result<Vec<(String, f64)>>,
+    N: Fn() -> Normalizer + Sync,
+    C: FnMut(&DocumentResult, E) -> io::Result<()> + Send,
+{
+    let jobs = jobs.max(1);
+    let capacity = jobs * QUEUE_PER_JOB;
+    thread::scope(|scope| {
+        let (doc_tx, doc_rx) = bounded(capacity);
+        let (ranked_tx, ranked_rx) =
+            bounded::<(usize, String, Arc<HulthDocument>, E, Vec<(String, f64)>)>(capacity);
+        let (scored_tx, scored_rx) = bounded(capacity);
+
+        scope.spawn(move || {
+            for (seq, item) in source.enumerate() {
+                let failed = item.is_err();
+                if doc_tx.send((seq, item)).is_err() || failed {
+                    break;
+                }
+            }
+        });
+
+        let normalizer = &normalizer;
+        for _ in 0..jobs {
+            let ranked_rx = ranked_rx.clone();
+            let scored_tx = scored_tx.clone();
+            scope.spawn(move || {
+                let mut interner = Interner::new();
+                let normalizer = normalizer();
+                for (seq, name, doc, extra, ranked) in ranked_rx {
+                    let measure = score_document(
+                        &name,
+                        &doc,
+                        ranked,
+                        top_k,
+                        keywords,
+                        &normalizer,
+                        &mut interner,
+                    );
+                    if scored_tx.send((seq, measure, extra)).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(ranked_rx);
+        drop(scored_tx);
+
+        let collector = scope.spawn(move || -> io::Result<Vec<DocumentResult>> {
+            let mut measures = vec![];
+            // measures arriving ahead of an earlier one, at most those in flight
+            let mut pending = BTreeMap::new();
+            let mut next = 0;
+            for (seq, measure, extra) in scored_rx {
+                pending.insert(seq, (measure?, extra));
+                while let Some((measure, extra)) = pending.remove(&next) {
+                    collect(&measure, extra)?;
+                    if keep {
+                        measures.push(measure);
+                    }
+                    next += 1;
+                }
+            }
+            Ok(measures)
+        });
+
+        for (seq, item) in doc_rx {
+            let (name, doc, extra) = item?;
+            let ranked = rank(&doc)?;
+            if ranked_tx.send((seq, name, doc, extra, ranked)).is_err() {
+                // the collector stopped early, its error is returned below
+                break;
+            }
+        }
+        drop(ranked_tx);
+
+        collector
+            .join()
+            .map_err(|_| io::Error::other("collector thread panicked"))?
+    })
+}
@@ -0,0 +1,181 @@
+//! `--dry-run`, checks the inputs and outputs of a run and
+//! estimates its size without fitting or ranking anything
+use crate::commands::eval::{DatasetSpec, EvalArgs};
+use crate::corpus::{self, Reading};
+use crate::scoring::Extractor;
+use crate::HulthDocumentKeywords;
+
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+/// what the documents of one dataset amount to
+#[derive(Debug, Default)]
+struct Plan {
+    files: usize,
+    bytes: u64,
+    /// documents evaluated, all files or those of --shard
+    evaluated: usize,
+    /// evaluated documents with an entry in the references
+    referenced: usize,
+}
+
+/// checks that every dataset and reference file of specs is readable and every
+/// evaluated document has references, that the rerankers of extractors and the
+/// baseline exist and that the output files can be created, then prints the size
+/// of the run. runs is the number of evaluations of each dataset.
+/// fails listing every problem found
+pub fn check(
+    args: &EvalArgs,
+    specs: &[DatasetSpec],
+    reading: &Reading,
+    extractors: &[Extractor],
+    runs: usize,
+) -> io::Result<()> {
+    let mut problems = vec![];
+    let mut plans = vec![];
+    for spec in specs {
+        match plan(args, spec, reading, &mut problems) {
+            Ok(plan) => plans.push((spec, plan)),
+            Err(e) => problems.push(format!("{}: {}", spec.dataset.display(), e)),
+        }
+    }
+
+    for extractor in extractors {
+        let scoring = args.scoring.for_extractor(*extractor)?;
+        if extractor.is_reranked() {
+            match &scoring.reranker {
+                Some(path) if !path.is_file() => {
+                    problems.push(format!("{}: reranker not found", path.display()))
+                }
+                None => problems.push(format!("the {} extractor needs --reranker", extractor)),
+                _ => {}
+            }
+        }
+        if *extractor == Extractor::EmbedRank {
+            match &scoring.vectors {
+                Some(path) if !path.exists() => {
+                    problems.push(format!("{}: vectors not found", path.display()))
+                }
+                None => problems.push("the embed-rank extractor needs --vectors".into()),
+                _ => {}
+            }
+        }
+    }
+    if let Some(path) = &args.baseline {
+        if !path.is_file() {
+            problems.push(format!("{}: baseline not found", path.display()));
+        }
+    }
+    let outputs = [
+        &args.save_model,
+        &args.results,
+        &args.summary_out,
+        &args.manifest,
+        &args.report,
+    ];
+    #[cfg(feature = "parquet")]
+    let outputs = [&outputs[..], &[&args.candidates]].concat();
+    for path in outputs.iter().copied().flatten() {
+        if !writable(path) {
+            problems.push(format!("{}: directory does not exist", path.display()));
+        }
+    }
+
+    print(io::stdout().lock(), &plans, runs)?;
+    if problems.is_empty() {
+        return Ok(());
+    }
+    for problem in &problems {
+        error!("{}", problem);
+    }
+    Err(io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("dry run found {} problems", problems.len()),
+    ))
+}
+
+/// counts the files of the dataset of spec and matches them with its references.
+/// documents that would fail the run are added to problems
+fn plan(
+    args: &EvalArgs,
+    spec: &DatasetSpec,
+    reading: &Reading,
+    problems: &mut Vec<String>,
+) -> io::Result<Plan> {
+    let keywords = fs::File::open(&spec.references).and_then(|file| {
+        serde_json::from_reader::<_, HulthDocumentKeywords>(io::BufReader::new(file))
+            .map_err(io::Error::from)
+    });
+    let keywords = match keywords {
+        Ok(keywords) => Some(keywords),
+        Err(e) => {
+            problems.push(format!("{}: {}", spec.references.display(), e));
+            None
+        }
+    };
+    let mut plan = Plan::default();
+    let mut unreferenced = HashSet::new();
+    corpus::for_each_file(&spec.dataset, reading, |path| {
+        plan.files += 1;
+        plan.bytes += fs::metadata(path)?.len();
+        let name = reading.document_id(path)?;
+        if args.shard.is_some_and(|s| !s.contains(&name)) {
+            return Ok(());
+        }
+        plan.evaluated += 1;
+        match &keywords {
+            Some(keywords) if keywords.contains_key(&name) => plan.referenced += 1,
+            Some(_) => {
+                unreferenced.insert(name);
+            }
+            None => {}
+        }
+        Ok(())
+    })?;
+    if !unreferenced.is_empty() {
+        problems.push(format!(
+            "{}: {} documents have no references, e.g. {}",
+            spec.references.display(),
+            unreferenced.len(),
+            unreferenced
+                .iter()
+                .min()
+                .map(String::as_str)
+                .unwrap_or_default(),
+        ));
+    }
+    Ok(plan)
+}
+
+/// whether the directory path would be created in exists
+fn writable(path: &Path) -> bool {
+    match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.is_dir(),
+        _ => true,
+    }
+}
+
+fn print<W: Write>(mut w: W, plans: &[(&DatasetSpec, Plan)], runs: usize) -> io::Result<()> {
+    let mut total = 0;
+    for (spec, plan) in plans {
+        writeln!(w, "{:<28} {}", "dataset", spec.name)?;
+        writeln!(w, "{:<28} {}", "  documents", plan.files)?;
+        writeln!(
+            w,
+            "{:<28} {:.1}",
+            "  size (MiB)",
+            plan.bytes as f64 / 1048576.0
+        )?;
+        writeln!(w, "{:<28} {}", "  documents evaluated", plan.evaluated)?;
+        writeln!(
+            w,
+            "{:<28} {}",
+            "  documents with references", plan.referenced
+        )?;
+        total += plan.evaluated * runs;
+    }
+    writeln!(w, "{:<28} {}", "runs per dataset", runs)?;
+    writeln!(w, "{:<28} {}", "documents to rank", total)
+}
@@ -0,0 +1,369 @@
+//! optional preprocessing stages, applied to the documents before fitting
+//! and ranking and to the references wherever they affect matching
+use crate::grammar::{Pattern, DEFAULT_PATTERN};
+use crate::language::Lang;
+use crate::lemmatize::{Lemmas, Lemmatizer};
+use crate::matching::{ReferenceTokenization, Split};
+use crate::phrases::{self, Overlap};
+use crate::synonyms::{SynonymFile, Synonyms};
+use crate::{HulthDocument, HulthToken, Sentence};
+
+use clap::Args;
+use rust_stemmers::Stemmer;
+
+use std::fmt;
+use std::io;
+use std::sync::Arc;
+
+#[derive(Debug, Clone, PartialEq, Eq, Args, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Preprocessing {
+    /// drops the stopwords of --lang and tokens without letters or digits
+    #[arg(long)]
+    pub stopwords: bool,
+
+    /// keeps only nouns and adjectives, tokens without a tag are kept
+    #[arg(long)]
+    pub pos_filter: bool,
+
+    /// reduces terms to their Snowball stem in --lang
+    #[arg(long)]
+    pub stem: bool,
+
+    /// lowercases terms so case variants become one candidate
+    #[arg(long)]
+    pub dedup: bool,
+
+    /// adds n-grams of adjacent kept tokens as candidates, up to --max-phrase-len
+    #[arg(long)]
+    pub ngrams: bool,
+
+    /// adds the phrases matching a POS pattern as candidates, see --pattern
+    #[arg(long)]
+    pub chunks: bool,
+
+    /// POS pattern of the chunking stage such as `(JJ|NN)* NN`, may be repeated
+    #[arg(long = "pattern", default_value = DEFAULT_PATTERN)]
+    pub patterns: Vec<Pattern>,
+
+    /// the most tokens of an n-gram or chunk candidate
+    #[arg(long, default_value_t = 3)]
+    pub max_phrase_len: usize,
+
+    /// which of two overlapping phrase candidates is kept
+    #[arg(long, value_enum, default_value_t = Overlap::KeepBoth)]
+    pub overlap: Overlap,
+
+    /// the language of the documents and references, picks the stopwords and the stemmer
+    #[arg(long, value_enum, default_value_t = Lang::En)]
+    pub lang: Lang,
+
+    /// replaces words by their lemmas before the other stages: `dataset`,
+    /// `dict:<form<TAB>lemma file>`, `command:<command line>` or `http://<host>/<path>`
+    #[arg(long)]
+    pub lemmatize: Option<Lemmatizer>,
+}
+
+impl Default for Preprocessing {
+    fn default() -> Self {
+        Preprocessing {
+            stopwords: false,
+            pos_filter: false,
+            stem: false,
+            dedup: false,
+            ngrams: false,
+            chunks: false,
+            patterns: vec![DEFAULT_PATTERN.parse().expect("default pattern")],
+            max_phrase_len: 3,
+            overlap: Overlap::KeepBoth,
+            lang: Lang::En,
+            lemmatize: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    Stopwords,
+    PosFilter,
+    Stem,
+    Dedup,
+    Ngrams,
+    Chunks,
+}
+
+pub const STAGES: [Stage; 6] = [
+    Stage::Stopwords,
+    Stage::PosFilter,
+    Stage::Stem,
+    Stage::Dedup,
+    Stage::Ngrams,
+    Stage::Chunks,
+];
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Stage::Stopwords => "stopwords",
+            Stage::PosFilter => "pos-filter",
+            Stage::Stem => "stem",
+            Stage::Dedup => "dedup",
+            Stage::Ngrams => "ngrams",
+            Stage::Chunks => "chunks",
+        };
+        f.write_str(name)
+    }
+}
+
+impl Preprocessing {
+    pub fn is_enabled(&self, stage: Stage) -> bool {
+        match stage {
+            Stage::Stopwords => self.stopwords,
+            Stage::PosFilter => self.pos_filter,
+            Stage::Stem => self.stem,
+            Stage::Dedup => self.dedup,
+            Stage::Ngrams => self.ngrams,
+            Stage::Chunks => self.chunks,
+        }
+    }
+
+    /// a copy with stage switched on or off
+    pub fn toggled(&self, stage: Stage) -> Self {
+        let mut toggled = self.clone();
+        let flag = match stage {
+            Stage::Stopwords => &mut toggled.stopwords,
+            Stage::PosFilter => &mut toggled.pos_filter,
+            Stage::Stem => &mut toggled.stem,
+            Stage::Dedup => &mut toggled.dedup,
+            Stage::Ngrams => &mut toggled.ngrams,
+            Stage::Chunks => &mut toggled.chunks,
+        };
+        *flag = !*flag;
+        toggled
+    }
+
+    /// the normalizer of a run, built once and cloned for every thread.
+    /// fails if the lemma dictionary can't be read
+    pub fn normalizer(&self) -> io::Result<Normalizer> {
+        Ok(Normalizer {
+            preprocessing: self.clone(),
+            stemmer: Stemmer::create(self.lang.algorithm()),
+            lemmas: Arc::new(Lemmas::new(self.lemmatize.as_ref())?),
+            references: ReferenceTokenization::default(),
+            synonyms: None,
+        })
+    }
+
+    fn apply_sentence(&self, tokens: &[HulthToken], normalizer: &Normalizer) -> Vec<HulthToken> {
+        let mut kept: Vec<HulthToken> = vec![];
+        // indices of the kept unigrams directly preceding the current token
+        let mut run: Vec<usize> = vec![];
+        for token in tokens {
+            let term = if self.pos_filter && !is_content_pos(&token.pos) {
+                None
+            } else {
+                normalizer.token_term(token)
+            };
+            let term = match term {
+                Some(term) => term,
+                None => {
+                    run.clear();
+                    continue;
+                }
+            };
+            let token = HulthToken {
+                word: term.into(),
+                ..token.clone()
+            };
+            kept.push(token);
+            run.push(kept.len() - 1);
+            if self.ngrams {
+                for n in 2..=self.max_phrase_len.min(run.len()) {
+                    let ngram = phrases::phrase(
+                        run[run.len() - n..]
+                            .iter()
+                            .map(|i| (&kept[*i], &*kept[*i].word)),
+                    );
+                    kept.push(ngram);
+                }
+            }
+        }
+        if self.chunks {
+            kept.extend(self.chunk(tokens, normalizer));
+        }
+        if self.overlap == Overlap::Longest {
+            return phrases::keep_longest(kept);
+        }
+        kept
+    }
+
+    /// the phrases of tokens matching a pattern, at every start the longest match.
+    /// phrases with a word dropped by another stage are skipped
+    fn chunk(&self, tokens: &[HulthToken], normalizer: &Normalizer) -> Vec<HulthToken> {
+        let tags = tokens.iter().map(|t| &*t.pos).collect::<Vec<_>>();
+        let mut chunks = vec![];
+        for start in 0..tokens.len() {
+            let end = self
+                .patterns
+                .iter()
+                .filter_map(|p| p.longest_match(&tags, start, self.max_phrase_len))
+                .max();
+            let span = match end {
+                Some(end) if end - start > 1 => &tokens[start..end],
+                _ => continue,
+            };
+            let words = span
+                .iter()
+                .map(|t| normalizer.token_term(t))
+                .collect::<Option<Vec<_>>>();
+            if let Some(words) = words {
+                let parts = span.iter().zip(words.iter().map(String::as_str));
+                chunks.push(phrases::phrase(parts));
+            }
+        }
+        chunks
+    }
+
+    /// resolves overlapping phrases by their scores if the policy says so,
+    /// doc is the preprocessed document that was scored
+    pub fn resolve(&self, doc: &HulthDocument, scores: Vec<(String, f64)>) -> Vec<(String, f64)> {
+        if self.overlap == Overlap::Highest {
+            return phrases::keep_highest(doc, scores);
+        }
+        scores
+    }
+}
+
+/// applies the enabled stages to documents and normalizes single words the way they do.
+/// clones share the lemmas
+pub struct Normalizer {
+    preprocessing: Preprocessing,
+    stemmer: Stemmer,
+    lemmas: Arc<Lemmas>,
+    references: ReferenceTokenization,
+    synonyms: Option<Synonyms>,
+}
+
+impl Clone for Normalizer {
+    fn clone(&self) -> Self {
+        Normalizer {
+            preprocessing: self.preprocessing.clone(),
+            stemmer: Stemmer::create(self.preprocessing.lang.algorithm()),
+            lemmas: self.lemmas.clone(),
+            references: self.references,
+            synonyms: self.synonyms.clone(),
+        }
+    }
+}
+
+impl Normalizer {
+    /// the document with every enabled stage applied
+    pub fn apply(&self, doc: &HulthDocument) -> HulthDocument {
+        let p = &self.preprocessing;
+        if *p == Preprocessing::default() {
+            return doc.clone();
+        }
+        self.lemmas.prefetch(
+            doc.sentences
+                .iter()
+                .flat_map(|s| &s.tokens)
+                .map(|t| &*t.word),
+        );
+        let sentences = doc
+            .sentences
+            .iter()
+            .map(|s| Sentence {
+                tokens: p.apply_sentence(&s.tokens, self),
+            })
+            .collect();
+        HulthDocument { sentences }
+    }
+
+    /// splits reference keyphrases as references says instead of on spaces
+    pub fn with_references(self, references: ReferenceTokenization) -> Self {
+        Normalizer { references, ..self }
+    }
+
+    /// matches reference terms by the classes of synonyms, normalized like them
+    pub fn with_synonyms(self, synonyms: Option<&SynonymFile>) -> Self {
+        let synonyms = synonyms.map(|file| file.normalize(&self));
+        Normalizer { synonyms, ..self }
+    }
+
+    pub fn synonyms(&self) -> Option<&Synonyms> {
+        self.synonyms.as_ref()
+    }
+
+    /// the Snowball stem of the lowercase word in --lang, whether or not --stem is on
+    pub fn stem(&self, word: &str) -> String {
+        self.stemmer.stem(&word.to_lowercase()).into_owned()
+    }
+
+    /// the normalized words of a reference keyphrase
+    pub fn reference_words(&self, phrase: &str) -> Vec<String> {
+        let words = self.references.words(phrase);
+        self.lemmas.prefetch(words.iter().map(|w| &**w));
+        words.iter().filter_map(|word| self.term(word)).collect()
+    }
+
+    /// the normalized terms of a reference keyphrase, None for words a stage drops.
+    /// with --reference-split phrase the kept words are joined into a single term
+    pub fn reference_terms(&self, phrase: &str) -> Vec<Option<String>> {
+        if self.references.split == Split::Phrase {
+            let words = self.reference_words(phrase);
+            if words.is_empty() {
+                return vec![];
+            }
+            return vec![Some(words.join(" "))];
+        }
+        let words = self.references.words(phrase);
+        self.lemmas.prefetch(words.iter().map(|w| &**w));
+        words.iter().map(|word| self.term(word)).collect()
+    }
+
+    pub fn ngrams(&self) -> bool {
+        self.preprocessing.ngrams
+    }
+
+    pub fn chunks(&self) -> bool {
+        self.preprocessing.chunks
+    }
+
+    pub fn max_phrase_len(&self) -> usize {
+        self.preprocessing.max_phrase_len
+    }
+
+    /// the normalized form of a document token, lemmatized by its own lemma
+    /// with `--lemmatize dataset`
+    pub fn token_term(&self, token: &HulthToken) -> Option<String> {
+        self.normalize(&self.lemmas.of_token(token))
+    }
+
+    /// the normalized form of a word, None if a stage drops it
+    pub fn term(&self, word: &str) -> Option<String> {
+        self.normalize(&self.lemmas.of(word))
+    }
+
+    fn normalize(&self, word: &str) -> Option<String> {
+        let p = &self.preprocessing;
+        if !p.stopwords && !p.stem && !p.dedup {
+            return Some(word.into());
+        }
+        let lower = word.to_lowercase();
+        if p.stopwords && p.lang.is_stopword(&lower) {
+            return None;
+        }
+        if p.stem {
+            return Some(self.stemmer.stem(&lower).into_owned());
+        }
+        if p.dedup {
+            return Some(lower);
+        }
+        Some(word.into())
+    }
+}
+
+/// nouns and adjectives in the Penn Treebank tag set
+fn is_content_pos(pos: &str) -> bool {
+    pos.is_empty() || pos.starts_with("NN") || pos.starts_with("JJ")
+}
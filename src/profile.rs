@@ -0,0 +1,40 @@
+//! `eval --profile` of the `profile` feature, sampling the stacks of the run
+//! with pprof and writing them as a flamegraph, where loading, fitting and
+//! ranking show up under their functions
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::path::{Path, PathBuf};
+
+/// samples per second, not a multiple of common timer rates
+const FREQUENCY: i32 = 997;
+
+/// the sampling of a run until `finish`
+pub struct Profiler {
+    guard: pprof::ProfilerGuard<'static>,
+    path: PathBuf,
+}
+
+impl Profiler {
+    /// starts sampling, the flamegraph is written to path
+    pub fn start(path: &Path) -> io::Result<Self> {
+        let guard = pprof::ProfilerGuardBuilder::default()
+            .frequency(FREQUENCY)
+            .blocklist(&["libc", "libgcc", "pthread", "vdso"])
+            .build()
+            .map_err(io::Error::other)?;
+        debug!(frequency = FREQUENCY, "started profiling");
+        Ok(Profiler {
+            guard,
+            path: path.into(),
+        })
+    }
+
+    /// stops sampling and writes the flamegraph
+    pub fn finish(self) -> io::Result<()> {
+        let report = self.guard.report().build().map_err(io::Error::other)?;
+        let writer = BufWriter::new(File::create(&self.path)?);
+        report.flamegraph(writer).map_err(io::Error::other)?;
+        info!(path = %self.path.display(), samples = report.data.values().sum::<isize>(), "wrote flamegraph");
+        Ok(())
+    }
+}
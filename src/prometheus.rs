@@ -0,0 +1,154 @@
+//! Prometheus metrics of the `serve` subcommand, rendered in the text exposition format
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+/// upper bounds of the latency buckets, in seconds
+const BUCKETS: [f64; 10] = [0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0];
+
+#[derive(Debug, Default)]
+struct Histogram {
+    counts: [u64; BUCKETS.len()],
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, value: f64) {
+        for (count, bound) in self.counts.iter_mut().zip(BUCKETS) {
+            if value <= bound {
+                *count += 1;
+            }
+        }
+        self.sum += value;
+        self.count += 1;
+    }
+}
+
+#[derive(Debug)]
+pub struct Metrics {
+    /// requests by route and status code
+    requests: BTreeMap<(&'static str, u16), u64>,
+    latency: BTreeMap<&'static str, Histogram>,
+    ranked: u64,
+    keywords: u64,
+    documents: usize,
+    terms: usize,
+    fit_seconds: f64,
+}
+
+impl Metrics {
+    /// metrics of a server whose model has documents and terms
+    /// and was fitted in fit_seconds
+    pub fn new(documents: usize, terms: usize, fit_seconds: f64) -> Self {
+        Metrics {
+            requests: BTreeMap::new(),
+            latency: BTreeMap::new(),
+            ranked: 0,
+            keywords: 0,
+            documents,
+            terms,
+            fit_seconds,
+        }
+    }
+
+    /// records a request of route, a fixed label so unknown paths don't add series
+    pub fn observe(&mut self, route: &'static str, status: u16, seconds: f64) {
+        *self.requests.entry((route, status)).or_insert(0) += 1;
+        self.latency.entry(route).or_default().observe(seconds);
+    }
+
+    /// records a ranked document and the number of keywords returned for it
+    pub fn ranked(&mut self, keywords: usize) {
+        self.ranked += 1;
+        self.keywords += keywords as u64;
+    }
+
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        // writing to a String does not fail
+        let _ = self.write(&mut out);
+        out
+    }
+
+    fn write(&self, out: &mut String) -> std::fmt::Result {
+        writeln!(
+            out,
+            "# HELP hulth_requests_total HTTP requests by route and status."
+        )?;
+        writeln!(out, "# TYPE hulth_requests_total counter")?;
+        for ((route, status), count) in &self.requests {
+            writeln!(
+                out,
+                "hulth_requests_total{{route=\"{}\",status=\"{}\"}} {}",
+                route, status, count
+            )?;
+        }
+
+        writeln!(
+            out,
+            "# HELP hulth_request_duration_seconds Latency of HTTP requests by route."
+        )?;
+        writeln!(out, "# TYPE hulth_request_duration_seconds histogram")?;
+        for (route, histogram) in &self.latency {
+            for (count, bound) in histogram.counts.iter().zip(BUCKETS) {
+                writeln!(
+                    out,
+                    "hulth_request_duration_seconds_bucket{{route=\"{}\",le=\"{}\"}} {}",
+                    route, bound, count
+                )?;
+            }
+            writeln!(
+                out,
+                "hulth_request_duration_seconds_bucket{{route=\"{}\",le=\"+Inf\"}} {}",
+                route, histogram.count
+            )?;
+            writeln!(
+                out,
+                "hulth_request_duration_seconds_sum{{route=\"{}\"}} {}",
+                route, histogram.sum
+            )?;
+            writeln!(
+                out,
+                "hulth_request_duration_seconds_count{{route=\"{}\"}} {}",
+                route, histogram.count
+            )?;
+        }
+
+        let counters = [
+            (
+                "hulth_ranked_documents_total",
+                "Documents ranked.",
+                self.ranked,
+            ),
+            ("hulth_keywords_total", "Keywords returned.", self.keywords),
+        ];
+        for (name, help, value) in counters {
+            writeln!(out, "# HELP {} {}", name, help)?;
+            writeln!(out, "# TYPE {} counter", name)?;
+            writeln!(out, "{} {}", name, value)?;
+        }
+        let gauges = [
+            (
+                "hulth_model_documents",
+                "Documents of the fitted corpus.",
+                self.documents as f64,
+            ),
+            (
+                "hulth_model_terms",
+                "Distinct terms of the fitted corpus.",
+                self.terms as f64,
+            ),
+            (
+                "hulth_model_fit_seconds",
+                "Time taken to fit the corpus.",
+                self.fit_seconds,
+            ),
+        ];
+        for (name, help, value) in gauges {
+            writeln!(out, "# HELP {} {}", name, help)?;
+            writeln!(out, "# TYPE {} gauge", name)?;
+            writeln!(out, "{} {}", name, value)?;
+        }
+        Ok(())
+    }
+}
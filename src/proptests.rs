@@ -0,0 +1,168 @@
+//! property tests over generated documents, references and rankings
+use crate::commands::convert;
+use crate::corpus::Reading;
+use crate::intern::Interner;
+use crate::preprocess::Preprocessing;
+use crate::scoring::Collapse;
+use crate::{corpus, metrics, rerank, score_document};
+use crate::{HulthDocument, HulthDocumentKeywords, HulthToken, Sentence};
+
+use proptest::prelude::*;
+
+use std::collections::BTreeMap;
+use std::fs;
+
+/// a small vocabulary so rankings and references share terms,
+/// with case variants for dedup
+const WORDS: &[&str] = &[
+    "keyword",
+    "Keyword",
+    "extraction",
+    "graph",
+    "Graph",
+    "ranking",
+    "rankings",
+    "the",
+    "of",
+    "tf-idf",
+    "(svm)",
+    ".",
+];
+
+const TAGS: &[&str] = &["NN", "NNS", "NNP", "JJ", "VB", "DT", "IN", "."];
+
+fn word() -> impl Strategy<Value = String> {
+    prop_oneof![
+        3 => prop::sample::select(WORDS).prop_map(String::from),
+        1 => "[A-Za-zäöüÄÖÜ]{1,6}",
+    ]
+}
+
+fn token() -> impl Strategy<Value = HulthToken> {
+    (word(), prop::sample::select(TAGS), 0..1000i64).prop_map(|(word, pos, begin)| HulthToken {
+        lemma: word.to_lowercase().into(),
+        offset_begin: begin,
+        offset_end: begin + word.len() as i64,
+        word: word.into(),
+        pos: pos.into(),
+    })
+}
+
+fn document() -> impl Strategy<Value = HulthDocument> {
+    let sentence = prop::collection::vec(token(), 0..8).prop_map(|tokens| Sentence { tokens });
+    prop::collection::vec(sentence, 0..5).prop_map(|sentences| HulthDocument { sentences })
+}
+
+/// the keyword groups of one document
+fn references() -> impl Strategy<Value = Vec<Vec<String>>> {
+    let phrase = prop::collection::vec(word(), 1..3).prop_map(|words| words.join(" "));
+    prop::collection::vec(prop::collection::vec(phrase, 0..4), 0..3)
+}
+
+fn ranking() -> impl Strategy<Value = Vec<(String, f64)>> {
+    prop::collection::vec((word(), -10f64..10f64), 0..10)
+}
+
+fn json(doc: &HulthDocument) -> serde_json::Value {
+    serde_json::to_value(doc).unwrap()
+}
+
+fn assert_unit(name: &str, value: f64) {
+    assert!((0f64..=1f64).contains(&value), "{} is {}", name, value);
+}
+
+proptest! {
+    #[test]
+    fn measures_are_between_0_and_1(
+        doc in document(),
+        keyphrases in references(),
+        ranked in ranking(),
+    ) {
+        let keywords: HulthDocumentKeywords = [("doc".to_string(), keyphrases)].into();
+        let normalizer = Preprocessing::default().normalizer().unwrap();
+        let measure = score_document(
+            "doc",
+            &doc,
+            ranked,
+            None,
+            &keywords,
+            &normalizer,
+            &mut Interner::new(),
+        )
+        .unwrap();
+        assert_unit("precision", measure.metrics.precision);
+        assert_unit("recall", measure.metrics.recall);
+        assert_unit("f1", measure.metrics.f1);
+        assert_unit("average precision", measure.metrics.average_precision);
+        assert_unit("ndcg", measure.metrics.ndcg);
+        assert_unit("coverage", measure.metrics.coverage);
+        assert_unit("rouge-1 recall", measure.rouge.one.recall());
+        assert_unit("rouge-2 recall", measure.rouge.two.recall());
+        assert_unit("adaptive precision", measure.adaptive.precision);
+        assert_unit("adaptive recall", measure.adaptive.recall);
+        for group in &measure.groups {
+            assert_unit("group precision", group.precision);
+            assert_unit("group recall", group.recall);
+            assert_unit("group f1", group.f1);
+        }
+    }
+
+    #[test]
+    fn metrics_of_any_hits_are_between_0_and_1(
+        hits in prop::collection::vec(any::<bool>(), 0..20),
+        missed in 0..5usize,
+    ) {
+        // a reference has at least as many terms as were found
+        let reference = hits.iter().filter(|hit| **hit).count() + missed;
+        let precision = metrics::precision(&hits);
+        let recall = metrics::recall(&hits, reference);
+        assert_unit("precision", precision);
+        assert_unit("recall", recall);
+        assert_unit("f1", metrics::f1(precision, recall));
+        assert_unit("average precision", metrics::average_precision(&hits, reference));
+        assert_unit("ndcg", metrics::ndcg(&hits, reference));
+    }
+
+    #[test]
+    fn dedup_is_idempotent(doc in document()) {
+        let dedup = Preprocessing {
+            dedup: true,
+            ..Preprocessing::default()
+        }
+        .normalizer()
+        .unwrap();
+        let once = dedup.apply(&doc);
+        prop_assert_eq!(json(&dedup.apply(&once)), json(&once));
+    }
+
+    #[test]
+    fn collapse_is_idempotent(ranked in ranking()) {
+        let once = rerank::collapse(ranked, Collapse::Highest);
+        prop_assert_eq!(rerank::collapse(once.clone(), Collapse::Highest), once);
+    }
+
+    #[test]
+    fn convert_round_trips(docs in prop::collection::vec(document(), 0..5)) {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().join("dir");
+        let lines = tmp.path().join("lines.jsonl");
+        let back = tmp.path().join("back");
+        fs::create_dir(&dir).unwrap();
+        let mut written = BTreeMap::new();
+        for (i, doc) in docs.iter().enumerate() {
+            let name = (i + 1).to_string();
+            let path = dir.join(format!("{}.json", name));
+            fs::write(path, serde_json::to_vec(doc).unwrap()).unwrap();
+            written.insert(name, json(doc));
+        }
+        let reading = Reading::default();
+        prop_assert_eq!(convert::to_lines(&dir, &lines, &reading).unwrap(), docs.len());
+        prop_assert_eq!(convert::to_dir(&lines, &back, &reading).unwrap(), docs.len());
+        let read = corpus::stream_dir(&back, &reading)
+            .unwrap()
+            .map(|entry| entry.map(|(name, doc)| (name, json(&doc))))
+            .collect::<Result<BTreeMap<_, _>, _>>()
+            .unwrap();
+        prop_assert_eq!(read, written);
+    }
+}
@@ -0,0 +1,131 @@
+//! `--protocol` of eval, ablate and sweep, the preprocessing, matching and cutoff of
+//! well-known papers on the Hulth 2003 dataset, so the measures are comparable to their tables.
+//! the options of a protocol are applied like a config section over the config
+//! file, options given on the command line or in the environment still take precedence
+use crate::commands::eval::EvalArgs;
+use crate::config::Config;
+
+use clap::{ArgMatches, ValueEnum};
+
+use std::io;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Protocol {
+    /// Hulth 2003: stemmed n-grams of up to 3 words without stopwords,
+    /// whole keyphrases matched after stemming, every prediction evaluated
+    Hulth2003,
+    /// KEA, Witten et al. 1999: the candidates of Hulth 2003 evaluated at the top 5
+    Kea,
+    /// Liu et al. 2010: stemmed noun phrases of adjectives followed by nouns,
+    /// whole keyphrases matched after stemming at the top 10
+    Liu2010,
+}
+
+impl Protocol {
+    /// the options of the protocol as a config section of `eval`
+    fn options(self) -> toml::Table {
+        let (candidates, top_k) = match self {
+            Protocol::Hulth2003 => (NGRAMS, None),
+            Protocol::Kea => (NGRAMS, Some(5)),
+            Protocol::Liu2010 => (CHUNKS, Some(10)),
+        };
+        let mut options = format!("{}{}", MATCHING, candidates)
+            .parse::<toml::Table>()
+            .expect("protocol options are valid TOML");
+        if let Some(k) = top_k {
+            options.insert("top_k".into(), toml::Value::Integer(k));
+        }
+        options
+    }
+}
+
+/// whole stemmed keyphrases matched exactly, shared by every protocol
+const MATCHING: &str = r#"
+[reference_tokenization]
+split = "phrase"
+strip_parens = false
+
+[matching]
+match_mode = "exact"
+"#;
+
+const NGRAMS: &str = r#"
+[preprocessing]
+stopwords = true
+pos_filter = false
+stem = true
+dedup = true
+ngrams = true
+chunks = false
+max_phrase_len = 3
+"#;
+
+const CHUNKS: &str = r#"
+[preprocessing]
+stopwords = false
+pos_filter = false
+stem = true
+dedup = true
+ngrams = false
+chunks = true
+patterns = ["JJ* NN+"]
+max_phrase_len = 3
+"#;
+
+/// args with the options of their --protocol, if any
+pub fn apply(args: EvalArgs, matches: &ArgMatches) -> io::Result<EvalArgs> {
+    let protocol = match args.protocol {
+        Some(protocol) => protocol,
+        None => return Ok(args),
+    };
+    let name = protocol
+        .to_possible_value()
+        .map(|v| v.get_name().to_string())
+        .unwrap_or_default();
+    debug!(protocol = %name, "applying evaluation protocol");
+    Config::preset(&format!("--protocol {}", name)).apply(args, &protocol.options(), matches)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use clap::{Args, FromArgMatches};
+
+    fn eval_args(argv: &[&str]) -> EvalArgs {
+        let matches = EvalArgs::augment_args(clap::Command::new("eval"))
+            .try_get_matches_from(argv)
+            .unwrap();
+        let args = EvalArgs::from_arg_matches(&matches).unwrap();
+        apply(args, &matches).unwrap()
+    }
+
+    #[test]
+    fn presets_set_their_cutoff_and_candidates() {
+        for (name, top_k, ngrams) in [
+            ("hulth2003", None, true),
+            ("kea", Some(5), true),
+            ("liu2010", Some(10), false),
+        ] {
+            let args = eval_args(&["eval", "--protocol", name]);
+            assert_eq!(args.top_k, top_k, "{}", name);
+            let preprocessing = &args.preprocessing;
+            assert_eq!(preprocessing.ngrams, ngrams, "{}", name);
+            assert_eq!(preprocessing.chunks, !ngrams, "{}", name);
+            assert!(preprocessing.stem, "{}", name);
+            assert_eq!(preprocessing.max_phrase_len, 3, "{}", name);
+            if !ngrams {
+                let patterns = preprocessing.patterns.iter().map(ToString::to_string);
+                assert_eq!(patterns.collect::<Vec<_>>(), ["JJ* NN+"]);
+            }
+        }
+    }
+
+    #[test]
+    fn command_line_overrides_the_preset() {
+        let args = eval_args(&["eval", "--protocol", "kea", "--top-k", "15"]);
+        assert_eq!(args.top_k, Some(15));
+        assert!(args.preprocessing.ngrams);
+    }
+}
@@ -0,0 +1,82 @@
+//! Python module `hulth_eval`, built with `maturin develop --release`
+//! for driving evaluations from notebooks
+use crate::commands::eval::{first_extractor, run_dataset, EvalArgs};
+use crate::corpus::Reading;
+use crate::report::Summary;
+
+use clap::{Args, FromArgMatches};
+use pyo3::exceptions::{PyIOError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+/// fits the documents of docs_dir, ranks their keywords and evaluates the best
+/// top_k of each against the references of refs_path, as `hulth-eval eval` does.
+/// returns a dict of the overall precision, recall and f1, the number of degenerate
+/// documents left out of them under `skipped`, and the same measures with
+/// the ranked keywords of every document under `documents`
+#[pyfunction]
+#[pyo3(signature = (docs_dir, refs_path, top_k=10))]
+fn evaluate<'py>(
+    py: Python<'py>,
+    docs_dir: &str,
+    refs_path: &str,
+    top_k: usize,
+) -> PyResult<Bound<'py, PyDict>> {
+    let top_k = top_k.to_string();
+    let matches = EvalArgs::augment_args(clap::Command::new("eval"))
+        .try_get_matches_from([
+            "eval",
+            "--dataset",
+            docs_dir,
+            "--references",
+            refs_path,
+            "--top-k",
+            &top_k,
+        ])
+        .map_err(|e| PyValueError::new_err(e.to_string()))?;
+    let args =
+        EvalArgs::from_arg_matches(&matches).map_err(|e| PyValueError::new_err(e.to_string()))?;
+
+    let reading = Reading::default();
+    let measures = py
+        .allow_threads(|| {
+            run_dataset(
+                &args,
+                &args.dataset,
+                &args.references,
+                &reading,
+                first_extractor(&args),
+                None,
+            )
+        })
+        .map_err(|e| PyIOError::new_err(e.to_string()))?;
+
+    let summary = Summary::from_measures(&measures);
+    let result = PyDict::new(py);
+    result.set_item("precision", summary.precision)?;
+    result.set_item("recall", summary.recall)?;
+    result.set_item("f1", summary.f1)?;
+    result.set_item("skipped", summary.skipped)?;
+    let mut documents = vec![];
+    for measure in &measures {
+        let document = PyDict::new(py);
+        document.set_item("document", &measure.document)?;
+        document.set_item("precision", measure.metrics.precision)?;
+        document.set_item("recall", measure.metrics.recall)?;
+        document.set_item("f1", measure.metrics.f1)?;
+        let keywords = measure
+            .ranking
+            .iter()
+            .map(|term| term.to_string())
+            .collect::<Vec<_>>();
+        document.set_item("keywords", keywords)?;
+        documents.push(document);
+    }
+    result.set_item("documents", documents)?;
+    Ok(result)
+}
+
+#[pymodule]
+fn hulth_eval(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_function(wrap_pyfunction!(evaluate, module)?)
+}
@@ -0,0 +1,632 @@
+use crate::features::quote;
+use crate::metrics::Running;
+use crate::results::DocumentResult;
+use crate::significance::{self, PValues};
+
+use clap::ValueEnum;
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+const GREEN: &str = "\x1b[32m";
+const YELLOW: &str = "\x1b[33m";
+const RED: &str = "\x1b[31m";
+const RESET: &str = "\x1b[0m";
+
+/// printed above the measures of an interrupted run
+pub const PARTIAL: &str = "partial run, interrupted before all documents were evaluated";
+
+/// differences below this are shown as unchanged
+const EPSILON: f64 = 1e-4;
+
+/// aggregate measures of one run, also used as the baseline format
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Summary {
+    pub precision: f64,
+    pub recall: f64,
+    pub f1: f64,
+    /// degenerate documents left out of the means
+    #[serde(default)]
+    pub skipped: usize,
+    /// documents whose measure went into each mean, missing in older summaries
+    #[serde(default)]
+    pub valid: Counts,
+    /// NaN or infinite measures left out of each mean
+    #[serde(default)]
+    pub invalid: Counts,
+    /// the run was interrupted, the means are over the documents finished before
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub partial: bool,
+}
+
+fn is_false(b: &bool) -> bool {
+    !b
+}
+
+/// a number of documents per measure
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Counts {
+    pub precision: usize,
+    pub recall: usize,
+    pub f1: usize,
+}
+
+/// the means of a summary taken measure by measure, see `Summary::from_measures`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Totals {
+    documents: usize,
+    skipped: usize,
+    precision: Running,
+    recall: Running,
+    f1: Running,
+}
+
+impl Totals {
+    /// degenerate documents are only counted
+    pub fn add(&mut self, measure: &DocumentResult) {
+        self.documents += 1;
+        if measure.degenerate.is_some() {
+            self.skipped += 1;
+            return;
+        }
+        self.precision.push(measure.metrics.precision);
+        self.recall.push(measure.metrics.recall);
+        self.f1.push(measure.metrics.f1);
+    }
+
+    /// the documents added, degenerate ones included
+    pub fn documents(&self) -> usize {
+        self.documents
+    }
+
+    pub fn summary(&self) -> Summary {
+        let (precision, recall, f1) = (self.precision.mean(), self.recall.mean(), self.f1.mean());
+        for (name, mean) in [("precision", precision), ("recall", recall), ("f1", f1)] {
+            if mean.invalid > 0 {
+                warn!(
+                    measure = name,
+                    documents = mean.invalid,
+                    "left out measures that are not finite"
+                );
+            }
+        }
+        Summary {
+            precision: precision.value,
+            recall: recall.value,
+            f1: f1.value,
+            skipped: self.skipped,
+            valid: Counts {
+                precision: precision.valid,
+                recall: recall.valid,
+                f1: f1.valid,
+            },
+            invalid: Counts {
+                precision: precision.invalid,
+                recall: recall.invalid,
+                f1: f1.invalid,
+            },
+            partial: false,
+        }
+    }
+}
+
+/// a value is green at or above good, yellow at or above fair and red below
+struct Thresholds {
+    good: f64,
+    fair: f64,
+}
+
+const PRECISION: Thresholds = Thresholds {
+    good: 0.3,
+    fair: 0.15,
+};
+const RECALL: Thresholds = Thresholds {
+    good: 0.5,
+    fair: 0.25,
+};
+const F1: Thresholds = Thresholds {
+    good: 0.35,
+    fair: 0.2,
+};
+
+impl Summary {
+    /// the means over the measures, without those of degenerate documents
+    pub fn from_measures<'a, I>(measures: I) -> Self
+    where
+        I: IntoIterator<Item = &'a DocumentResult>,
+    {
+        let mut totals = Totals::default();
+        for measure in measures {
+            totals.add(measure);
+        }
+        totals.summary()
+    }
+
+    /// writes one line per metric, with the change relative to baseline if given
+    pub fn print<W: Write>(
+        &self,
+        mut w: W,
+        baseline: Option<&Summary>,
+        color: bool,
+    ) -> io::Result<()> {
+        if self.partial {
+            writeln!(w, "{}", paint(PARTIAL, YELLOW, color))?;
+        }
+        let rows = [
+            (
+                "precision",
+                self.precision,
+                baseline.map(|b| b.precision),
+                PRECISION,
+                self.valid.precision,
+                self.invalid.precision,
+            ),
+            (
+                "recall",
+                self.recall,
+                baseline.map(|b| b.recall),
+                RECALL,
+                self.valid.recall,
+                self.invalid.recall,
+            ),
+            (
+                "f1",
+                self.f1,
+                baseline.map(|b| b.f1),
+                F1,
+                self.valid.f1,
+                self.invalid.f1,
+            ),
+        ];
+        for (name, value, base, thresholds, valid, invalid) in rows.iter() {
+            if *valid == 0 {
+                match invalid {
+                    0 => writeln!(w, "{:<10}n/a, no documents", name)?,
+                    _ => writeln!(w, "{:<10}n/a, all {} values are not finite", name, invalid)?,
+                }
+                continue;
+            }
+            let value_color = if *value >= thresholds.good {
+                GREEN
+            } else if *value >= thresholds.fair {
+                YELLOW
+            } else {
+                RED
+            };
+            write!(
+                w,
+                "{:<10}{}",
+                name,
+                paint(&format!("{:.4}", value), value_color, color)
+            )?;
+            if let Some(base) = base {
+                let diff = value - base;
+                let change = if diff > EPSILON {
+                    paint(&format!("↑ {:+.4}", diff), GREEN, color)
+                } else if diff < -EPSILON {
+                    paint(&format!("↓ {:+.4}", diff), RED, color)
+                } else {
+                    "=".to_string()
+                };
+                write!(w, " {}", change)?;
+            }
+            if *invalid > 0 {
+                write!(
+                    w,
+                    " ({} of {} not finite, left out)",
+                    invalid,
+                    valid + invalid
+                )?;
+            }
+            writeln!(w)?;
+        }
+        if self.skipped > 0 {
+            writeln!(w, "{:<10}{} degenerate documents", "skipped", self.skipped)?;
+        }
+        Ok(())
+    }
+}
+
+/// one line of a multi-dataset report
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Row {
+    pub dataset: String,
+    pub extractor: String,
+    pub documents: usize,
+    #[serde(flatten)]
+    pub summary: Summary,
+    /// the standard deviations of the measures of a random extractor run with `--seeds`,
+    /// whose summary holds the mean measures
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spread: Option<Spread>,
+    /// against the extractor of --baseline-extractor on the same dataset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub p_values: Option<PValues>,
+}
+
+/// the sample standard deviations of the measures over the seeds of a run
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Spread {
+    pub seeds: usize,
+    pub precision: f64,
+    pub recall: f64,
+    pub f1: f64,
+}
+
+/// the mean of the summaries of the seeds with the spread of their measures,
+/// the counts of documents are those of the first seed
+pub fn across_seeds(summaries: &[Summary]) -> (Summary, Spread) {
+    let stats = |measure: fn(&Summary) -> f64| {
+        let values = summaries.iter().map(measure).collect::<Vec<_>>();
+        let n = values.len() as f64;
+        let mean = values.iter().sum::<f64>() / n;
+        let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1.0).max(1.0);
+        (mean, variance.sqrt())
+    };
+    let (precision, precision_sd) = stats(|s| s.precision);
+    let (recall, recall_sd) = stats(|s| s.recall);
+    let (f1, f1_sd) = stats(|s| s.f1);
+    let summary = Summary {
+        precision,
+        recall,
+        f1,
+        partial: summaries.iter().any(|s| s.partial),
+        ..summaries[0]
+    };
+    let spread = Spread {
+        seeds: summaries.len(),
+        precision: precision_sd,
+        recall: recall_sd,
+        f1: f1_sd,
+    };
+    (summary, spread)
+}
+
+/// a measure with its standard deviation over the seeds if there are several
+fn measure(value: f64, sd: Option<f64>) -> String {
+    match sd {
+        Some(sd) => format!("{:.4} ± {:.4}", value, sd),
+        None => format!("{:.4}", value),
+    }
+}
+
+pub fn print_table<W: Write>(mut w: W, rows: &[Row]) -> io::Result<()> {
+    let dataset_width = rows
+        .iter()
+        .map(|r| r.dataset.len())
+        .chain(Some("dataset".len()))
+        .max()
+        .unwrap_or_default();
+    let extractor_width = rows
+        .iter()
+        .map(|r| r.extractor.len())
+        .chain(Some("extractor".len()))
+        .max()
+        .unwrap_or_default();
+    let cells = rows
+        .iter()
+        .map(|row| {
+            let (s, sd, p) = (&row.summary, row.spread.as_ref(), row.p_values.as_ref());
+            let marker = |p: Option<f64>| p.map_or("", significance::marker);
+            [
+                (
+                    measure(s.precision, sd.map(|sd| sd.precision)),
+                    marker(p.map(|p| p.precision)),
+                ),
+                (
+                    measure(s.recall, sd.map(|sd| sd.recall)),
+                    marker(p.map(|p| p.recall)),
+                ),
+                (measure(s.f1, sd.map(|sd| sd.f1)), marker(p.map(|p| p.f1))),
+            ]
+        })
+        .collect::<Vec<_>>();
+    let measure_width = cells
+        .iter()
+        .flatten()
+        .map(|(m, _)| m.chars().count())
+        .chain(Some(9))
+        .max()
+        .unwrap_or_default();
+    // room for the significance markers after the measures
+    let marker_width = rows.iter().any(|r| r.p_values.is_some()) as usize;
+    writeln!(
+        w,
+        "{:<dw$}  {:<ew$}  {:>9}  {:>mw$}{:kw$}  {:>mw$}{:kw$}  {:>mw$}",
+        "dataset",
+        "extractor",
+        "documents",
+        "precision",
+        "",
+        "recall",
+        "",
+        "f1",
+        dw = dataset_width,
+        ew = extractor_width,
+        mw = measure_width,
+        kw = marker_width
+    )?;
+    for (row, [precision, recall, f1]) in rows.iter().zip(&cells) {
+        writeln!(
+            w,
+            "{:<dw$}  {:<ew$}  {:>9}  {:>mw$}{:kw$}  {:>mw$}{:kw$}  {:>mw$}{}",
+            row.dataset,
+            row.extractor,
+            row.documents,
+            precision.0,
+            precision.1,
+            recall.0,
+            recall.1,
+            f1.0,
+            f1.1,
+            dw = dataset_width,
+            ew = extractor_width,
+            mw = measure_width,
+            kw = marker_width
+        )?;
+    }
+    Ok(())
+}
+
+/// the format of the comparison matrix of --report
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportFormat {
+    /// a Markdown table, the best value of each column in bold
+    Markdown,
+    /// comma-separated values, ending in a `best` row naming
+    /// the best extractors of each column
+    Csv,
+    /// an HTML table, the best value of each column in bold
+    Html,
+}
+
+impl ReportFormat {
+    /// the format of the extension of path, Markdown unless it is `.csv` or `.html`
+    pub fn of(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("csv") => ReportFormat::Csv,
+            Some("html") | Some("htm") => ReportFormat::Html,
+            _ => ReportFormat::Markdown,
+        }
+    }
+}
+
+const METRICS: [&str; 3] = ["precision", "recall", "f1"];
+
+/// the rows of a report with an extractor per row and the metrics
+/// of every dataset as columns, in the order they were run
+struct Matrix<'a> {
+    extractors: Vec<&'a str>,
+    datasets: Vec<&'a str>,
+    /// the cells of every extractor, None for n/a
+    cells: Vec<Vec<Option<f64>>>,
+    /// the standard deviations over the seeds of the cells
+    spreads: Vec<Vec<Option<f64>>>,
+    /// the significance markers of the cells
+    markers: Vec<Vec<&'static str>>,
+}
+
+impl<'a> Matrix<'a> {
+    fn new(rows: &'a [Row]) -> Self {
+        let mut extractors = vec![];
+        let mut datasets = vec![];
+        for row in rows {
+            if !extractors.contains(&row.extractor.as_str()) {
+                extractors.push(row.extractor.as_str());
+            }
+            if !datasets.contains(&row.dataset.as_str()) {
+                datasets.push(row.dataset.as_str());
+            }
+        }
+        let mut cells = vec![vec![None; datasets.len() * METRICS.len()]; extractors.len()];
+        let mut spreads = vec![vec![None; datasets.len() * METRICS.len()]; extractors.len()];
+        let mut markers = vec![vec![""; datasets.len() * METRICS.len()]; extractors.len()];
+        for row in rows {
+            let e = extractors.iter().position(|e| *e == row.extractor);
+            let d = datasets.iter().position(|d| *d == row.dataset);
+            let (e, d) = (e.unwrap_or_default(), d.unwrap_or_default());
+            let s = &row.summary;
+            let values = [
+                (s.precision, s.valid.precision),
+                (s.recall, s.valid.recall),
+                (s.f1, s.valid.f1),
+            ];
+            for (m, (value, valid)) in values.iter().enumerate() {
+                if *valid > 0 && value.is_finite() {
+                    cells[e][d * METRICS.len() + m] = Some(*value);
+                }
+            }
+            if let Some(sd) = &row.spread {
+                for (m, sd) in [sd.precision, sd.recall, sd.f1].iter().enumerate() {
+                    spreads[e][d * METRICS.len() + m] = Some(*sd);
+                }
+            }
+            if let Some(p) = &row.p_values {
+                for (m, p) in [p.precision, p.recall, p.f1].iter().enumerate() {
+                    markers[e][d * METRICS.len() + m] = significance::marker(*p);
+                }
+            }
+        }
+        Matrix {
+            extractors,
+            datasets,
+            cells,
+            spreads,
+            markers,
+        }
+    }
+
+    /// `<dataset> <metric>` of every column
+    fn columns(&self) -> Vec<String> {
+        self.datasets
+            .iter()
+            .flat_map(|d| METRICS.iter().map(move |m| format!("{} {}", d, m)))
+            .collect()
+    }
+
+    /// whether the cell of extractor e in column c is the best of the column, ties included
+    fn is_best(&self, e: usize, c: usize) -> bool {
+        let best = self
+            .cells
+            .iter()
+            .filter_map(|row| row[c])
+            .fold(f64::NEG_INFINITY, f64::max);
+        self.cells[e][c] == Some(best)
+    }
+
+    /// the value of the cell with its spread and significance marker
+    fn cell(&self, e: usize, c: usize) -> String {
+        match self.cells[e][c] {
+            Some(value) => measure(value, self.spreads[e][c]) + self.markers[e][c],
+            None => "n/a".into(),
+        }
+    }
+}
+
+/// writes rows as a matrix of extractors by dataset and metric,
+/// followed by the legend of the markers if baseline is given
+pub fn write_matrix<W: Write>(
+    mut w: W,
+    rows: &[Row],
+    format: ReportFormat,
+    baseline: Option<&str>,
+) -> io::Result<()> {
+    let matrix = Matrix::new(rows);
+    let columns = matrix.columns();
+    let legend = baseline.map(legend);
+    match format {
+        ReportFormat::Markdown => {
+            let escape = |s: &str| s.replace('|', "\\|");
+            write!(w, "| extractor |")?;
+            for column in &columns {
+                write!(w, " {} |", escape(column))?;
+            }
+            write!(w, "\n| --- |")?;
+            for _ in &columns {
+                write!(w, " ---: |")?;
+            }
+            writeln!(w)?;
+            for (e, extractor) in matrix.extractors.iter().enumerate() {
+                write!(w, "| {} |", escape(extractor))?;
+                for c in 0..columns.len() {
+                    if matrix.is_best(e, c) {
+                        write!(w, " **{}** |", matrix.cell(e, c))?;
+                    } else {
+                        write!(w, " {} |", matrix.cell(e, c))?;
+                    }
+                }
+                writeln!(w)?;
+            }
+            if let Some(legend) = &legend {
+                writeln!(w, "\n{}", legend)?;
+            }
+        }
+        ReportFormat::Csv => {
+            write!(w, "extractor")?;
+            for column in &columns {
+                write!(w, ",{}", quote(column))?;
+            }
+            writeln!(w)?;
+            for (e, extractor) in matrix.extractors.iter().enumerate() {
+                write!(w, "{}", quote(extractor))?;
+                for c in 0..columns.len() {
+                    write!(w, ",{}", matrix.cell(e, c))?;
+                }
+                writeln!(w)?;
+            }
+            write!(w, "best")?;
+            for c in 0..columns.len() {
+                let best = (0..matrix.extractors.len())
+                    .filter(|e| matrix.is_best(*e, c))
+                    .map(|e| matrix.extractors[e])
+                    .collect::<Vec<_>>();
+                write!(w, ",{}", quote(&best.join(" ")))?;
+            }
+            writeln!(w)?;
+        }
+        ReportFormat::Html => {
+            writeln!(w, "<table>")?;
+            writeln!(w, "<thead>")?;
+            writeln!(w, "<tr><th rowspan=\"2\">extractor</th>")?;
+            for dataset in &matrix.datasets {
+                writeln!(
+                    w,
+                    "<th colspan=\"{}\">{}</th>",
+                    METRICS.len(),
+                    html(dataset)
+                )?;
+            }
+            writeln!(w, "</tr>")?;
+            write!(w, "<tr>")?;
+            for _ in &matrix.datasets {
+                for metric in METRICS {
+                    write!(w, "<th>{}</th>", metric)?;
+                }
+            }
+            writeln!(w, "</tr>")?;
+            writeln!(w, "</thead>")?;
+            writeln!(w, "<tbody>")?;
+            for (e, extractor) in matrix.extractors.iter().enumerate() {
+                write!(w, "<tr><th>{}</th>", html(extractor))?;
+                for c in 0..columns.len() {
+                    if matrix.is_best(e, c) {
+                        write!(w, "<td><b>{}</b></td>", matrix.cell(e, c))?;
+                    } else {
+                        write!(w, "<td>{}</td>", matrix.cell(e, c))?;
+                    }
+                }
+                writeln!(w, "</tr>")?;
+            }
+            writeln!(w, "</tbody>")?;
+            writeln!(w, "</table>")?;
+            if let Some(legend) = &legend {
+                writeln!(w, "<p>{}</p>", html(legend))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// writes the matrix of rows to path, in format or the one of its extension
+pub fn save_matrix(
+    path: &Path,
+    rows: &[Row],
+    format: Option<ReportFormat>,
+    baseline: Option<&str>,
+) -> io::Result<()> {
+    let format = format.unwrap_or_else(|| ReportFormat::of(path));
+    let mut writer = BufWriter::new(File::create(path)?);
+    write_matrix(&mut writer, rows, format, baseline)?;
+    writer.flush()
+}
+
+/// what the significance markers mean, against the extractor baseline
+pub fn legend(baseline: &str) -> String {
+    let levels = significance::LEVELS
+        .iter()
+        .map(|(level, marker)| format!("{} p < {}", marker, level))
+        .collect::<Vec<_>>();
+    format!(
+        "{}, paired randomization test against {}",
+        levels.join(", "),
+        baseline
+    )
+}
+
+/// s with the characters special to HTML escaped
+fn html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn paint(s: &str, code: &str, color: bool) -> String {
+    if color {
+        format!("{}{}{}", code, s, RESET)
+    } else {
+        s.to_string()
+    }
+}
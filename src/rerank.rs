@@ -0,0 +1,132 @@
+//! reorderings of a ranking that look at the terms themselves, not only their scores
+use crate::scoring::Collapse;
+use crate::vectors::{self, Vectors};
+use crate::{phrases, sort_ranking, HulthDocument};
+
+use rust_stemmers::{Algorithm, Stemmer};
+
+use std::collections::HashSet;
+
+/// EmbedRank: the cosine similarity of the mean vector of the words of each term
+/// to the mean vector of the words of doc. terms without any vector score -1,
+/// below all others
+pub fn embed_rank(
+    vectors: &Vectors,
+    doc: &HulthDocument,
+    ranked: Vec<(String, f64)>,
+) -> Vec<(String, f64)> {
+    let words = doc
+        .sentences
+        .iter()
+        .flat_map(|s| &s.tokens)
+        .filter(|t| !phrases::is_phrase(&t.word))
+        .map(|t| &*t.word);
+    let centroid = match vectors.mean(words) {
+        Some(centroid) => centroid,
+        None => {
+            warn!("no word of the document has a vector, keeping its ranking");
+            return ranked;
+        }
+    };
+    sort_ranking(ranked.into_iter().map(|(term, _)| {
+        let score = vectors
+            .mean(term.split(' '))
+            .map_or(-1f64, |v| vectors::cosine(&v, &centroid));
+        (term, score)
+    }))
+}
+
+/// Maximal Marginal Relevance: repeatedly picks the term maximizing
+/// lambda * score - (1 - lambda) * its highest similarity to the terms picked so far.
+/// scores are scaled to [0, 1] first, ranked must be best first
+pub fn mmr(ranked: Vec<(String, f64)>, lambda: f64) -> Vec<(String, f64)> {
+    let stemmer = Stemmer::create(Algorithm::English);
+    let max = ranked
+        .first()
+        .map_or(1f64, |(_, score)| score.abs().max(f64::EPSILON));
+    let mut candidates = ranked
+        .into_iter()
+        .map(|(term, score)| {
+            let features = Features::new(&term, &stemmer);
+            (term, score, features, 0f64)
+        })
+        .collect::<Vec<_>>();
+
+    let mut picked: Vec<(String, f64)> = Vec::with_capacity(candidates.len());
+    while !candidates.is_empty() {
+        let mut best = 0;
+        let mut best_value = f64::NEG_INFINITY;
+        for (i, (_, score, _, redundancy)) in candidates.iter().enumerate() {
+            let value = lambda * score / max - (1f64 - lambda) * redundancy;
+            if value > best_value {
+                best = i;
+                best_value = value;
+            }
+        }
+        let (term, score, features, _) = candidates.remove(best);
+        for (_, _, other, redundancy) in candidates.iter_mut() {
+            *redundancy = redundancy.max(features.similarity(other));
+        }
+        picked.push((term, score));
+    }
+    picked
+}
+
+/// merges terms with the same stems or where one contains the words of the other
+/// into groups, keeping one term per group as chosen by policy.
+/// ranked must be best first
+pub fn collapse(ranked: Vec<(String, f64)>, policy: Collapse) -> Vec<(String, f64)> {
+    let stemmer = Stemmer::create(Algorithm::English);
+    // the kept term of every group with its lowercased form padded
+    // with spaces, so that containment is checked on whole words, and its stems
+    let mut groups: Vec<((String, f64), String, Vec<String>)> = vec![];
+    for (term, score) in ranked {
+        let lower = format!(" {} ", term.to_lowercase());
+        let stems = lower
+            .split_whitespace()
+            .map(|word| stemmer.stem(word).into_owned())
+            .collect::<Vec<_>>();
+        let group = groups.iter_mut().find(|(_, kept, kept_stems)| {
+            *kept_stems == stems || kept.contains(&lower) || lower.contains(kept.as_str())
+        });
+        match group {
+            Some(group) => {
+                if policy == Collapse::Longest && lower.len() > group.1.len() {
+                    *group = ((term, score), lower, stems);
+                }
+            }
+            None => groups.push(((term, score), lower, stems)),
+        }
+    }
+    sort_ranking(groups.into_iter().map(|(kept, _, _)| kept))
+}
+
+/// what two terms are compared by
+struct Features {
+    stems: HashSet<String>,
+    bigrams: HashSet<(char, char)>,
+}
+
+impl Features {
+    fn new(term: &str, stemmer: &Stemmer) -> Self {
+        let lower = term.to_lowercase();
+        let stems = lower
+            .split(' ')
+            .map(|word| stemmer.stem(word).into_owned())
+            .collect();
+        let chars = lower.chars().collect::<Vec<_>>();
+        let bigrams = chars.windows(2).map(|w| (w[0], w[1])).collect();
+        Features { stems, bigrams }
+    }
+
+    /// the larger of the Jaccard index of the stems
+    /// and the Dice coefficient of the character bigrams
+    fn similarity(&self, other: &Features) -> f64 {
+        let shared_stems = self.stems.intersection(&other.stems).count();
+        let stems = shared_stems as f64 / self.stems.union(&other.stems).count().max(1) as f64;
+        let shared_bigrams = self.bigrams.intersection(&other.bigrams).count();
+        let total_bigrams = (self.bigrams.len() + other.bigrams.len()).max(1);
+        let bigrams = 2f64 * shared_bigrams as f64 / total_bigrams as f64;
+        stems.max(bigrams)
+    }
+}
@@ -0,0 +1,367 @@
+//! the results of an evaluation, serialized into the files of `--results` and
+//! `--summary-out`: a `DocumentResult` per document, a line of the results file,
+//! with its `MetricSet`, and the `RunResult` of a run. both carry the version of
+//! their schema, 0 for files written before it was recorded
+use crate::bio::BioCounts;
+use crate::breakdown::{Adaptive, GroupMeasure, PosCounts, Positions};
+use crate::matching::SynonymMatch;
+use crate::report::{Row, Summary, Totals};
+use crate::rouge::Rouge;
+use crate::Degenerate;
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::sync::Arc;
+
+/// the version of the schema written, raised when a field changes meaning or goes away.
+/// new fields with defaults don't need a new version
+pub const SCHEMA: u32 = 1;
+
+/// the measures of a ranking against the references of its document
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct MetricSet {
+    pub precision: f64,
+    pub recall: f64,
+    pub f1: f64,
+    /// missing in results written before it existed, like the measures below
+    #[serde(default)]
+    pub average_precision: f64,
+    #[serde(default)]
+    pub ndcg: f64,
+    /// the share of the distinct words of the references among the ranked words
+    #[serde(default)]
+    pub coverage: f64,
+}
+
+/// the results of one document
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DocumentResult {
+    #[serde(default)]
+    pub(crate) schema: u32,
+    pub(crate) document: String,
+    #[serde(flatten)]
+    pub(crate) metrics: MetricSet,
+    #[serde(default)]
+    pub(crate) adaptive: Adaptive,
+    /// why the document has no measures, its zeros are left out of the means
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) degenerate: Option<Degenerate>,
+    /// token counts per word class for `--breakdown pos`,
+    /// missing in results written before it existed
+    #[serde(default)]
+    pub(crate) pos: PosCounts,
+    /// number of tokens of the document as ranked, after preprocessing
+    #[serde(default)]
+    pub(crate) tokens: usize,
+    /// number of reference keyphrases of the document
+    #[serde(default)]
+    pub(crate) keyphrases: usize,
+    /// number of its keyphrases that never occur in the document
+    #[serde(default)]
+    pub(crate) unreachable: usize,
+    #[serde(default)]
+    pub(crate) positions: Positions,
+    #[serde(default)]
+    pub(crate) bio: BioCounts,
+    /// the n-gram counts of `--breakdown rouge`
+    #[serde(default)]
+    pub(crate) rouge: Rouge,
+    /// Spearman's rho of scores and gold relevance, None if undefined
+    #[serde(default)]
+    pub(crate) relevance: Option<f64>,
+    /// the measures against each keyword group on its own, for `--breakdown groups`
+    #[serde(default)]
+    pub(crate) groups: Vec<GroupMeasure>,
+    /// the measures against the keyphrases occurring in the document,
+    /// for `--breakdown presence`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) present: Option<GroupMeasure>,
+    /// the measures against the unreachable keyphrases
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) absent: Option<GroupMeasure>,
+    /// the ranked terms matched only through a synonym with --match-mode synonym
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) synonyms: Vec<SynonymMatch>,
+    /// the ranked terms, best first, only kept in memory for comparing extractors
+    #[serde(skip)]
+    pub(crate) ranking: Vec<Arc<str>>,
+    /// score and relevance of every ranked term, in memory only like ranking
+    #[serde(skip)]
+    pub(crate) candidates: Vec<(f64, bool)>,
+}
+
+/// the results of a run, the summary over its documents,
+/// or a row for every dataset and extractor of a run over several
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RunResult {
+    #[serde(default)]
+    pub schema: u32,
+    #[serde(flatten)]
+    pub summary: Option<Summary>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rows: Vec<Row>,
+}
+
+impl RunResult {
+    pub fn new(summary: Summary) -> Self {
+        RunResult {
+            schema: SCHEMA,
+            summary: Some(summary),
+            rows: vec![],
+        }
+    }
+
+    pub fn with_rows(rows: Vec<Row>) -> Self {
+        RunResult {
+            schema: SCHEMA,
+            summary: None,
+            rows,
+        }
+    }
+
+    /// the summary of a single run read from path, as `--baseline`
+    pub fn load_summary<P: AsRef<Path>>(path: P) -> io::Result<Summary> {
+        let path = path.as_ref();
+        RunResult::load(path)?.summary.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "{}: holds the rows of several runs, not one summary",
+                    path.display()
+                ),
+            )
+        })
+    }
+
+    /// reads a file written by save, or a summary written before the schema was versioned
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref();
+        let result: RunResult = serde_json::from_reader(BufReader::new(File::open(path)?))?;
+        check_schema(path, result.schema)?;
+        Ok(result)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        writeln!(writer)
+    }
+}
+
+/// refuses results of a newer schema, whose fields may mean something else
+fn check_schema(path: &Path, schema: u32) -> io::Result<()> {
+    if schema > SCHEMA {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "{}: written with schema {}, this version reads up to {}",
+                path.display(),
+                schema,
+                SCHEMA
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// writes one JSON line per document
+pub fn write<P: AsRef<Path>>(path: P, measures: &[DocumentResult]) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    for measure in measures {
+        serde_json::to_writer(&mut writer, measure)?;
+        writeln!(writer)?;
+    }
+    writer.flush()
+}
+
+/// reads a file written by write
+pub fn read<P: AsRef<Path>>(path: P) -> io::Result<Vec<DocumentResult>> {
+    let mut measures = vec![];
+    for_each(path, |measure| measures.push(measure))?;
+    Ok(measures)
+}
+
+/// the totals of a file written by write, reading one measure at a time
+pub fn totals<P: AsRef<Path>>(path: P) -> io::Result<Totals> {
+    let mut totals = Totals::default();
+    for_each(path, |measure| totals.add(&measure))?;
+    Ok(totals)
+}
+
+fn for_each<P: AsRef<Path>, F: FnMut(DocumentResult)>(path: P, mut f: F) -> io::Result<()> {
+    let path = path.as_ref();
+    for (i, line) in BufReader::new(File::open(path)?).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let measure: DocumentResult = serde_json::from_str(&line).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}:{}: {}", path.display(), i + 1, e),
+            )
+        })?;
+        check_schema(path, measure.schema)?;
+        f(measure);
+    }
+    Ok(())
+}
+
+/// append-only results file, every line is flushed as it is written
+/// so an interrupted run leaves all finished documents behind
+pub struct Checkpoint {
+    writer: BufWriter<File>,
+}
+
+impl Checkpoint {
+    /// starts a new results file, replacing an existing one
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(Checkpoint {
+            writer: BufWriter::new(File::create(path)?),
+        })
+    }
+
+    /// reads the results of an interrupted run and continues appending to them.
+    /// a last line cut off mid-write is dropped, a missing file starts fresh
+    pub fn resume<P: AsRef<Path>>(path: P) -> io::Result<(Vec<DocumentResult>, Self)> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok((vec![], Self::create(path)?));
+        }
+        let content = fs::read(path)?;
+        let mut measures = vec![];
+        let mut valid = 0;
+        for (i, line) in content.split_inclusive(|b| *b == b'\n').enumerate() {
+            if line.iter().all(u8::is_ascii_whitespace) {
+                valid += line.len();
+                continue;
+            }
+            match serde_json::from_slice::<DocumentResult>(line) {
+                Ok(measure) => {
+                    check_schema(path, measure.schema)?;
+                    measures.push(measure)
+                }
+                Err(_) if !line.ends_with(b"\n") => {
+                    warn!(path = %path.display(), "dropping incomplete last result line");
+                    break;
+                }
+                Err(e) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("{}:{}: {}", path.display(), i + 1, e),
+                    ))
+                }
+            }
+            valid += line.len();
+        }
+
+        let file = OpenOptions::new().append(true).open(path)?;
+        file.set_len(valid as u64)?;
+        let mut checkpoint = Checkpoint {
+            writer: BufWriter::new(file),
+        };
+        if valid > 0 && content[valid - 1] != b'\n' {
+            checkpoint.writer.write_all(b"\n")?;
+        }
+        Ok((measures, checkpoint))
+    }
+
+    pub fn append(&mut self, measure: &DocumentResult) -> io::Result<()> {
+        serde_json::to_writer(&mut self.writer, measure)?;
+        writeln!(self.writer)?;
+        self.writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(document: &str) -> DocumentResult {
+        serde_json::from_value(serde_json::json!({
+            "schema": SCHEMA,
+            "document": document,
+            "precision": 0.5,
+            "recall": 0.25,
+            "f1": 1.0 / 3.0,
+        }))
+        .unwrap()
+    }
+
+    fn documents(results: &[DocumentResult]) -> Vec<&str> {
+        results.iter().map(|r| r.document.as_str()).collect()
+    }
+
+    /// a results file of the documents, each line finished
+    fn written(path: &Path, names: &[&str]) {
+        let mut checkpoint = Checkpoint::create(path).unwrap();
+        for name in names {
+            checkpoint.append(&result(name)).unwrap();
+        }
+    }
+
+    #[test]
+    fn resuming_a_finished_file_leaves_nothing_to_score() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("results.jsonl");
+        written(&path, &["d0", "d1"]);
+        let before = fs::read(&path).unwrap();
+        let (finished, checkpoint) = Checkpoint::resume(&path).unwrap();
+        drop(checkpoint);
+        assert_eq!(documents(&finished), ["d0", "d1"]);
+        assert_eq!(fs::read(&path).unwrap(), before);
+    }
+
+    #[test]
+    fn a_cut_off_last_line_is_truncated() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("results.jsonl");
+        written(&path, &["d0", "d1"]);
+        let complete = fs::read(&path).unwrap();
+        let mut cut = complete.clone();
+        cut.extend_from_slice(&serde_json::to_vec(&result("d2")).unwrap()[..20]);
+        fs::write(&path, &cut).unwrap();
+
+        let (finished, mut checkpoint) = Checkpoint::resume(&path).unwrap();
+        assert_eq!(documents(&finished), ["d0", "d1"]);
+        assert_eq!(fs::read(&path).unwrap(), complete);
+        checkpoint.append(&result("d2")).unwrap();
+        assert_eq!(documents(&read(&path).unwrap()), ["d0", "d1", "d2"]);
+    }
+
+    #[test]
+    fn a_complete_last_line_without_newline_is_kept() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("results.jsonl");
+        fs::write(&path, serde_json::to_vec(&result("d0")).unwrap()).unwrap();
+        let (finished, mut checkpoint) = Checkpoint::resume(&path).unwrap();
+        assert_eq!(documents(&finished), ["d0"]);
+        checkpoint.append(&result("d1")).unwrap();
+        assert_eq!(documents(&read(&path).unwrap()), ["d0", "d1"]);
+    }
+
+    #[test]
+    fn corrupt_lines_before_the_last_fail() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("results.jsonl");
+        written(&path, &["d0"]);
+        let mut content = b"{\"document\":\n".to_vec();
+        content.extend(fs::read(&path).unwrap());
+        fs::write(&path, &content).unwrap();
+        let err = Checkpoint::resume(&path).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("results.jsonl:1:"), "{}", err);
+        assert_eq!(fs::read(&path).unwrap(), content);
+    }
+
+    #[test]
+    fn a_missing_file_starts_fresh() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("results.jsonl");
+        let (finished, mut checkpoint) = Checkpoint::resume(&path).unwrap();
+        assert!(finished.is_empty());
+        checkpoint.append(&result("d0")).unwrap();
+        assert_eq!(documents(&read(&path).unwrap()), ["d0"]);
+    }
+}
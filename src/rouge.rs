@@ -0,0 +1,187 @@
+//! ROUGE-N of a ranking for users coming from summarization: the words of the ranked
+//! terms, best first, and those of the reference keyphrases are each concatenated
+//! into one text and their n-grams compared, a repeated n-gram matching up to as
+//! often as it occurs on both sides. like in a summary, n-grams span neighbouring phrases
+use crate::metrics::{f1, mean};
+use crate::preprocess::Normalizer;
+use crate::results::DocumentResult;
+
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::sync::Arc;
+
+/// the n-gram counts of ROUGE-1 and ROUGE-2 of one document
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Rouge {
+    pub one: Overlap,
+    pub two: Overlap,
+}
+
+/// the n-grams of the predictions, of the references and those they share
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Overlap {
+    pub predicted: usize,
+    pub reference: usize,
+    pub overlap: usize,
+}
+
+impl Overlap {
+    fn of(predicted: &[&str], reference: &[&str], n: usize) -> Self {
+        let predicted = ngrams(predicted, n);
+        let reference = ngrams(reference, n);
+        Overlap {
+            predicted: predicted.values().sum(),
+            reference: reference.values().sum(),
+            overlap: predicted
+                .iter()
+                .map(|(ngram, count)| (*count).min(reference.get(ngram).copied().unwrap_or(0)))
+                .sum(),
+        }
+    }
+
+    pub fn precision(&self) -> f64 {
+        ratio(self.overlap, self.predicted)
+    }
+
+    pub fn recall(&self) -> f64 {
+        ratio(self.overlap, self.reference)
+    }
+}
+
+/// the n-grams of words with their number of occurrences
+fn ngrams<'a, 'b>(words: &'b [&'a str], n: usize) -> HashMap<&'b [&'a str], usize> {
+    let mut counts = HashMap::new();
+    for window in words.windows(n) {
+        *counts.entry(window).or_insert(0) += 1;
+    }
+    counts
+}
+
+/// ranked is cut to --top-k, the keyphrases of all groups are concatenated in
+/// the order of the references and normalized like the ranked terms
+pub fn of(
+    ranked: &[(Arc<str>, f64)],
+    keyphrases: &[Vec<String>],
+    normalizer: &Normalizer,
+) -> Rouge {
+    let predicted = ranked
+        .iter()
+        .flat_map(|(term, _)| term.split(' '))
+        .collect::<Vec<_>>();
+    let reference = keyphrases
+        .iter()
+        .flatten()
+        .flat_map(|phrase| normalizer.reference_words(phrase))
+        .collect::<Vec<_>>();
+    let reference = reference.iter().map(String::as_str).collect::<Vec<_>>();
+    Rouge {
+        one: Overlap::of(&predicted, &reference, 1),
+        two: Overlap::of(&predicted, &reference, 2),
+    }
+}
+
+/// the means of ROUGE-1 and ROUGE-2 over the documents, like the summary
+pub fn print_rouge<W: Write>(mut w: W, measures: &[DocumentResult]) -> io::Result<()> {
+    let measures = measures
+        .iter()
+        .filter(|m| m.degenerate.is_none())
+        .collect::<Vec<_>>();
+    writeln!(
+        w,
+        "{:<10}  {:>9}  {:>9}  {:>9}  {:>9}",
+        "rouge", "documents", "precision", "recall", "f1"
+    )?;
+    let one = measures.iter().map(|m| m.rouge.one).collect::<Vec<_>>();
+    let two = measures.iter().map(|m| m.rouge.two).collect::<Vec<_>>();
+    for (name, overlaps) in [("rouge-1", one), ("rouge-2", two)] {
+        let mean = |f: &dyn Fn(&Overlap) -> f64| {
+            mean(&overlaps.iter().map(f).collect::<Vec<_>>()).format()
+        };
+        writeln!(
+            w,
+            "{:<10}  {:>9}  {:>9}  {:>9}  {:>9}",
+            name,
+            measures.len(),
+            mean(&Overlap::precision),
+            mean(&Overlap::recall),
+            mean(&|o| f1(o.precision(), o.recall()))
+        )?;
+    }
+    Ok(())
+}
+
+fn ratio(part: usize, whole: usize) -> f64 {
+    if whole == 0 {
+        return 0f64;
+    }
+    part as f64 / whole as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::preprocess::Preprocessing;
+
+    fn ranked(terms: &[&str]) -> Vec<(Arc<str>, f64)> {
+        terms.iter().map(|t| (Arc::from(*t), 1.0)).collect()
+    }
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn n_grams_span_neighbouring_phrases() {
+        let normalizer = Preprocessing::default().normalizer().unwrap();
+        let keyphrases = vec![vec!["neural network model".into(), "learning".into()]];
+        let rouge = of(
+            &ranked(&["neural network", "deep learning"]),
+            &keyphrases,
+            &normalizer,
+        );
+        // neural, network and learning of 4 words on each side
+        assert_eq!(
+            (rouge.one.predicted, rouge.one.reference, rouge.one.overlap),
+            (4, 4, 3)
+        );
+        assert!(close(rouge.one.precision(), 0.75));
+        assert!(close(rouge.one.recall(), 0.75));
+        assert!(close(f1(rouge.one.precision(), rouge.one.recall()), 0.75));
+        // only "neural network" of 3 bigrams on each side
+        assert_eq!(
+            (rouge.two.predicted, rouge.two.reference, rouge.two.overlap),
+            (3, 3, 1)
+        );
+        assert!(close(rouge.two.precision(), 1.0 / 3.0));
+        assert!(close(rouge.two.recall(), 1.0 / 3.0));
+    }
+
+    #[test]
+    fn repeated_n_grams_match_as_often_as_on_both_sides() {
+        let overlap = Overlap::of(&["a", "a", "a", "b"], &["a", "b", "b"], 1);
+        assert_eq!(overlap.overlap, 2);
+        assert!(close(overlap.precision(), 0.5));
+        assert!(close(overlap.recall(), 2.0 / 3.0));
+        assert!(close(f1(overlap.precision(), overlap.recall()), 4.0 / 7.0));
+        let overlap = Overlap::of(&["a", "b", "a", "b"], &["a", "b"], 2);
+        assert_eq!(
+            (overlap.predicted, overlap.reference, overlap.overlap),
+            (3, 1, 1)
+        );
+    }
+
+    #[test]
+    fn an_empty_reference_scores_zero() {
+        let normalizer = Preprocessing::default().normalizer().unwrap();
+        let rouge = of(&ranked(&["neural network"]), &[], &normalizer);
+        for overlap in [rouge.one, rouge.two] {
+            assert_eq!(overlap.reference, 0);
+            assert_eq!(overlap.precision(), 0.0);
+            assert_eq!(overlap.recall(), 0.0);
+            assert_eq!(f1(overlap.precision(), overlap.recall()), 0.0);
+        }
+        let rouge = of(&ranked(&["neural"]), &[vec!["neural".into()]], &normalizer);
+        assert_eq!(rouge.two.predicted, 0);
+        assert!(close(rouge.one.recall(), 1.0));
+    }
+}
@@ -0,0 +1,69 @@
+//! `--run-dir`, a new directory under `runs/` for every run so
+//! the files of different experiments never overwrite each other
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// the directory the run directories are created in
+const ROOT: &str = "runs";
+
+/// the files of a run directory, see `create`
+pub const MANIFEST: &str = "manifest.json";
+pub const SUMMARY: &str = "summary.json";
+pub const RESULTS: &str = "results.jsonl";
+pub const KEYWORDS: &str = "keywords.jsonl";
+pub const LOG: &str = "run.log";
+#[cfg(feature = "alloc-stats")]
+pub const MEMORY: &str = "memory.json";
+#[cfg(feature = "profile")]
+pub const FLAMEGRAPH: &str = "flamegraph.svg";
+
+/// creates `runs/<timestamp>-<label>/`, the timestamp being the UTC
+/// start of the run such as `20200412T093015Z`
+pub fn create(label: &str) -> io::Result<PathBuf> {
+    if label.is_empty() || Path::new(label).file_name() != Some(label.as_ref()) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("--run-dir {:?} is not usable as a directory name", label),
+        ));
+    }
+    let dir = Path::new(ROOT).join(format!("{}-{}", timestamp(SystemTime::now()), label));
+    fs::create_dir_all(ROOT)?;
+    // create_dir fails if the directory exists, two runs never share one
+    fs::create_dir(&dir)?;
+    Ok(dir)
+}
+
+fn timestamp(time: SystemTime) -> String {
+    let seconds = time
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let (days, seconds) = (seconds / 86400, seconds % 86400);
+    let (year, month, day) = civil_from_days(days as i64);
+    format!(
+        "{:04}{:02}{:02}T{:02}{:02}{:02}Z",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+/// the proleptic Gregorian date of a number of days since 1970-01-01,
+/// Howard Hinnant's `civil_from_days`
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
@@ -0,0 +1,451 @@
+//! adjustments of the raw tf-idf scores before the terms are ranked
+use crate::fnv::Fnv;
+use crate::kp_miner::KpMiner;
+use crate::logistic::{Reranker, Scorer, Weights};
+#[cfg(feature = "onnx")]
+use crate::onnx::OnnxModel;
+use crate::rng::Rng;
+use crate::textrank::TextRank;
+use crate::vectors::{Precision, VectorFormat, Vectors};
+use crate::weighting::DocumentFrequencies;
+use crate::{rerank, sort_ranking, HulthDocument};
+
+use clap::{Args, ValueEnum};
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+/// the ranking variants compared by eval
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Extractor {
+    /// the tf-idf scores
+    Tfidf,
+    /// tf-idf scores decayed by the position of the first occurrence, see --decay-rate
+    PositionDecay,
+    /// tf-idf candidates rescored by the logistic regression of --reranker
+    Logistic,
+    /// tf-idf candidates rescored by the ONNX model of --reranker
+    #[cfg(feature = "onnx")]
+    Onnx,
+    /// tf-idf candidates rescored by TextRank, see --textrank-window
+    #[value(name = "textrank")]
+    #[serde(rename = "textrank")]
+    TextRank,
+    /// KP-Miner: tf-idf candidates seen at least --kp-lasf times within the first
+    /// --kp-cutoff words, multi-word candidates boosted, see --kp-alpha
+    KpMiner,
+    /// tf-idf candidates rescored by the similarity of their word vectors
+    /// to the mean vector of the document, see --vectors
+    EmbedRank,
+    /// the tf-idf candidates in a random order drawn with --seed,
+    /// the baseline every other extractor should beat
+    Random,
+}
+
+/// which of a group of near-duplicate terms is kept
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Collapse {
+    /// the best ranked term of the group
+    Highest,
+    /// the longest term of the group, with its own score
+    Longest,
+}
+
+/// rescaling of the final scores of a document, keeping their order
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScoreNorm {
+    /// the scores as ranked
+    #[default]
+    None,
+    /// scaled to [0, 1] by the lowest and highest score
+    Minmax,
+    /// the number of standard deviations from the mean score
+    Zscore,
+    /// exp(score) divided by the sum over all terms, the scores sum to 1
+    Softmax,
+}
+
+impl fmt::Display for Extractor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Extractor::Tfidf => "tfidf",
+            Extractor::PositionDecay => "position-decay",
+            Extractor::Logistic => "logistic",
+            #[cfg(feature = "onnx")]
+            Extractor::Onnx => "onnx",
+            Extractor::TextRank => "textrank",
+            Extractor::KpMiner => "kp-miner",
+            Extractor::EmbedRank => "embed-rank",
+            Extractor::Random => "random",
+        };
+        f.write_str(name)
+    }
+}
+
+impl Extractor {
+    /// whether the extractor rescores with the model of --reranker
+    pub fn is_reranked(self) -> bool {
+        match self {
+            Extractor::Logistic => true,
+            #[cfg(feature = "onnx")]
+            Extractor::Onnx => true,
+            _ => false,
+        }
+    }
+
+    /// whether the ranking depends on --seed, so runs with several seeds
+    /// are averaged, see `eval --seeds`
+    pub fn is_stochastic(self) -> bool {
+        self == Extractor::Random
+    }
+
+    /// the option group of the extractor and the other options only it uses,
+    /// what `[extractors.<name>]` may set besides COMMON_OPTIONS
+    fn options(self) -> (Option<&'static str>, &'static [&'static str]) {
+        match self {
+            Extractor::Tfidf => (None, &[]),
+            Extractor::PositionDecay => (None, &["decay_rate"]),
+            Extractor::Logistic => (None, &["reranker"]),
+            #[cfg(feature = "onnx")]
+            Extractor::Onnx => (None, &["reranker"]),
+            Extractor::TextRank => (Some("textrank"), &[]),
+            Extractor::KpMiner => (Some("kp_miner"), &[]),
+            Extractor::EmbedRank => (None, &["vectors", "vector_format", "vector_cache"]),
+            Extractor::Random => (None, &["seed"]),
+        }
+    }
+}
+
+/// the options every `[extractors.<name>]` section may set
+const COMMON_OPTIONS: &[&str] = &["title_boost", "mmr_lambda", "collapse", "score_norm"];
+
+#[derive(Debug, Clone, PartialEq, Args, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Scoring {
+    /// multiplies the scores of terms occurring in the first sentence,
+    /// the title in the Hulth data
+    #[arg(long, default_value_t = 1.0)]
+    pub title_boost: f64,
+
+    /// the position-decay extractor multiplies scores by exp(-rate * p),
+    /// p being the first offset of the term relative to the document length
+    #[arg(long, default_value_t = 1.0)]
+    pub decay_rate: f64,
+
+    #[command(flatten)]
+    #[serde(default)]
+    pub textrank: TextRank,
+
+    #[command(flatten)]
+    #[serde(default)]
+    pub kp_miner: KpMiner,
+
+    /// reorders the ranking by Maximal Marginal Relevance with this lambda,
+    /// 1 keeps the order and lower values push down terms similar to better ones
+    #[arg(long)]
+    pub mmr_lambda: Option<f64>,
+
+    /// collapses terms sharing their stems or contained in one another
+    /// into the one term chosen by the policy
+    #[arg(long, value_enum)]
+    pub collapse: Option<Collapse>,
+
+    /// rescales the scores of every document so they are comparable across documents,
+    /// applied to all ranked terms before any cutoff
+    #[arg(long, value_enum, default_value_t = ScoreNorm::None)]
+    pub score_norm: ScoreNorm,
+
+    /// weights written by `train` for the logistic extractor,
+    /// or the ONNX model of the onnx extractor
+    #[arg(long)]
+    pub reranker: Option<PathBuf>,
+
+    /// word vectors of the embed-rank extractor, GloVe, word2vec or a `convert-vectors` store
+    #[arg(long)]
+    pub vectors: Option<PathBuf>,
+
+    /// the format of --vectors
+    #[arg(long, value_enum, default_value_t = VectorFormat::Auto)]
+    pub vector_format: VectorFormat,
+
+    /// the most word vectors kept decoded
+    #[arg(long, default_value_t = 100_000)]
+    pub vector_cache: usize,
+
+    /// the seed of every random choice of a run, the shuffles of the random extractor
+    /// and the sign flips of the significance tests, the same seed repeats a run exactly
+    #[arg(long, default_value_t = 0)]
+    pub seed: u64,
+
+    /// the `[extractors.<name>]` sections of the config file, options applied
+    /// only when ranking with that extractor, see for_extractor
+    #[arg(skip)]
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub extractors: BTreeMap<Extractor, toml::Table>,
+}
+
+impl Default for Scoring {
+    fn default() -> Self {
+        Scoring {
+            title_boost: 1.0,
+            decay_rate: 1.0,
+            textrank: TextRank::default(),
+            kp_miner: KpMiner::default(),
+            mmr_lambda: None,
+            collapse: None,
+            score_norm: ScoreNorm::None,
+            reranker: None,
+            vectors: None,
+            vector_format: VectorFormat::Auto,
+            vector_cache: 100_000,
+            seed: 0,
+            extractors: BTreeMap::new(),
+        }
+    }
+}
+
+impl Scoring {
+    /// the options ranking with extractor, those of its `[extractors.<name>]`
+    /// section taking precedence. options the extractor doesn't use are an error
+    pub fn for_extractor(&self, extractor: Extractor) -> io::Result<Scoring> {
+        let resolved = self.resolve(extractor)?;
+        if extractor == Extractor::TextRank {
+            resolved.textrank.check()?;
+        }
+        Ok(resolved)
+    }
+
+    fn resolve(&self, extractor: Extractor) -> io::Result<Scoring> {
+        let resolved = Scoring {
+            extractors: BTreeMap::new(),
+            ..self.clone()
+        };
+        let section = match self.extractors.get(&extractor) {
+            Some(section) => section,
+            None => return Ok(resolved),
+        };
+        let invalid = |e: String| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("[extractors.{}]: {}", extractor, e),
+            )
+        };
+        let mut value = serde_json::to_value(resolved)?;
+        let object = value
+            .as_object_mut()
+            .expect("options serialize to an object");
+        let (group, own) = extractor.options();
+        let mut grouped = match group.and_then(|g| object.remove(g)) {
+            Some(serde_json::Value::Object(grouped)) => grouped,
+            _ => serde_json::Map::new(),
+        };
+        for (key, option) in section {
+            let option = serde_json::to_value(option)?;
+            if grouped.contains_key(key) {
+                grouped.insert(key.clone(), option);
+            } else if COMMON_OPTIONS.contains(&key.as_str()) || own.contains(&key.as_str()) {
+                object.insert(key.clone(), option);
+            } else {
+                let expected = COMMON_OPTIONS
+                    .iter()
+                    .chain(own)
+                    .copied()
+                    .chain(grouped.keys().map(String::as_str))
+                    .collect::<Vec<_>>();
+                return Err(invalid(format!(
+                    "unknown option `{}`, expected one of {}",
+                    key,
+                    expected.join(", ")
+                )));
+            }
+        }
+        if let Some(group) = group {
+            object.insert(group.into(), grouped.into());
+        }
+        serde_json::from_value(value).map_err(|e| invalid(e.to_string()))
+    }
+
+    /// the reranker of the logistic, onnx and embed-rank extractors, None for the other extractors.
+    /// frequencies gives the document frequencies of its features, called only for a reranker
+    pub fn reranker<F>(&self, extractor: Extractor, frequencies: F) -> io::Result<Option<Reranker>>
+    where
+        F: FnOnce() -> DocumentFrequencies,
+    {
+        let path = || {
+            self.reranker.as_ref().ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("the {} extractor needs --reranker", extractor),
+                )
+            })
+        };
+        let scorer = match extractor {
+            Extractor::Logistic => Scorer::Logistic(Weights::load(path()?)?),
+            #[cfg(feature = "onnx")]
+            Extractor::Onnx => Scorer::Onnx(OnnxModel::load(path()?)?),
+            Extractor::EmbedRank => {
+                let path = self.vectors.as_ref().ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "the embed-rank extractor needs --vectors",
+                    )
+                })?;
+                let vectors =
+                    Vectors::open(path, self.vector_format, Precision::F32, self.vector_cache)?;
+                Scorer::Embedding(vectors)
+            }
+            _ => return Ok(None),
+        };
+        Ok(Some(Reranker::new(scorer, frequencies())))
+    }
+
+    /// the scores of the terms of doc as adjusted for extractor, best first.
+    /// reranker is the one of `Scoring::reranker` for extractor
+    pub fn rank<R: IntoIterator<Item = (String, f64)>>(
+        &self,
+        extractor: Extractor,
+        reranker: Option<&Reranker>,
+        doc: &HulthDocument,
+        scores: R,
+    ) -> Vec<(String, f64)> {
+        let scores = self.boost_title(doc, scores);
+        let ranked = match (extractor, reranker) {
+            (Extractor::PositionDecay, _) => sort_ranking(self.decay(doc, scores)),
+            (Extractor::TextRank, _) => sort_ranking(self.textrank.rescore(doc, scores)),
+            (Extractor::KpMiner, _) => sort_ranking(self.kp_miner.rescore(doc, scores)),
+            (Extractor::Random, _) => sort_ranking(self.shuffle(scores)),
+            (_, Some(reranker)) => reranker.rescore(doc, sort_ranking(scores)),
+            _ => sort_ranking(scores),
+        };
+        let ranked = match self.collapse {
+            Some(policy) => rerank::collapse(ranked, policy),
+            None => ranked,
+        };
+        let ranked = match self.mmr_lambda {
+            Some(lambda) => rerank::mmr(ranked, lambda),
+            None => ranked,
+        };
+        normalize(ranked, self.score_norm)
+    }
+
+    fn boost_title<R: IntoIterator<Item = (String, f64)>>(
+        &self,
+        doc: &HulthDocument,
+        scores: R,
+    ) -> Vec<(String, f64)> {
+        if self.title_boost == 1.0 {
+            return scores.into_iter().collect();
+        }
+        let title = doc
+            .sentences
+            .first()
+            .map(|s| s.tokens.iter().map(|t| &*t.word).collect::<HashSet<_>>())
+            .unwrap_or_default();
+        scores
+            .into_iter()
+            .map(|(term, score)| {
+                if title.contains(term.as_str()) {
+                    (term, score * self.title_boost)
+                } else {
+                    (term, score)
+                }
+            })
+            .collect()
+    }
+
+    /// uniform random scores, drawn in the order of the terms from --seed and
+    /// the terms themselves, so the order scores arrive in doesn't matter
+    fn shuffle(&self, scores: Vec<(String, f64)>) -> Vec<(String, f64)> {
+        let mut terms = scores.into_iter().map(|(term, _)| term).collect::<Vec<_>>();
+        terms.sort_unstable();
+        let mut hash = Fnv::new();
+        for term in &terms {
+            hash.write(term.as_bytes());
+            hash.write(&[0]);
+        }
+        let mut rng = Rng::new(self.seed ^ hash.finish());
+        terms.into_iter().map(|term| (term, rng.float())).collect()
+    }
+
+    fn decay(&self, doc: &HulthDocument, scores: Vec<(String, f64)>) -> Vec<(String, f64)> {
+        let tokens = doc.sentences.iter().flat_map(|s| s.tokens.iter());
+        let length = tokens
+            .clone()
+            .map(|t| t.offset_end)
+            .max()
+            .unwrap_or(0)
+            .max(1) as f64;
+        let mut first = HashMap::new();
+        for token in tokens {
+            first.entry(&*token.word).or_insert(token.offset_begin);
+        }
+        scores
+            .into_iter()
+            .map(|(term, score)| {
+                let offset = first.get(term.as_str()).copied().unwrap_or(0) as f64;
+                (term, score * (-self.decay_rate * offset / length).exp())
+            })
+            .collect()
+    }
+}
+
+/// checks the `[extractors]` table of the config file, naming extractors
+/// and setting options of the right type they use
+pub fn check_extractors(table: &toml::Table) -> io::Result<()> {
+    let extractors = toml::Value::Table(table.clone())
+        .try_into::<BTreeMap<Extractor, toml::Table>>()
+        .map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("[extractors]: {}", e.message()),
+            )
+        })?;
+    let scoring = Scoring {
+        extractors,
+        ..Scoring::default()
+    };
+    for extractor in scoring.extractors.keys() {
+        scoring.for_extractor(*extractor)?;
+    }
+    Ok(())
+}
+
+fn normalize(mut ranked: Vec<(String, f64)>, norm: ScoreNorm) -> Vec<(String, f64)> {
+    let scores = ranked.iter().map(|(_, s)| *s).collect::<Vec<_>>();
+    let max = scores.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let scale: Box<dyn Fn(f64) -> f64> = match norm {
+        ScoreNorm::None => return ranked,
+        ScoreNorm::Minmax => {
+            let min = scores.iter().copied().fold(f64::INFINITY, f64::min);
+            let range = max - min;
+            Box::new(move |s| if range > 0.0 { (s - min) / range } else { 1.0 })
+        }
+        ScoreNorm::Zscore => {
+            let mean = scores.iter().sum::<f64>() / scores.len().max(1) as f64;
+            let variance =
+                scores.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / scores.len().max(1) as f64;
+            let deviation = variance.sqrt();
+            Box::new(move |s| {
+                if deviation > 0.0 {
+                    (s - mean) / deviation
+                } else {
+                    0.0
+                }
+            })
+        }
+        ScoreNorm::Softmax => {
+            // shifted by the maximum so exp does not overflow
+            let sum = scores.iter().map(|s| (s - max).exp()).sum::<f64>();
+            Box::new(move |s| (s - max).exp() / sum)
+        }
+    };
+    for (_, score) in &mut ranked {
+        *score = scale(*score);
+    }
+    ranked
+}
@@ -0,0 +1,66 @@
+//! HTTP plumbing of the `serve` subcommand: responses, the probes
+//! answered while the model is loading and shutdown on SIGTERM
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// how long the server waits for a request before checking for a shutdown
+pub const POLL: Duration = Duration::from_millis(200);
+
+/// set once SIGTERM or SIGINT arrived
+pub fn shutdown_flag() -> io::Result<Arc<AtomicBool>> {
+    let flag = Arc::new(AtomicBool::new(false));
+    for signal in [signal_hook::consts::SIGTERM, signal_hook::consts::SIGINT] {
+        signal_hook::flag::register(signal, flag.clone())?;
+    }
+    Ok(flag)
+}
+
+pub fn respond(request: Request, status: u16, content_type: &str, body: String) {
+    let response = Response::from_string(body).with_status_code(status);
+    let response = match Header::from_bytes(&b"Content-Type"[..], content_type.as_bytes()) {
+        Ok(header) => response.with_header(header),
+        Err(_) => response,
+    };
+    if let Err(e) = request.respond(response) {
+        warn!(error = %e, "failed to respond");
+    }
+}
+
+/// the response to the probes, None for other requests
+pub fn probe(request: &Request, ready: bool) -> Option<(u16, &'static str)> {
+    let path = request.url().split('?').next().unwrap_or_default();
+    match (request.method(), path) {
+        (Method::Get, "/healthz") => Some((200, "ok\n")),
+        (Method::Get, "/readyz") if ready => Some((200, "ready\n")),
+        (Method::Get, "/readyz") => Some((503, "loading model\n")),
+        _ => None,
+    }
+}
+
+/// answers requests on another thread until ready or shutdown is set,
+/// the probes as usual and everything else as unavailable
+pub fn answer_while_loading(
+    server: Arc<Server>,
+    ready: Arc<AtomicBool>,
+    shutdown: Arc<AtomicBool>,
+) -> JoinHandle<()> {
+    thread::spawn(move || {
+        while !ready.load(Ordering::Relaxed) && !shutdown.load(Ordering::Relaxed) {
+            let request = match server.recv_timeout(POLL) {
+                Ok(Some(request)) => request,
+                Ok(None) => continue,
+                Err(e) => {
+                    warn!(error = %e, "failed to receive request");
+                    continue;
+                }
+            };
+            let (status, body) = probe(&request, false).unwrap_or((503, "loading model\n"));
+            respond(request, status, "text/plain", body.into());
+        }
+    })
+}
@@ -0,0 +1,107 @@
+use crate::fnv::Fnv;
+
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+
+/// the i-th of n deterministic slices of a corpus, written `i/n` with 1 <= i <= n.
+/// documents are assigned by a hash of their name, so every process agrees
+/// on the split regardless of directory listing order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+pub struct Shard {
+    index: u64,
+    count: u64,
+}
+
+impl Shard {
+    pub fn contains(&self, name: &str) -> bool {
+        let mut hash = Fnv::new();
+        hash.write(name.as_bytes());
+        hash.finish() % self.count == self.index - 1
+    }
+}
+
+impl FromStr for Shard {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (index, count) = s
+            .split_once('/')
+            .ok_or_else(|| format!("expected i/n, got {}", s))?;
+        let index: u64 = index.parse().map_err(|e| format!("shard index: {}", e))?;
+        let count: u64 = count.parse().map_err(|e| format!("shard count: {}", e))?;
+        if count == 0 || index == 0 || index > count {
+            return Err(format!(
+                "shard {} is not within 1/{}..{}/{}",
+                s, count, count, count
+            ));
+        }
+        Ok(Shard { index, count })
+    }
+}
+
+impl fmt::Display for Shard {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{}", self.index, self.count)
+    }
+}
+
+impl TryFrom<String> for Shard {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<Shard> for String {
+    fn from(shard: Shard) -> String {
+        shard.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shards_are_disjoint_and_cover_the_corpus() {
+        let names = (0..500).map(|i| format!("{}.abstr", i)).collect::<Vec<_>>();
+        for count in 1..=7 {
+            let shards = (1..=count)
+                .map(|i| format!("{}/{}", i, count).parse::<Shard>().unwrap())
+                .collect::<Vec<_>>();
+            let mut sizes = vec![0; shards.len()];
+            for name in &names {
+                let owners = shards.iter().filter(|s| s.contains(name)).count();
+                assert_eq!(owners, 1, "{} in {} shards of {}", name, owners, count);
+                let owner = shards.iter().position(|s| s.contains(name)).unwrap();
+                sizes[owner] += 1;
+            }
+            // the hash spreads the names, no shard is left empty
+            assert!(sizes.iter().all(|size| *size > 0), "{:?}", sizes);
+        }
+    }
+
+    #[test]
+    fn shards_outside_1_to_n_are_rejected() {
+        for shard in ["0/3", "4/3", "1/0", "0/0"] {
+            let err = shard.parse::<Shard>().unwrap_err();
+            assert!(err.contains("is not within"), "{}: {}", shard, err);
+        }
+        for shard in ["3", "a/3", "1/b", "-1/3"] {
+            assert!(shard.parse::<Shard>().is_err(), "{}", shard);
+        }
+    }
+
+    #[test]
+    fn shards_round_trip_through_their_text() {
+        let shard = "2/5".parse::<Shard>().unwrap();
+        assert_eq!(shard.to_string(), "2/5");
+        let json = serde_json::to_string(&shard).unwrap();
+        assert_eq!(json, "\"2/5\"");
+        assert_eq!(serde_json::from_str::<Shard>(&json).unwrap(), shard);
+        assert!(serde_json::from_str::<Shard>("\"6/5\"").is_err());
+    }
+}
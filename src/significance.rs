@@ -0,0 +1,139 @@
+//! paired significance of the differences between the measures of two extractors
+//! over the same documents, by an approximate randomization test
+use crate::results::DocumentResult;
+use crate::rng::Rng;
+
+use std::collections::HashMap;
+
+/// the markers of the significance levels, the first level a p-value is below
+pub const LEVELS: [(f64, &str); 2] = [(0.01, "†"), (0.05, "*")];
+
+/// two-sided p-values of the differences of each measure from the baseline
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct PValues {
+    pub precision: f64,
+    pub recall: f64,
+    pub f1: f64,
+}
+
+/// the p-values of the measures of candidate against those of baseline, over the
+/// documents both have and neither found degenerate. trials random sign flips
+/// of the differences, drawn from seed, are compared with the observed mean difference
+pub fn test(
+    baseline: &[DocumentResult],
+    candidate: &[DocumentResult],
+    trials: usize,
+    seed: u64,
+) -> PValues {
+    let baseline = baseline
+        .iter()
+        .filter(|m| m.degenerate.is_none())
+        .map(|m| (m.document.as_str(), m))
+        .collect::<HashMap<_, _>>();
+    let pairs = candidate
+        .iter()
+        .filter(|m| m.degenerate.is_none())
+        .filter_map(|m| Some((*baseline.get(m.document.as_str())?, m)))
+        .collect::<Vec<_>>();
+    let differences = |measure: fn(&DocumentResult) -> f64| {
+        pairs
+            .iter()
+            .map(|(b, c)| measure(c) - measure(b))
+            .filter(|d| d.is_finite())
+            .collect::<Vec<_>>()
+    };
+    PValues {
+        precision: randomization(&differences(|m| m.metrics.precision), trials, seed),
+        recall: randomization(&differences(|m| m.metrics.recall), trials, seed),
+        f1: randomization(&differences(|m| m.metrics.f1), trials, seed),
+    }
+}
+
+/// the share of sign flips of differences whose mean is at least as far from 0
+/// as the observed one, counting the observed one. seeded by --seed,
+/// so a report is reproducible
+fn randomization(differences: &[f64], trials: usize, seed: u64) -> f64 {
+    if differences.is_empty() {
+        return 1.0;
+    }
+    let observed = differences.iter().sum::<f64>().abs();
+    let mut rng = Rng::new(seed);
+    let mut extreme = 0;
+    for _ in 0..trials {
+        let flipped = differences
+            .iter()
+            .map(|d| if rng.next_u64() & 1 == 0 { *d } else { -d })
+            .sum::<f64>();
+        // tolerates the rounding of summing in another order
+        if flipped.abs() >= observed - 1e-12 {
+            extreme += 1;
+        }
+    }
+    (extreme + 1) as f64 / (trials + 1) as f64
+}
+
+/// the marker of p, empty if it is not significant
+pub fn marker(p: f64) -> &'static str {
+    LEVELS
+        .iter()
+        .find(|(level, _)| p < *level)
+        .map_or("", |(_, marker)| marker)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// results of documents d0, d1, .. with the given f1, and precision and recall alike
+    fn results(f1: &[f64]) -> Vec<DocumentResult> {
+        f1.iter()
+            .enumerate()
+            .map(|(i, f)| {
+                serde_json::from_value(serde_json::json!({
+                    "document": format!("d{}", i),
+                    "precision": f,
+                    "recall": f,
+                    "f1": f,
+                }))
+                .unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn identical_systems_are_not_significant() {
+        let measures = results(&[0.1, 0.4, 0.3, 0.9, 0.0, 0.6]);
+        let p = test(&measures, &measures, 1000, 7);
+        assert_eq!((p.precision, p.recall, p.f1), (1.0, 1.0, 1.0));
+        assert_eq!(marker(p.f1), "");
+    }
+
+    #[test]
+    fn separated_systems_are_significant() {
+        let baseline = results(&[0.1; 20]);
+        let candidate = results(&[0.6; 20]);
+        let p = test(&baseline, &candidate, 1000, 7);
+        // only the flips keeping every sign count, 2 in 2^20 of them
+        assert!(p.f1 < 0.01, "{}", p.f1);
+        assert_eq!(marker(p.f1), "†");
+    }
+
+    #[test]
+    fn the_seed_makes_p_values_reproducible() {
+        let differences = [0.2, -0.1, 0.3, 0.05, -0.2, 0.1, 0.15];
+        let p = randomization(&differences, 500, 42);
+        assert_eq!(p, randomization(&differences, 500, 42));
+        assert!(p > 0.0 && p <= 1.0);
+        assert_eq!(randomization(&[], 500, 42), 1.0);
+    }
+
+    #[test]
+    fn only_shared_documents_are_paired() {
+        let baseline = results(&[0.1, 0.1]);
+        let mut candidate = results(&[0.9, 0.9, 0.9]);
+        candidate.remove(0);
+        // d1 alone differs, so half of the flips are as extreme
+        let p = test(&baseline, &candidate, 1000, 3);
+        assert!(p.f1 > 0.3, "{}", p.f1);
+    }
+}
@@ -0,0 +1,464 @@
+//! where `--dataset` reads its documents from: a directory, a tar archive, gzipped
+//! or not, the `http://` URL of an index listing the URLs of the documents one per
+//! line or, with the `s3` feature, an `s3://bucket/prefix` of S3-compatible object
+//! storage. remote corpora are read document by document without mirroring them
+use crate::corpus::{self, Reading};
+use crate::http;
+use crate::HulthDocument;
+
+use flate2::read::GzDecoder;
+use memmap2::Mmap;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read};
+use std::ops::{Deref, Range};
+use std::path::{Path, PathBuf};
+
+/// the size of the header and data blocks of a tar archive
+const BLOCK: usize = 512;
+
+/// the documents of a dataset by name
+pub trait CorpusSource: fmt::Display + Send + Sync {
+    /// the names `read` takes in the order the documents are read: file names in
+    /// a directory, full paths in an archive, URLs of an index and keys under an
+    /// S3 prefix. the ids of the documents are derived from them when they are read
+    fn list(&self, reading: &Reading) -> io::Result<Vec<String>>;
+
+    /// the document listed as name
+    fn read(&self, name: &str, reading: &Reading) -> io::Result<HulthDocument>;
+
+    /// where the document listed as name comes from, for logs and `--id-map`
+    fn origin(&self, name: &str) -> String;
+}
+
+/// the source of dataset by its form, see the module documentation
+pub fn open(dataset: &Path) -> io::Result<Box<dyn CorpusSource>> {
+    let location = dataset.to_string_lossy();
+    if location.starts_with("http://") {
+        return Ok(Box::new(HttpIndex {
+            url: location.into_owned(),
+        }));
+    }
+    if let Some(location) = location.strip_prefix("s3://") {
+        #[cfg(feature = "s3")]
+        return Ok(Box::new(s3::S3::open(location)?));
+        #[cfg(not(feature = "s3"))]
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("s3://{}: reading from S3 needs the s3 feature", location),
+        ));
+    }
+    if dataset.is_file() {
+        return Ok(Box::new(Archive::open(dataset)?));
+    }
+    Ok(Box::new(Directory {
+        dir: dataset.into(),
+    }))
+}
+
+/// whether dataset is a directory, which some options need
+pub fn is_local(dataset: &Path) -> bool {
+    dataset.is_dir()
+}
+
+/// every file of a directory, not descending into subdirectories
+pub struct Directory {
+    dir: PathBuf,
+}
+
+impl fmt::Display for Directory {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.dir.display())
+    }
+}
+
+impl CorpusSource for Directory {
+    fn list(&self, reading: &Reading) -> io::Result<Vec<String>> {
+        let mut names = vec![];
+        corpus::for_each_file(&self.dir, reading, |path| {
+            names.push(
+                path.file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .into(),
+            );
+            Ok(())
+        })?;
+        Ok(names)
+    }
+
+    fn read(&self, name: &str, reading: &Reading) -> io::Result<HulthDocument> {
+        reading.read_document(&self.dir.join(name))
+    }
+
+    fn origin(&self, name: &str) -> String {
+        self.dir.join(name).display().to_string()
+    }
+}
+
+/// the `.json` files of a tar archive, kept in memory if gzipped and mapped otherwise
+pub struct Archive {
+    path: PathBuf,
+    data: Box<dyn Deref<Target = [u8]> + Send + Sync>,
+    /// the documents with their bytes in data
+    entries: Vec<(String, Range<usize>)>,
+    /// the entry of every name
+    index: HashMap<String, usize>,
+}
+
+impl Archive {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let file = File::open(path)?;
+        // SAFETY: the archive must not be modified while it's being read, like the dataset
+        let map = unsafe { Mmap::map(&file)? };
+        let data: Box<dyn Deref<Target = [u8]> + Send + Sync> = if map.starts_with(&[0x1f, 0x8b]) {
+            let mut tar = vec![];
+            GzDecoder::new(&map[..]).read_to_end(&mut tar)?;
+            Box::new(tar)
+        } else {
+            Box::new(map)
+        };
+        let entries = entries(&data).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: {}", path.display(), e),
+            )
+        })?;
+        debug!(path = %path.display(), documents = entries.len(), "read archive");
+        let index = entries
+            .iter()
+            .enumerate()
+            .map(|(i, (name, _))| (name.clone(), i))
+            .collect();
+        Ok(Archive {
+            path: path.into(),
+            data,
+            entries,
+            index,
+        })
+    }
+}
+
+impl fmt::Display for Archive {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.path.display())
+    }
+}
+
+impl CorpusSource for Archive {
+    fn list(&self, _: &Reading) -> io::Result<Vec<String>> {
+        Ok(self.entries.iter().map(|(name, _)| name.clone()).collect())
+    }
+
+    fn read(&self, name: &str, reading: &Reading) -> io::Result<HulthDocument> {
+        let range = self
+            .index
+            .get(name)
+            .map(|i| self.entries[*i].1.clone())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, self.origin(name)))?;
+        reading.read_bytes(&self.origin(name), &self.data[range])
+    }
+
+    fn origin(&self, name: &str) -> String {
+        format!("{}:{}", self.path.display(), name)
+    }
+}
+
+/// the regular `.json` files of a tar archive. long names of GNU tar and
+/// the paths of pax headers are read, other extensions are ignored
+fn entries(tar: &[u8]) -> Result<Vec<(String, Range<usize>)>, String> {
+    let mut entries = vec![];
+    let mut offset = 0;
+    // the name given to the next entry by a GNU or pax header
+    let mut long_name = None;
+    while offset + BLOCK <= tar.len() {
+        let header = &tar[offset..offset + BLOCK];
+        if header.iter().all(|b| *b == 0) {
+            break;
+        }
+        let size = octal(&header[124..136])
+            .ok_or_else(|| format!("invalid size of the entry at byte {}", offset))?;
+        let start = offset + BLOCK;
+        let end = start + size;
+        if end > tar.len() {
+            return Err(format!("entry at byte {} is cut off", offset));
+        }
+        let name = match long_name.take() {
+            Some(name) => name,
+            None => {
+                let mut name = field(&header[0..100]);
+                if &header[257..262] == b"ustar" && header[345] != 0 {
+                    name = format!("{}/{}", field(&header[345..500]), name);
+                }
+                name
+            }
+        };
+        match header[156] {
+            b'0' | 0 if name.ends_with(".json") => entries.push((name, start..end)),
+            b'L' => long_name = Some(field(&tar[start..end])),
+            b'x' => long_name = pax_path(&tar[start..end]),
+            _ => {}
+        }
+        offset = start + size.div_ceil(BLOCK) * BLOCK;
+    }
+    Ok(entries)
+}
+
+/// a NUL terminated field of a header
+fn field(bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end]).into_owned()
+}
+
+fn octal(bytes: &[u8]) -> Option<usize> {
+    let digits = field(bytes);
+    let digits = digits.trim_matches(|c: char| c == ' ' || c == '\0');
+    if digits.is_empty() {
+        return Some(0);
+    }
+    usize::from_str_radix(digits, 8).ok()
+}
+
+/// the `path` of pax records like `25 path=dir/document.json\n`
+fn pax_path(records: &[u8]) -> Option<String> {
+    String::from_utf8_lossy(records)
+        .lines()
+        .filter_map(|record| record.split_once(' '))
+        .find_map(|(_, record)| record.strip_prefix("path=").map(String::from))
+}
+
+/// an index listing the URLs of the documents one per line, relative ones
+/// resolved against the index. empty lines and lines starting with `#` are skipped
+pub struct HttpIndex {
+    url: String,
+}
+
+impl fmt::Display for HttpIndex {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.url)
+    }
+}
+
+impl CorpusSource for HttpIndex {
+    fn list(&self, reading: &Reading) -> io::Result<Vec<String>> {
+        let index = reading.retrying(Path::new(&self.url), || http::get(&self.url))?;
+        Ok(String::from_utf8_lossy(&index)
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| resolve(&self.url, line))
+            .collect())
+    }
+
+    fn read(&self, name: &str, reading: &Reading) -> io::Result<HulthDocument> {
+        let json = reading.retrying(Path::new(name), || http::get(name))?;
+        reading.read_bytes(name, &json)
+    }
+
+    fn origin(&self, name: &str) -> String {
+        name.into()
+    }
+}
+
+/// the URL of a line of the index at url: full URLs are kept, absolute paths
+/// are taken on the host of the index and relative ones next to it
+fn resolve(url: &str, line: &str) -> String {
+    let (base, host) = match url[7..].find('/') {
+        Some(i) => (&url[..url.rfind('/').unwrap_or_default()], &url[..7 + i]),
+        None => (url, url),
+    };
+    if line.starts_with("http://") {
+        line.to_string()
+    } else if line.starts_with('/') {
+        format!("{}{}", host, line)
+    } else {
+        format!("{}/{}", base, line)
+    }
+}
+
+#[cfg(feature = "s3")]
+mod s3 {
+    //! the `.json` objects under a prefix of a bucket. the region is `AWS_REGION`,
+    //! the endpoint of S3-compatible storage `AWS_ENDPOINT_URL` and the credentials
+    //! are taken from the environment or the AWS profile
+    use super::CorpusSource;
+    use crate::corpus::Reading;
+    use crate::HulthDocument;
+
+    use s3::creds::Credentials;
+    use s3::{Bucket, Region};
+
+    use std::env;
+    use std::fmt;
+    use std::io;
+    use std::path::Path;
+
+    pub struct S3 {
+        bucket: Box<Bucket>,
+        prefix: String,
+    }
+
+    impl S3 {
+        /// location is `bucket/prefix` of `s3://bucket/prefix`
+        pub fn open(location: &str) -> io::Result<Self> {
+            let (name, prefix) = location.split_once('/').unwrap_or((location, ""));
+            let region = env::var("AWS_REGION").unwrap_or_else(|_| "us-east-1".into());
+            let region = match env::var("AWS_ENDPOINT_URL") {
+                Ok(endpoint) => Region::Custom { region, endpoint },
+                Err(_) => region.parse().map_err(io::Error::other)?,
+            };
+            let credentials = Credentials::default().map_err(io::Error::other)?;
+            let bucket = Bucket::new(name, region, credentials)
+                .map_err(io::Error::other)?
+                .with_path_style();
+            Ok(S3 {
+                bucket,
+                prefix: prefix.into(),
+            })
+        }
+    }
+
+    impl fmt::Display for S3 {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "s3://{}/{}", self.bucket.name(), self.prefix)
+        }
+    }
+
+    impl CorpusSource for S3 {
+        fn list(&self, _: &Reading) -> io::Result<Vec<String>> {
+            let pages = self
+                .bucket
+                .list(self.prefix.clone(), None)
+                .map_err(io::Error::other)?;
+            Ok(pages
+                .into_iter()
+                .flat_map(|page| page.contents)
+                .map(|object| object.key)
+                .filter(|key| key.ends_with(".json"))
+                .collect())
+        }
+
+        fn read(&self, name: &str, reading: &Reading) -> io::Result<HulthDocument> {
+            let origin = self.origin(name);
+            let json = reading.retrying(Path::new(&origin), || {
+                let response = self.bucket.get_object(name).map_err(io::Error::other)?;
+                match response.status_code() {
+                    200 => Ok(response.bytes().to_vec()),
+                    status => Err(io::Error::other(format!("{} answered {}", origin, status))),
+                }
+            })?;
+            reading.read_bytes(&origin, &json)
+        }
+
+        fn origin(&self, name: &str) -> String {
+            format!("s3://{}/{}", self.bucket.name(), name)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+
+    use std::io::Write;
+
+    /// a tar entry of type kind named name, its data padded to whole blocks
+    fn entry(name: &str, kind: u8, data: &[u8]) -> Vec<u8> {
+        let mut header = vec![0u8; BLOCK];
+        header[..name.len()].copy_from_slice(name.as_bytes());
+        let size = format!("{:011o}\0", data.len());
+        header[124..136].copy_from_slice(size.as_bytes());
+        header[156] = kind;
+        let mut entry = header;
+        entry.extend_from_slice(data);
+        entry.resize(entry.len().div_ceil(BLOCK) * BLOCK, 0);
+        entry
+    }
+
+    fn archive(entries: &[Vec<u8>]) -> Vec<u8> {
+        let mut tar = entries.concat();
+        tar.extend_from_slice(&[0; 2 * BLOCK]);
+        tar
+    }
+
+    fn names(tar: &[u8]) -> Vec<String> {
+        entries(tar)
+            .unwrap()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect()
+    }
+
+    #[test]
+    fn archives_list_their_json_files() {
+        let tar = archive(&[
+            entry("docs/1.json", b'0', b"{}"),
+            entry("docs/README", b'0', b"not a document"),
+            entry("docs", b'5', b""),
+        ]);
+        let entries = entries(&tar).unwrap();
+        assert_eq!(entries.len(), 1);
+        let (name, range) = &entries[0];
+        assert_eq!(name, "docs/1.json");
+        assert_eq!(&tar[range.clone()], b"{}");
+    }
+
+    #[test]
+    fn long_names_come_from_the_preceding_header() {
+        let long = format!("{}/1.json", "nested".repeat(20));
+        let mut gnu = long.clone().into_bytes();
+        gnu.push(0);
+        let pax = format!("{} path={}\n", long.len() + 10, long);
+        let tar = archive(&[
+            entry("././@LongLink", b'L', &gnu),
+            entry("truncated-gnu", b'0', b"{}"),
+            entry("PaxHeaders/2", b'x', pax.as_bytes()),
+            entry("truncated-pax", b'0', b"{}"),
+        ]);
+        assert_eq!(names(&tar), [long.clone(), long]);
+    }
+
+    #[test]
+    fn cut_off_archives_are_rejected() {
+        let tar = entry("1.json", b'0', &[b' '; 600]);
+        assert!(entries(&tar[..BLOCK + 100]).is_err());
+        let mut corrupt = entry("1.json", b'0', b"{}");
+        corrupt[124..136].copy_from_slice(b"not octal\0\0\0");
+        assert!(entries(&corrupt).is_err());
+    }
+
+    #[test]
+    fn gzipped_archives_are_read() {
+        let tar = archive(&[entry("1.json", b'0', b"{\"sentences\": []}")]);
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("dataset.tar.gz");
+        let mut gz = GzEncoder::new(File::create(&path).unwrap(), Compression::default());
+        gz.write_all(&tar).unwrap();
+        gz.finish().unwrap();
+        let archive = Archive::open(&path).unwrap();
+        let reading = Reading::default();
+        assert_eq!(archive.list(&reading).unwrap(), ["1.json"]);
+        let doc = archive.read("1.json", &reading).unwrap();
+        assert!(doc.sentences.is_empty());
+    }
+
+    #[test]
+    fn index_lines_resolve_against_the_index() {
+        let url = "http://corpus:8080/hulth/index.txt";
+        assert_eq!(
+            resolve(url, "test/1.json"),
+            "http://corpus:8080/hulth/test/1.json"
+        );
+        assert_eq!(
+            resolve(url, "/other/1.json"),
+            "http://corpus:8080/other/1.json"
+        );
+        assert_eq!(resolve(url, "http://mirror/1.json"), "http://mirror/1.json");
+        assert_eq!(resolve("http://corpus", "1.json"), "http://corpus/1.json");
+    }
+}
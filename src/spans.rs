@@ -0,0 +1,197 @@
+//! predictions as character spans of the input, for annotation and highlighting tools
+use crate::HulthDocument;
+
+use std::collections::{BTreeMap, HashMap};
+
+/// one occurrence of a predicted keyword, end is exclusive
+#[derive(Debug, Serialize)]
+pub struct Span {
+    pub(crate) start: i64,
+    pub(crate) end: i64,
+    pub(crate) text: String,
+    pub(crate) score: f64,
+}
+
+/// every occurrence of the keywords in ranked, the preprocessed form of original.
+/// a span must start and end at the offsets of tokens of original, and if the
+/// source text is known the text at the offsets must be the words of those tokens.
+/// spans failing either check are left out with a warning
+pub fn spans(
+    original: &HulthDocument,
+    source: Option<&str>,
+    ranked: &HulthDocument,
+    keywords: &[(String, f64)],
+) -> Vec<Span> {
+    let words = words(original);
+    let scores = keywords
+        .iter()
+        .map(|(term, score)| (term.as_str(), *score))
+        .collect::<BTreeMap<_, _>>();
+
+    let mut spans = vec![];
+    for token in ranked.sentences.iter().flat_map(|s| s.tokens.iter()) {
+        let score = match scores.get(&*token.word) {
+            Some(score) => *score,
+            None => continue,
+        };
+        let (start, end) = (token.offset_begin, token.offset_end);
+        let text =
+            covered(&words, start, end).and_then(|covered| text(source, start, end, &covered));
+        match text {
+            Some(text) => spans.push(Span {
+                start,
+                end,
+                text,
+                score,
+            }),
+            None => warn!(
+                term = %token.word,
+                start,
+                end,
+                "offsets do not match the document, leaving out span"
+            ),
+        }
+    }
+    spans.sort_by_key(|s| (s.start, s.end));
+    spans
+}
+
+/// the most frequent surface form of every term of preprocessed, the form of original,
+/// as the words its occurrences cover joined by spaces. of equally frequent forms the
+/// first is taken, terms whose offsets don't match original are left out
+pub fn surface_forms(
+    original: &HulthDocument,
+    preprocessed: &HulthDocument,
+) -> HashMap<String, String> {
+    let words = words(original);
+    // the count and first occurrence of every form of every term
+    let mut counts = HashMap::<&str, HashMap<String, (usize, usize)>>::new();
+    let tokens = preprocessed.sentences.iter().flat_map(|s| s.tokens.iter());
+    for (i, token) in tokens.enumerate() {
+        let covered = match covered(&words, token.offset_begin, token.offset_end) {
+            Some(covered) => covered,
+            None => continue,
+        };
+        let form = covered.iter().map(|(_, (_, w))| *w).collect::<Vec<_>>();
+        let entry = counts
+            .entry(&token.word)
+            .or_default()
+            .entry(form.join(" "))
+            .or_insert((0, i));
+        entry.0 += 1;
+    }
+    counts
+        .into_iter()
+        .filter_map(|(term, forms)| {
+            let (form, _) = forms
+                .into_iter()
+                .max_by(|(_, (a, i)), (_, (b, j))| a.cmp(b).then(j.cmp(i)))?;
+            Some((term.to_string(), form))
+        })
+        .collect()
+}
+
+/// the tokens of original by their offsets, with their end and word
+fn words(original: &HulthDocument) -> BTreeMap<i64, (i64, &str)> {
+    original
+        .sentences
+        .iter()
+        .flat_map(|s| s.tokens.iter())
+        .map(|t| (t.offset_begin, (t.offset_end, &*t.word)))
+        .collect()
+}
+
+/// the tokens of words from start to end, None unless they start and end there
+fn covered<'a>(
+    words: &'a BTreeMap<i64, (i64, &'a str)>,
+    start: i64,
+    end: i64,
+) -> Option<Vec<(&'a i64, &'a (i64, &'a str))>> {
+    let covered = words.range(start..end).collect::<Vec<_>>();
+    match (covered.first(), covered.last()) {
+        (Some((first, _)), Some((_, (last_end, _)))) if **first == start && *last_end == end => {
+            Some(covered)
+        }
+        _ => None,
+    }
+}
+
+/// the text of the span from source, checked against the covered words,
+/// or the covered words joined by spaces without a source
+fn text(
+    source: Option<&str>,
+    start: i64,
+    end: i64,
+    covered: &[(&i64, &(i64, &str))],
+) -> Option<String> {
+    let source = match source {
+        Some(source) => source,
+        None => {
+            let words = covered.iter().map(|(_, (_, w))| *w).collect::<Vec<_>>();
+            return Some(words.join(" "));
+        }
+    };
+    let slice = source.get(start as usize..end as usize)?;
+    for (begin, (word_end, word)) in covered {
+        let at = (**begin - start) as usize..(*word_end - start) as usize;
+        if slice.get(at)? != *word {
+            return None;
+        }
+    }
+    Some(slice.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{HulthToken, Sentence};
+
+    /// a document of one sentence of words with their offsets
+    fn document(words: &[(&str, i64, i64)]) -> HulthDocument {
+        let tokens = words
+            .iter()
+            .map(|(word, begin, end)| HulthToken {
+                word: (*word).into(),
+                lemma: (*word).into(),
+                offset_begin: *begin,
+                offset_end: *end,
+                pos: "".into(),
+            })
+            .collect();
+        HulthDocument {
+            sentences: vec![Sentence { tokens }],
+        }
+    }
+
+    #[test]
+    fn surface_forms_take_the_most_frequent_form() {
+        let original = document(&[
+            ("Networks", 0, 8),
+            ("networks", 9, 17),
+            ("networks", 18, 26),
+            ("Model", 27, 32),
+            ("model", 33, 38),
+        ]);
+        let preprocessed = document(&[
+            ("network", 0, 8),
+            ("network", 9, 17),
+            ("network", 18, 26),
+            ("model", 27, 32),
+            ("model", 33, 38),
+        ]);
+        let forms = surface_forms(&original, &preprocessed);
+        assert_eq!(forms["network"], "networks");
+        // equally frequent, the first is taken
+        assert_eq!(forms["model"], "Model");
+    }
+
+    #[test]
+    fn surface_forms_join_the_covered_words() {
+        let original = document(&[("Support", 0, 7), ("Vector", 8, 14), ("Machines", 15, 23)]);
+        let preprocessed = document(&[("support vector machin", 0, 23), ("vector", 9, 14)]);
+        let forms = surface_forms(&original, &preprocessed);
+        assert_eq!(forms["support vector machin"], "Support Vector Machines");
+        // offsets not starting at a token of original
+        assert!(!forms.contains_key("vector"));
+    }
+}
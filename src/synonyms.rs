@@ -0,0 +1,218 @@
+//! synonym classes for `--match-mode synonym`, read from a TSV file of one class
+//! per line or from the `wn_s.pl` of the Prolog export of WordNet, whose synsets
+//! are the classes. a ranked term matches a reference term of one of its classes
+use crate::preprocess::Normalizer;
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+
+/// the classes as read from the file
+#[derive(Debug, Default)]
+pub struct SynonymFile {
+    classes: Vec<Vec<String>>,
+}
+
+impl SynonymFile {
+    /// a file ending in `.pl` is read as WordNet, any other as TSV
+    pub fn read(path: &Path) -> io::Result<Self> {
+        let lines = BufReader::new(File::open(path)?).lines();
+        let classes = if path.extension().is_some_and(|e| e == "pl") {
+            wordnet(lines)?
+        } else {
+            tsv(lines)?
+        };
+        debug!(path = %path.display(), classes = classes.len(), "read synonyms");
+        Ok(SynonymFile { classes })
+    }
+
+    /// the classes with their entries normalized as the reference keyphrases are,
+    /// entries normalizer drops completely are left out
+    pub fn normalize(&self, normalizer: &Normalizer) -> Synonyms {
+        let mut classes = vec![];
+        let mut index = HashMap::<_, Vec<_>>::new();
+        for class in &self.classes {
+            let mut terms = class
+                .iter()
+                .map(|entry| normalizer.reference_words(entry).join(" "))
+                .filter(|term| !term.is_empty())
+                .collect::<Vec<_>>();
+            terms.sort_unstable();
+            terms.dedup();
+            if terms.len() < 2 {
+                continue;
+            }
+            for term in &terms {
+                index.entry(term.clone()).or_default().push(classes.len());
+            }
+            classes.push(terms);
+        }
+        Synonyms { classes, index }
+    }
+}
+
+/// one class per line, its entries separated by tabs. empty lines
+/// and lines starting with `#` are skipped
+fn tsv<I: Iterator<Item = io::Result<String>>>(lines: I) -> io::Result<Vec<Vec<String>>> {
+    let mut classes = vec![];
+    for line in lines {
+        let line = line?;
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        classes.push(
+            line.split('\t')
+                .map(str::trim)
+                .filter(|entry| !entry.is_empty())
+                .map(String::from)
+                .collect(),
+        );
+    }
+    Ok(classes)
+}
+
+/// facts like `s(102958343,1,'car',n,1,0).`, the words of a synset id
+/// form a class. the quotes of a word are escaped by doubling them
+fn wordnet<I: Iterator<Item = io::Result<String>>>(lines: I) -> io::Result<Vec<Vec<String>>> {
+    let mut synsets = BTreeMap::<_, Vec<_>>::new();
+    for (n, line) in lines.enumerate() {
+        let line = line?;
+        let fact = match line.strip_prefix("s(") {
+            Some(fact) => fact,
+            None => continue,
+        };
+        let malformed = || {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("line {}: expected s(synset,number,'word',...)", n + 1),
+            )
+        };
+        let (id, rest) = fact.split_once(',').ok_or_else(malformed)?;
+        let (_, rest) = rest.split_once(",'").ok_or_else(malformed)?;
+        let mut word = String::new();
+        let mut chars = rest.chars();
+        loop {
+            match chars.next().ok_or_else(malformed)? {
+                '\'' if chars.as_str().starts_with('\'') => {
+                    chars.next();
+                    word.push('\'');
+                }
+                '\'' => break,
+                c => word.push(c),
+            }
+        }
+        synsets.entry(id.to_string()).or_default().push(word);
+    }
+    Ok(synsets.into_values().collect())
+}
+
+/// the normalized classes
+#[derive(Debug, Clone, Default)]
+pub struct Synonyms {
+    classes: Vec<Vec<String>>,
+    /// the classes of every term
+    index: HashMap<String, Vec<usize>>,
+}
+
+impl Synonyms {
+    /// the other terms of the classes of term
+    pub fn of<'a>(&'a self, term: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.index
+            .get(term)
+            .into_iter()
+            .flatten()
+            .flat_map(move |class| &self.classes[*class])
+            .map(String::as_str)
+            .filter(move |synonym| *synonym != term)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::preprocess::Preprocessing;
+
+    use std::fs;
+
+    fn lines(text: &str) -> impl Iterator<Item = io::Result<String>> + '_ {
+        text.lines().map(|line| Ok(line.to_string()))
+    }
+
+    fn synonyms(classes: &[&[&str]], preprocessing: Preprocessing) -> Synonyms {
+        let file = SynonymFile {
+            classes: classes
+                .iter()
+                .map(|class| class.iter().map(|e| e.to_string()).collect())
+                .collect(),
+        };
+        file.normalize(&preprocessing.normalizer().unwrap())
+    }
+
+    #[test]
+    fn tsv_has_a_class_per_line() {
+        let classes = tsv(lines("# cars\n\ncar\tautomobile\t auto \nnet\t\tnetwork\n")).unwrap();
+        assert_eq!(
+            classes,
+            vec![vec!["car", "automobile", "auto"], vec!["net", "network"]]
+        );
+    }
+
+    #[test]
+    fn wordnet_groups_the_words_of_a_synset() {
+        let text = "s(102,1,'hour',n,1,0).\n\
+                    g(102,'a period of time').\n\
+                    s(101,1,'car',n,1,0).\n\
+                    s(102,2,'o''clock',n,1,0).\n\
+                    s(101,2,'motor car',n,1,0).\n";
+        let classes = wordnet(lines(text)).unwrap();
+        assert_eq!(
+            classes,
+            vec![vec!["car", "motor car"], vec!["hour", "o'clock"]]
+        );
+        let err = wordnet(lines("s(101,1,'car',n,1,0).\ns(101,2,'auto")).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().starts_with("line 2:"), "{}", err);
+    }
+
+    #[test]
+    fn the_extension_picks_the_format() {
+        let dir = tempfile::tempdir().unwrap();
+        let (pl, tsv) = (dir.path().join("wn_s.pl"), dir.path().join("synonyms.tsv"));
+        fs::write(&pl, "s(101,1,'car',n,1,0).\ns(101,2,'auto',n,1,0).\n").unwrap();
+        fs::write(&tsv, "car\tauto\n").unwrap();
+        let (pl, tsv) = (
+            SynonymFile::read(&pl).unwrap(),
+            SynonymFile::read(&tsv).unwrap(),
+        );
+        assert_eq!(pl.classes, tsv.classes);
+    }
+
+    #[test]
+    fn entries_normalizing_alike_merge() {
+        let preprocessing = Preprocessing {
+            stem: true,
+            stopwords: true,
+            ..Preprocessing::default()
+        };
+        // cars and car merge, leaving the first class a single term. the stopword is dropped
+        let synonyms = synonyms(
+            &[&["cars", "car", "the"], &["Networks", "net"]],
+            preprocessing,
+        );
+        assert_eq!(synonyms.classes, vec![vec!["net", "network"]]);
+        assert_eq!(synonyms.of("car").count(), 0);
+        assert_eq!(synonyms.of("network").collect::<Vec<_>>(), ["net"]);
+    }
+
+    #[test]
+    fn a_term_of_two_classes_matches_both() {
+        let synonyms = synonyms(
+            &[&["bank", "shore"], &["bank", "lender"]],
+            Preprocessing::default(),
+        );
+        assert_eq!(synonyms.of("bank").collect::<Vec<_>>(), ["shore", "lender"]);
+        assert_eq!(synonyms.of("lender").collect::<Vec<_>>(), ["bank"]);
+        assert_eq!(synonyms.of("unknown").count(), 0);
+    }
+}
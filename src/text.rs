@@ -0,0 +1,50 @@
+use crate::{HulthDocument, HulthToken, Sentence};
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use std::io;
+
+/// parses input as Hulth JSON if it looks like a JSON object,
+/// otherwise tokenizes it as raw text
+pub fn parse_document(input: &str) -> io::Result<HulthDocument> {
+    if is_json(input) {
+        return Ok(serde_json::from_str(input)?);
+    }
+    Ok(document_from_text(input))
+}
+
+pub fn is_json(input: &str) -> bool {
+    input.trim_start().starts_with('{')
+}
+
+/// builds a document from raw text, split into sentences and words by the
+/// Unicode text segmentation of UAX #29 so that words such as `Straße`,
+/// `aujourd'hui` or words with combining accents stay whole in any language.
+/// segments without letters or digits are left out.
+/// offsets are byte offsets into text, the lemma is the lowercased word
+/// and the pos tag is left empty
+pub fn document_from_text(text: &str) -> HulthDocument {
+    let sentences = text
+        .split_sentence_bound_indices()
+        .map(|(start, sentence)| {
+            let tokens = sentence
+                .split_word_bound_indices()
+                .filter(|(_, word)| word.chars().any(char::is_alphanumeric))
+                .map(|(i, word)| token(word, start + i))
+                .collect::<Vec<_>>();
+            Sentence { tokens }
+        })
+        .filter(|sentence| !sentence.tokens.is_empty())
+        .collect();
+    HulthDocument { sentences }
+}
+
+fn token(word: &str, offset: usize) -> HulthToken {
+    HulthToken {
+        word: word.into(),
+        lemma: word.to_lowercase().into(),
+        offset_begin: offset as i64,
+        offset_end: (offset + word.len()) as i64,
+        pos: "".into(),
+    }
+}
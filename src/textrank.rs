@@ -0,0 +1,236 @@
+//! TextRank, PageRank over the graph of the words of a document
+//! connecting those that co-occur within a window of a sentence
+use crate::phrases;
+use crate::HulthDocument;
+
+use clap::Args;
+
+use std::collections::HashMap;
+use std::io;
+
+#[derive(Debug, Clone, PartialEq, Args, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TextRank {
+    /// words at most this many positions apart in a sentence are connected,
+    /// 2 connects neighbors
+    #[arg(long = "textrank-window", default_value_t = 2)]
+    pub window: usize,
+
+    /// the probability of following an edge rather than jumping to any word
+    #[arg(long = "textrank-damping", default_value_t = 0.85)]
+    pub damping: f64,
+
+    /// the most iterations of PageRank
+    #[arg(long = "textrank-iterations", default_value_t = 100)]
+    pub iterations: usize,
+
+    /// PageRank stops once no score changes by more than this
+    #[arg(long = "textrank-epsilon", default_value_t = 1e-4)]
+    pub epsilon: f64,
+}
+
+impl Default for TextRank {
+    fn default() -> Self {
+        TextRank {
+            window: 2,
+            damping: 0.85,
+            iterations: 100,
+            epsilon: 1e-4,
+        }
+    }
+}
+
+impl TextRank {
+    /// fails on options PageRank can't run with
+    pub fn check(&self) -> io::Result<()> {
+        let problem = if self.window < 2 {
+            "--textrank-window must be at least 2"
+        } else if !(0f64..=1f64).contains(&self.damping) {
+            "--textrank-damping must be between 0 and 1"
+        } else if self.iterations == 0 {
+            "--textrank-iterations must be at least 1"
+        } else if !(self.epsilon.is_finite() && self.epsilon >= 0f64) {
+            "--textrank-epsilon must be a number of at least 0"
+        } else {
+            return Ok(());
+        };
+        Err(io::Error::new(io::ErrorKind::InvalidInput, problem))
+    }
+
+    /// the candidates scored by TextRank, a phrase by the sum of the scores of its
+    /// words. candidates whose words are not in doc score 0
+    pub fn rescore(
+        &self,
+        doc: &HulthDocument,
+        candidates: Vec<(String, f64)>,
+    ) -> Vec<(String, f64)> {
+        let scores = self.scores(doc);
+        let score = |word: &str| scores.get(word).copied().unwrap_or_default();
+        candidates
+            .into_iter()
+            .map(|(term, _)| {
+                let s = term.split(' ').map(score).sum();
+                (term, s)
+            })
+            .collect()
+    }
+
+    /// the TextRank score of every word of doc
+    pub fn scores<'a>(&self, doc: &'a HulthDocument) -> HashMap<&'a str, f64> {
+        let mut ids = HashMap::new();
+        let mut words = vec![];
+        let mut weights = HashMap::<(usize, usize), f64>::new();
+        for sentence in &doc.sentences {
+            let sentence = sentence
+                .tokens
+                .iter()
+                .filter(|t| !phrases::is_phrase(&t.word))
+                .map(|t| {
+                    *ids.entry(&*t.word).or_insert_with(|| {
+                        words.push(&*t.word);
+                        words.len() - 1
+                    })
+                })
+                .collect::<Vec<_>>();
+            for (i, a) in sentence.iter().enumerate() {
+                for b in sentence
+                    .iter()
+                    .skip(i + 1)
+                    .take(self.window.saturating_sub(1))
+                {
+                    if a != b {
+                        *weights.entry((*a, *b)).or_default() += 1f64;
+                        *weights.entry((*b, *a)).or_default() += 1f64;
+                    }
+                }
+            }
+        }
+        let n = words.len();
+        let mut strength = vec![0f64; n];
+        let mut edges = vec![vec![]; n];
+        for ((from, to), weight) in weights {
+            strength[from] += weight;
+            edges[to].push((from, weight));
+        }
+        // summed in a fixed order so runs agree to the last bit
+        for incoming in &mut edges {
+            incoming.sort_unstable_by_key(|(from, _)| *from);
+        }
+        let mut scores = vec![1f64; n];
+        for iteration in 0..self.iterations {
+            let next = edges
+                .iter()
+                .map(|incoming| {
+                    let rank = incoming
+                        .iter()
+                        .map(|(from, weight)| weight / strength[*from] * scores[*from])
+                        .sum::<f64>();
+                    1f64 - self.damping + self.damping * rank
+                })
+                .collect::<Vec<_>>();
+            let change = next
+                .iter()
+                .zip(&scores)
+                .map(|(a, b)| (a - b).abs())
+                .fold(0f64, f64::max);
+            scores = next;
+            if change <= self.epsilon {
+                trace!(iterations = iteration + 1, words = n, "textrank converged");
+                break;
+            }
+        }
+        words.into_iter().zip(scores).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{HulthToken, Sentence};
+
+    fn document(sentences: &[&[&str]]) -> HulthDocument {
+        let sentences = sentences
+            .iter()
+            .map(|words| Sentence {
+                tokens: words
+                    .iter()
+                    .map(|word| HulthToken {
+                        word: (*word).into(),
+                        lemma: (*word).into(),
+                        offset_begin: 0,
+                        offset_end: 0,
+                        pos: "".into(),
+                    })
+                    .collect(),
+            })
+            .collect();
+        HulthDocument { sentences }
+    }
+
+    fn converged(window: usize) -> TextRank {
+        TextRank {
+            window,
+            iterations: 1000,
+            epsilon: 1e-12,
+            ..TextRank::default()
+        }
+    }
+
+    #[test]
+    fn the_center_of_a_star_ranks_first() {
+        let doc = document(&[&["a", "b"], &["a", "c"], &["a", "d"]]);
+        let scores = converged(2).scores(&doc);
+        // x = 0.15 + 0.85 * 3y and y = 0.15 + 0.85 * x / 3
+        let leaf = 0.1925 / 0.2775;
+        let center = 0.15 + 2.55 * leaf;
+        assert!((scores["a"] - center).abs() < 1e-9, "{:?}", scores);
+        for leaf_word in ["b", "c", "d"] {
+            assert!((scores[leaf_word] - leaf).abs() < 1e-9, "{:?}", scores);
+        }
+        // the scores of a connected graph sum to its number of words
+        assert!((scores.values().sum::<f64>() - 4.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn only_words_within_the_window_are_connected() {
+        let doc = document(&[&["a", "b", "c"]]);
+        let chain = converged(2).scores(&doc);
+        assert!(chain["b"] > chain["a"]);
+        assert!((chain["a"] - chain["c"]).abs() < 1e-9);
+        let triangle = converged(3).scores(&doc);
+        for word in ["a", "b", "c"] {
+            assert!((triangle[word] - 1.0).abs() < 1e-9, "{:?}", triangle);
+        }
+    }
+
+    #[test]
+    fn options_pagerank_cant_run_with_are_rejected() {
+        let invalid = [
+            TextRank {
+                window: 1,
+                ..TextRank::default()
+            },
+            TextRank {
+                damping: 1.5,
+                ..TextRank::default()
+            },
+            TextRank {
+                damping: f64::NAN,
+                ..TextRank::default()
+            },
+            TextRank {
+                iterations: 0,
+                ..TextRank::default()
+            },
+            TextRank {
+                epsilon: -1.0,
+                ..TextRank::default()
+            },
+        ];
+        for textrank in invalid {
+            let err = textrank.check().unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput, "{:?}", textrank);
+        }
+        assert!(TextRank::default().check().is_ok());
+    }
+}
@@ -0,0 +1,112 @@
+//! live dashboard for long evaluation runs, enabled with `eval --tui`
+use crate::cmp_f64;
+use crate::results::DocumentResult;
+
+use ratatui::layout::{Constraint, Layout};
+use ratatui::widgets::{Block, Gauge, Paragraph, Row, Table};
+use ratatui::DefaultTerminal;
+
+use std::io;
+use std::time::{Duration, Instant};
+
+const WORST_SHOWN: usize = 10;
+const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+
+pub struct Dashboard {
+    terminal: DefaultTerminal,
+    total: usize,
+    done: usize,
+    precision_sum: f64,
+    recall_sum: f64,
+    f1_sum: f64,
+    /// the documents with the lowest f1 so far, worst first
+    worst: Vec<(String, f64)>,
+    last_draw: Option<Instant>,
+}
+
+impl Dashboard {
+    /// switches the terminal into the dashboard,
+    /// it is restored when the dashboard is dropped
+    pub fn start(total: usize) -> io::Result<Self> {
+        let mut dashboard = Dashboard {
+            terminal: ratatui::try_init()?,
+            total,
+            done: 0,
+            precision_sum: 0f64,
+            recall_sum: 0f64,
+            f1_sum: 0f64,
+            worst: vec![],
+            last_draw: None,
+        };
+        dashboard.draw()?;
+        Ok(dashboard)
+    }
+
+    pub fn update(&mut self, measure: &DocumentResult) -> io::Result<()> {
+        self.done += 1;
+        self.precision_sum += measure.metrics.precision;
+        self.recall_sum += measure.metrics.recall;
+        self.f1_sum += measure.metrics.f1;
+        self.worst
+            .push((measure.document.clone(), measure.metrics.f1));
+        self.worst.sort_by(|a, b| cmp_f64(b.1, a.1));
+        self.worst.truncate(WORST_SHOWN);
+
+        let due = self
+            .last_draw
+            .is_none_or(|at| at.elapsed() >= REDRAW_INTERVAL);
+        if due || self.done == self.total {
+            self.draw()?;
+        }
+        Ok(())
+    }
+
+    fn draw(&mut self) -> io::Result<()> {
+        let ratio = if self.total == 0 {
+            1f64
+        } else {
+            (self.done as f64 / self.total as f64).min(1f64)
+        };
+        let progress = Gauge::default()
+            .block(Block::bordered().title("progress"))
+            .ratio(ratio)
+            .label(format!("{}/{}", self.done, self.total));
+
+        let n = self.done.max(1) as f64;
+        let means = Paragraph::new(vec![
+            format!("precision {:.4}", self.precision_sum / n).into(),
+            format!("recall    {:.4}", self.recall_sum / n).into(),
+            format!("f1        {:.4}", self.f1_sum / n).into(),
+        ])
+        .block(Block::bordered().title("running means"));
+
+        let rows = self
+            .worst
+            .iter()
+            .map(|(name, f1)| Row::new(vec![name.clone(), format!("{:.4}", f1)]))
+            .collect::<Vec<_>>();
+        let worst = Table::new(rows, [Constraint::Min(10), Constraint::Length(8)])
+            .header(Row::new(vec!["document", "f1"]))
+            .block(Block::bordered().title("worst documents"));
+
+        self.terminal.draw(|frame| {
+            let [top, middle, bottom] = Layout::vertical([
+                Constraint::Length(3),
+                Constraint::Length(5),
+                Constraint::Min(0),
+            ])
+            .areas(frame.area());
+            frame.render_widget(progress, top);
+            frame.render_widget(means, middle);
+            frame.render_widget(worst, bottom);
+        })?;
+        self.last_draw = Some(Instant::now());
+        Ok(())
+    }
+}
+
+impl Drop for Dashboard {
+    fn drop(&mut self) {
+        ratatui::restore();
+    }
+}
@@ -0,0 +1,603 @@
+//! word vectors for the embedding based matchers and rerankers. GloVe and
+//! word2vec files are read into a compact store, the words sorted with one
+//! row of f32 or i8 quantized components each, that `convert-vectors` writes
+//! to a file which is memory-mapped instead of read. looked up vectors are
+//! decoded into an LRU cache
+use clap::ValueEnum;
+use memmap2::Mmap;
+
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryFrom;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader};
+use std::ops::Deref;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+const MAGIC: &[u8; 8] = b"HVEC\0\0\0\x01";
+/// magic, count, dim, precision and the length of the words
+const HEADER: usize = 32;
+/// offset and length of a word
+const ENTRY: usize = 12;
+/// the most entries of a word2vec file allocated before they are read
+const PREALLOCATED: usize = 1 << 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum VectorFormat {
+    /// by the file: `.bin` is word2vec binary, `.hvec` a store,
+    /// text with a `<count> <dim>` header word2vec and any other text GloVe
+    Auto,
+    /// lines of a word and its components separated by spaces
+    Glove,
+    /// GloVe lines after a `<count> <dim>` header line
+    Word2vec,
+    /// a `<count> <dim>` header line, then every word followed by a space
+    /// and its components as little endian f32
+    Word2vecBinary,
+    /// a store written by `convert-vectors`, memory-mapped
+    Store,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Precision {
+    /// 4 bytes per component, as read
+    F32,
+    /// 1 byte per component, scaled per vector so its largest component is ±127
+    I8,
+}
+
+enum Bytes {
+    Owned(Vec<u8>),
+    Mapped(Mmap),
+}
+
+impl Deref for Bytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            Bytes::Owned(bytes) => bytes,
+            Bytes::Mapped(map) => map,
+        }
+    }
+}
+
+pub struct Vectors {
+    bytes: Bytes,
+    count: usize,
+    dim: usize,
+    precision: Precision,
+    words: usize,
+    rows: usize,
+    cache: Mutex<Lru>,
+}
+
+impl fmt::Debug for Vectors {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Vectors")
+            .field("count", &self.count)
+            .field("dim", &self.dim)
+            .field("precision", &self.precision)
+            .finish()
+    }
+}
+
+impl Vectors {
+    /// reads the vectors at path, keeping up to cache decoded vectors.
+    /// precision is that of a store converted from other formats
+    pub fn open(
+        path: &Path,
+        format: VectorFormat,
+        precision: Precision,
+        cache: usize,
+    ) -> io::Result<Self> {
+        let format = match format {
+            VectorFormat::Auto => detect(path)?,
+            format => format,
+        };
+        let bytes = match format {
+            VectorFormat::Store => {
+                let file = File::open(path)?;
+                // SAFETY: stores are written once by `convert-vectors` and not changed while read
+                Bytes::Mapped(unsafe { Mmap::map(&file)? })
+            }
+            VectorFormat::Word2vecBinary => {
+                let entries = read_binary(BufReader::new(File::open(path)?))?;
+                Bytes::Owned(encode(entries, precision))
+            }
+            _ => {
+                let reader = BufReader::new(File::open(path)?);
+                let entries = read_text(reader, format == VectorFormat::Word2vec)?;
+                Bytes::Owned(encode(entries, precision))
+            }
+        };
+        let vectors = Vectors::new(bytes, cache)?;
+        debug!(
+            path = %path.display(),
+            words = vectors.count,
+            dim = vectors.dim,
+            "read word vectors"
+        );
+        Ok(vectors)
+    }
+
+    /// validates the store in bytes, the sizes in its header
+    /// and the offset, length and order of every word
+    fn new(bytes: Bytes, cache: usize) -> io::Result<Self> {
+        let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message);
+        if bytes.len() < HEADER || &bytes[..8] != MAGIC {
+            return Err(invalid("not a vector store"));
+        }
+        let count = usize::try_from(u64_at(&bytes, 8)).map_err(|_| invalid("too many words"))?;
+        let dim = u32_at(&bytes, 16) as usize;
+        let precision = match u32_at(&bytes, 20) {
+            0 => Precision::F32,
+            1 => Precision::I8,
+            _ => return Err(invalid("unknown precision")),
+        };
+        let length = usize::try_from(u64_at(&bytes, 24)).ok();
+        let row = match precision {
+            Precision::F32 => dim.checked_mul(4),
+            Precision::I8 => dim.checked_add(4),
+        };
+        let words = count.checked_mul(ENTRY).and_then(|n| n.checked_add(HEADER));
+        let rows = words
+            .zip(length)
+            .and_then(|(words, length)| words.checked_add(length))
+            .and_then(|end| end.checked_next_multiple_of(4));
+        let size = rows
+            .zip(row)
+            .and_then(|(rows, row)| count.checked_mul(row)?.checked_add(rows));
+        let (words, rows, length) = match (words, rows, length, size) {
+            (Some(words), Some(rows), Some(length), Some(size)) if size == bytes.len() => {
+                (words, rows, length)
+            }
+            _ => return Err(invalid("truncated vector store")),
+        };
+        let mut previous: Option<&[u8]> = None;
+        for i in 0..count {
+            let entry = HEADER + i * ENTRY;
+            let offset = usize::try_from(u64_at(&bytes, entry)).ok();
+            let len = u32_at(&bytes, entry + 8) as usize;
+            let word = match offset.and_then(|o| o.checked_add(len)) {
+                Some(end) if end <= length => &bytes[words + end - len..words + end],
+                _ => return Err(invalid("word outside the vector store")),
+            };
+            if previous.is_some_and(|previous| previous >= word) {
+                return Err(invalid("words of the vector store out of order"));
+            }
+            previous = Some(word);
+        }
+        Ok(Vectors {
+            bytes,
+            count,
+            dim,
+            precision,
+            words,
+            rows,
+            cache: Mutex::new(Lru::new(cache)),
+        })
+    }
+
+    /// a copy of the store with its components in precision
+    pub fn convert(&self, precision: Precision, cache: usize) -> io::Result<Self> {
+        let entries = (0..self.count)
+            .map(|row| (self.word_at(row), self.decode(row)))
+            .collect();
+        Vectors::new(Bytes::Owned(encode(entries, precision)), cache)
+    }
+
+    /// the words in byte order
+    pub fn words(&self) -> impl Iterator<Item = String> + '_ {
+        (0..self.count).map(move |row| self.word_at(row))
+    }
+
+    fn word_at(&self, row: usize) -> String {
+        String::from_utf8_lossy(self.word(row)).into_owned()
+    }
+
+    /// writes the store, to be opened as `--vector-format store`
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, &*self.bytes)
+    }
+
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn dim(&self) -> usize {
+        self.dim
+    }
+
+    /// the vector of word, or of its lowercase form if word has none
+    pub fn get(&self, word: &str) -> Option<Arc<[f32]>> {
+        if let Some(vector) = self.lock().get(word) {
+            return Some(vector);
+        }
+        let row = self
+            .find(word)
+            .or_else(|| self.find(&word.to_lowercase()))?;
+        let vector: Arc<[f32]> = self.decode(row).into();
+        self.lock().insert(word, vector.clone());
+        Some(vector)
+    }
+
+    /// the mean of the vectors of words, None if none of them has one
+    pub fn mean<'a, I: IntoIterator<Item = &'a str>>(&self, words: I) -> Option<Vec<f32>> {
+        let mut sum = vec![0f32; self.dim];
+        let mut n = 0;
+        for vector in words.into_iter().filter_map(|word| self.get(word)) {
+            for (s, c) in sum.iter_mut().zip(vector.iter()) {
+                *s += c;
+            }
+            n += 1;
+        }
+        if n == 0 {
+            return None;
+        }
+        for s in &mut sum {
+            *s /= n as f32;
+        }
+        Some(sum)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Lru> {
+        self.cache.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn word(&self, i: usize) -> &[u8] {
+        let entry = HEADER + i * ENTRY;
+        let offset = self.words + u64_at(&self.bytes, entry) as usize;
+        let len = u32_at(&self.bytes, entry + 8) as usize;
+        &self.bytes[offset..offset + len]
+    }
+
+    /// the row of word by binary search over the sorted words
+    fn find(&self, word: &str) -> Option<usize> {
+        let (mut low, mut high) = (0, self.count);
+        while low < high {
+            let mid = (low + high) / 2;
+            match self.word(mid).cmp(word.as_bytes()) {
+                std::cmp::Ordering::Less => low = mid + 1,
+                std::cmp::Ordering::Greater => high = mid,
+                std::cmp::Ordering::Equal => return Some(mid),
+            }
+        }
+        None
+    }
+
+    fn decode(&self, row: usize) -> Vec<f32> {
+        match self.precision {
+            Precision::F32 => {
+                let start = self.rows + row * 4 * self.dim;
+                (0..self.dim)
+                    .map(|i| f32_at(&self.bytes, start + 4 * i))
+                    .collect()
+            }
+            Precision::I8 => {
+                let start = self.rows + row * (4 + self.dim);
+                let scale = f32_at(&self.bytes, start);
+                self.bytes[start + 4..start + 4 + self.dim]
+                    .iter()
+                    .map(|b| f32::from(*b as i8) * scale)
+                    .collect()
+            }
+        }
+    }
+}
+
+/// the cosine similarity of a and b, 0 if either is zero
+pub fn cosine(a: &[f32], b: &[f32]) -> f64 {
+    let dot = a.iter().zip(b).map(|(x, y)| f64::from(x * y)).sum::<f64>();
+    let norm = |v: &[f32]| v.iter().map(|x| f64::from(x * x)).sum::<f64>().sqrt();
+    let norms = norm(a) * norm(b);
+    if norms > 0f64 {
+        dot / norms
+    } else {
+        0f64
+    }
+}
+
+/// the recently used vectors, the least recently used one evicted when full
+struct Lru {
+    capacity: usize,
+    tick: u64,
+    entries: HashMap<String, (Arc<[f32]>, u64)>,
+    /// the words by the tick of their last use
+    order: BTreeMap<u64, String>,
+}
+
+impl Lru {
+    fn new(capacity: usize) -> Self {
+        Lru {
+            capacity,
+            tick: 0,
+            entries: HashMap::new(),
+            order: BTreeMap::new(),
+        }
+    }
+
+    fn get(&mut self, word: &str) -> Option<Arc<[f32]>> {
+        self.tick += 1;
+        let (vector, used) = self.entries.get_mut(word)?;
+        self.order.remove(used);
+        *used = self.tick;
+        self.order.insert(self.tick, word.into());
+        Some(vector.clone())
+    }
+
+    fn insert(&mut self, word: &str, vector: Arc<[f32]>) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.entries.len() >= self.capacity {
+            if let Some((_, oldest)) = self.order.pop_first() {
+                self.entries.remove(&oldest);
+            }
+        }
+        self.tick += 1;
+        self.order.insert(self.tick, word.into());
+        if let Some((_, used)) = self.entries.insert(word.into(), (vector, self.tick)) {
+            self.order.remove(&used);
+        }
+    }
+}
+
+fn detect(path: &Path) -> io::Result<VectorFormat> {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("bin") => return Ok(VectorFormat::Word2vecBinary),
+        Some("hvec") => return Ok(VectorFormat::Store),
+        _ => {}
+    }
+    let mut first = String::new();
+    BufReader::new(File::open(path)?).read_line(&mut first)?;
+    let fields = first.split_whitespace().collect::<Vec<_>>();
+    if fields.len() == 2 && fields.iter().all(|f| f.parse::<usize>().is_ok()) {
+        Ok(VectorFormat::Word2vec)
+    } else {
+        Ok(VectorFormat::Glove)
+    }
+}
+
+fn invalid_line(n: usize, message: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("line {}: {}", n + 1, message),
+    )
+}
+
+/// `<count> <dim>`
+fn header(line: &str) -> Option<(usize, usize)> {
+    let (count, dim) = line.trim().split_once(' ')?;
+    Some((count.parse().ok()?, dim.parse().ok()?))
+}
+
+/// the words and vectors of GloVe lines, after a header line with word2vec.
+/// a word may contain spaces, the last dim fields are its components
+fn read_text<R: BufRead>(reader: R, word2vec: bool) -> io::Result<Vec<(String, Vec<f32>)>> {
+    let mut lines = reader.lines().enumerate();
+    let mut dim = None;
+    if word2vec {
+        if let Some((n, line)) = lines.next() {
+            let (_, d) = header(&line?).ok_or_else(|| invalid_line(n, "expected <count> <dim>"))?;
+            dim = Some(d);
+        }
+    }
+    let mut entries = vec![];
+    for (n, line) in lines {
+        let line = line?;
+        let fields = line.split_whitespace().collect::<Vec<_>>();
+        if fields.is_empty() {
+            continue;
+        }
+        let dim = *dim.get_or_insert(fields.len() - 1);
+        if dim == 0 {
+            return Err(invalid_line(n, "expected a word and its components"));
+        }
+        if fields.len() <= dim {
+            return Err(invalid_line(
+                n,
+                &format!("expected a word and {} components", dim),
+            ));
+        }
+        let (word, components) = fields.split_at(fields.len() - dim);
+        let vector = components
+            .iter()
+            .map(|c| c.parse::<f32>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| invalid_line(n, &e.to_string()))?;
+        entries.push((word.join(" "), vector));
+    }
+    Ok(entries)
+}
+
+fn read_binary<R: BufRead>(mut reader: R) -> io::Result<Vec<(String, Vec<f32>)>> {
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let (count, dim) = header(&line).ok_or_else(|| invalid_line(0, "expected <count> <dim>"))?;
+    let mut entries = Vec::with_capacity(count.min(PREALLOCATED));
+    let width = dim
+        .checked_mul(4)
+        .ok_or_else(|| invalid_line(0, "too many components"))?;
+    let mut components = vec![0u8; width];
+    for _ in 0..count {
+        let mut word = vec![];
+        reader.read_until(b' ', &mut word)?;
+        if word.pop() != Some(b' ') {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "truncated word2vec file",
+            ));
+        }
+        reader.read_exact(&mut components)?;
+        let vector = components
+            .chunks_exact(4)
+            .map(|c| f32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+            .collect();
+        // the newline some writers put after every vector
+        let word = String::from_utf8_lossy(&word).trim_start().to_string();
+        entries.push((word, vector));
+    }
+    Ok(entries)
+}
+
+/// the store of entries, the first vector of a word kept
+fn encode(mut entries: Vec<(String, Vec<f32>)>, precision: Precision) -> Vec<u8> {
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+    entries.dedup_by(|a, b| a.0 == b.0);
+    let dim = entries.first().map_or(0, |(_, v)| v.len());
+    let words = entries.iter().map(|(w, _)| w.len()).sum::<usize>();
+    let mut bytes = Vec::with_capacity(HEADER + entries.len() * (ENTRY + 4 * dim) + words);
+    bytes.extend_from_slice(MAGIC);
+    bytes.extend_from_slice(&(entries.len() as u64).to_le_bytes());
+    bytes.extend_from_slice(&(dim as u32).to_le_bytes());
+    bytes.extend_from_slice(&(precision as u32).to_le_bytes());
+    bytes.extend_from_slice(&(words as u64).to_le_bytes());
+    let mut offset = 0u64;
+    for (word, _) in &entries {
+        bytes.extend_from_slice(&offset.to_le_bytes());
+        bytes.extend_from_slice(&(word.len() as u32).to_le_bytes());
+        offset += word.len() as u64;
+    }
+    for (word, _) in &entries {
+        bytes.extend_from_slice(word.as_bytes());
+    }
+    bytes.resize(align(bytes.len()), 0);
+    for (_, vector) in &entries {
+        match precision {
+            Precision::F32 => {
+                for c in vector {
+                    bytes.extend_from_slice(&c.to_le_bytes());
+                }
+            }
+            Precision::I8 => {
+                let max = vector.iter().fold(0f32, |max, c| max.max(c.abs()));
+                let scale = if max > 0f32 { max / 127f32 } else { 1f32 };
+                bytes.extend_from_slice(&scale.to_le_bytes());
+                bytes.extend(vector.iter().map(|c| (c / scale).round() as i8 as u8));
+            }
+        }
+    }
+    bytes
+}
+
+/// rounded up to a multiple of 4
+fn align(n: usize) -> usize {
+    n.div_ceil(4) * 4
+}
+
+// the readers below index within the bounds `Vectors::new` validated
+
+fn u64_at(bytes: &[u8], at: usize) -> u64 {
+    let mut le = [0; 8];
+    le.copy_from_slice(&bytes[at..at + 8]);
+    u64::from_le_bytes(le)
+}
+
+fn u32_at(bytes: &[u8], at: usize) -> u32 {
+    let mut le = [0; 4];
+    le.copy_from_slice(&bytes[at..at + 4]);
+    u32::from_le_bytes(le)
+}
+
+fn f32_at(bytes: &[u8], at: usize) -> f32 {
+    f32::from_bits(u32_at(bytes, at))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GLOVE: &str = "tree 1 0 0\ngraph 0 2 0\nnode 0 0 -3\n";
+
+    fn store(precision: Precision) -> Vec<u8> {
+        encode(read_text(GLOVE.as_bytes(), false).unwrap(), precision)
+    }
+
+    fn open(bytes: Vec<u8>) -> io::Result<Vectors> {
+        Vectors::new(Bytes::Owned(bytes), 4)
+    }
+
+    #[test]
+    fn text_round_trips_through_a_store() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vectors.hvec");
+        open(store(Precision::F32)).unwrap().write(&path).unwrap();
+        let vectors = Vectors::open(&path, VectorFormat::Auto, Precision::F32, 4).unwrap();
+        assert_eq!((vectors.len(), vectors.dim()), (3, 3));
+        assert_eq!(
+            vectors.words().collect::<Vec<_>>(),
+            ["graph", "node", "tree"]
+        );
+        assert_eq!(&*vectors.get("graph").unwrap(), &[0.0, 2.0, 0.0]);
+        assert_eq!(&*vectors.get("Node").unwrap(), &[0.0, 0.0, -3.0]);
+        assert!(vectors.get("edge").is_none());
+    }
+
+    #[test]
+    fn binary_word2vec_reads_like_text() {
+        let mut binary = b"2 2\n".to_vec();
+        for (word, vector) in [("tree", [1f32, 0.5]), ("graph", [-2.0, 0.0])] {
+            binary.extend_from_slice(word.as_bytes());
+            binary.push(b' ');
+            for c in vector {
+                binary.extend_from_slice(&c.to_le_bytes());
+            }
+            binary.push(b'\n');
+        }
+        let vectors = open(encode(read_binary(&binary[..]).unwrap(), Precision::F32)).unwrap();
+        assert_eq!(&*vectors.get("tree").unwrap(), &[1.0, 0.5]);
+        assert_eq!(&*vectors.get("graph").unwrap(), &[-2.0, 0.0]);
+    }
+
+    #[test]
+    fn quantized_stores_keep_the_largest_component() {
+        let vectors = open(store(Precision::I8)).unwrap();
+        assert_eq!(&*vectors.get("node").unwrap(), &[0.0, 0.0, -3.0]);
+    }
+
+    #[test]
+    fn truncated_stores_are_rejected() {
+        let bytes = store(Precision::F32);
+        for len in [0, HEADER - 1, HEADER + ENTRY, bytes.len() - 1] {
+            let err = open(bytes[..len].to_vec()).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData, "{} bytes", len);
+        }
+    }
+
+    #[test]
+    fn corrupt_stores_are_rejected() {
+        let bytes = store(Precision::F32);
+        let corrupt = |at: usize, value: &[u8]| {
+            let mut bytes = bytes.clone();
+            bytes[at..at + value.len()].copy_from_slice(value);
+            let err = open(bytes).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+            err.to_string()
+        };
+        // a count overflowing the size of the header and entries
+        assert_eq!(
+            corrupt(8, &u64::MAX.to_le_bytes()),
+            "truncated vector store"
+        );
+        let word = "word outside the vector store";
+        assert_eq!(corrupt(HEADER + 8, &u32::MAX.to_le_bytes()), word);
+        assert_eq!(corrupt(HEADER, &u64::MAX.to_le_bytes()), word);
+        // the first entry pointing at the last word
+        let mut entry = 9u64.to_le_bytes().to_vec();
+        entry.extend_from_slice(&4u32.to_le_bytes());
+        assert_eq!(
+            corrupt(HEADER, &entry),
+            "words of the vector store out of order"
+        );
+    }
+
+    #[test]
+    fn word2vec_counts_are_not_preallocated() {
+        let err = read_binary(&b"18446744073709551615 2\ntree "[..]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+}
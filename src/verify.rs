@@ -0,0 +1,84 @@
+//! checks the token offsets of the documents against the original abstracts
+use crate::HulthDocument;
+
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+/// the tokens of one document whose offsets don't point at their word
+#[derive(Debug)]
+pub struct Mismatches {
+    pub(crate) name: String,
+    pub(crate) tokens: usize,
+    /// (offset_begin, offset_end, word, text found at the offsets)
+    pub(crate) mismatched: Vec<(i64, i64, String, String)>,
+}
+
+/// compares every token of doc with the characters of text at its offsets.
+/// Penn Treebank escapes such as `-LRB-` are compared as the bracket they stand for
+pub fn check(name: &str, doc: &HulthDocument, text: &str) -> Mismatches {
+    let chars = text.chars().collect::<Vec<_>>();
+    let mut tokens = 0;
+    let mut mismatched = vec![];
+    for token in doc.sentences.iter().flat_map(|s| s.tokens.iter()) {
+        tokens += 1;
+        let (begin, end) = (token.offset_begin, token.offset_end);
+        let found = if 0 <= begin && begin <= end {
+            chars.get(begin as usize..end as usize)
+        } else {
+            None
+        };
+        let found = found
+            .map(|c| c.iter().collect::<String>())
+            .unwrap_or_default();
+        if found != *token.word && found != unescape(&token.word) {
+            mismatched.push((begin, end, token.word.to_string(), found));
+        }
+    }
+    Mismatches {
+        name: name.into(),
+        tokens,
+        mismatched,
+    }
+}
+
+/// reads `<name>.abstr` from dir, None if there is none
+pub fn read_abstract(dir: &Path, name: &str) -> io::Result<Option<String>> {
+    match fs::read_to_string(dir.join(format!("{}.abstr", name))) {
+        Ok(text) => Ok(Some(text)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// one line per document with mismatches, showing the first of them
+pub fn print<W: Write>(mut w: W, results: &[Mismatches]) -> io::Result<()> {
+    for result in results.iter().filter(|r| !r.mismatched.is_empty()) {
+        let (begin, end, word, found) = &result.mismatched[0];
+        writeln!(
+            w,
+            "{}: {} of {} tokens, first {:?} at {}..{} reads {:?}",
+            result.name,
+            result.mismatched.len(),
+            result.tokens,
+            word,
+            begin,
+            end,
+            found
+        )?;
+    }
+    Ok(())
+}
+
+fn unescape(word: &str) -> &str {
+    match word {
+        "-LRB-" => "(",
+        "-RRB-" => ")",
+        "-LSB-" => "[",
+        "-RSB-" => "]",
+        "-LCB-" => "{",
+        "-RCB-" => "}",
+        "``" | "''" => "\"",
+        word => word,
+    }
+}
@@ -0,0 +1,70 @@
+//! browser bindings of the `wasm` feature, built with
+//! `wasm-pack build --target web -- --features wasm`
+use crate::batch::Keyword;
+use crate::idf_table::IdfTable;
+use crate::preprocess::Normalizer;
+use crate::{sort_ranking, text};
+
+use wasm_bindgen::prelude::*;
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io;
+
+thread_local! {
+    static TABLE: RefCell<Option<(IdfTable, Normalizer)>> = const { RefCell::new(None) };
+}
+
+impl IdfTable {
+    /// the terms of input, raw text or Hulth JSON, scored by count * idf, best first.
+    /// terms outside the table count as seen in a single document
+    fn rank(&self, normalizer: &Normalizer, input: &str) -> io::Result<Vec<(String, f64)>> {
+        let doc = normalizer.apply(&text::parse_document(input)?);
+        let mut counts = HashMap::new();
+        for token in doc.sentences.iter().flat_map(|s| s.tokens.iter()) {
+            *counts.entry(token.word.to_string()).or_insert(0usize) += 1;
+        }
+        let unseen = (self.documents.max(1) as f64).ln();
+        let scores = counts
+            .into_iter()
+            .map(|(term, count)| {
+                let idf = self.idf.get(&term).copied().unwrap_or(unseen);
+                (term, count as f64 * idf)
+            })
+            .collect();
+        let scores = self.preprocessing.resolve(&doc, scores);
+        Ok(sort_ranking(scores))
+    }
+}
+
+/// loads the table written by `hulth-eval export-idf`, required before rank_text
+#[wasm_bindgen]
+pub fn load_idf_table(json: &str) -> Result<(), JsValue> {
+    let table: IdfTable =
+        serde_json::from_str(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let normalizer = table
+        .preprocessing
+        .normalizer()
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    TABLE.with(|t| *t.borrow_mut() = Some((table, normalizer)));
+    Ok(())
+}
+
+/// the keywords of text, raw or Hulth JSON, as `[{term, score}]` best first
+#[wasm_bindgen]
+pub fn rank_text(text: &str) -> Result<JsValue, JsValue> {
+    let ranked = TABLE.with(|t| match &*t.borrow() {
+        Some((table, normalizer)) => table.rank(normalizer, text).map_err(|e| e.to_string()),
+        None => Err("no idf table loaded, call load_idf_table first".to_string()),
+    });
+    let keywords = ranked
+        .map_err(|e| JsValue::from_str(&e))?
+        .into_iter()
+        .map(|(term, score)| Keyword {
+            term,
+            score,
+            surface: None,
+        })
+        .collect::<Vec<_>>();
+    serde_wasm_bindgen::to_value(&keywords).map_err(JsValue::from)
+}
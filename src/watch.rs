@@ -0,0 +1,123 @@
+//! `eval --watch`, evaluating again whenever the dataset or the references change.
+//! only the documents whose files changed are ranked again, the measures of the
+//! others are kept. the corpus is still refitted, so those measures can drift
+//! from a full run by as much as the changed documents move the idf
+use crate::commands::eval::{self, EvalArgs};
+use crate::corpus::{self, Reading};
+use crate::fnv::Fnv;
+use crate::report::Summary;
+
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io::{self, IsTerminal};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// the hash and document id of a dataset file
+type Files = BTreeMap<PathBuf, (u64, String)>;
+
+/// evaluates once, then again on every change until interrupted. failed runs,
+/// e.g. on a file saved half way, are logged and the next change is awaited
+pub fn run(args: &EvalArgs, reading: &Reading) -> io::Result<()> {
+    let extractor = eval::first_extractor(args);
+    let color = !args.no_color && io::stdout().is_terminal();
+    let mut files = scan(&args.dataset, reading, &Files::new())?;
+    let mut references = hash(&args.references)?;
+    let mut measures = BTreeMap::new();
+    let mut changed = None;
+    loop {
+        let mut variant = args.clone();
+        variant.changed = changed.clone();
+        let started = Instant::now();
+        let run = eval::run_dataset(
+            &variant,
+            &args.dataset,
+            &args.references,
+            reading,
+            extractor,
+            None,
+        );
+        match run {
+            Ok(ranked) => {
+                let ranked_count = ranked.len();
+                for m in ranked {
+                    measures.insert(m.document.clone(), m);
+                }
+                info!(
+                    ranked = ranked_count,
+                    documents = measures.len(),
+                    elapsed = ?started.elapsed(),
+                    "evaluated changes"
+                );
+                Summary::from_measures(measures.values()).print(
+                    io::stdout().lock(),
+                    None,
+                    color,
+                )?;
+                println!();
+            }
+            Err(e) => error!(error = %e, "evaluation failed, waiting for changes"),
+        }
+        changed = loop {
+            thread::sleep(Duration::from_millis(args.watch_interval));
+            let scanned = scan(&args.dataset, reading, &files)
+                .and_then(|now| Ok((now, hash(&args.references)?)));
+            let (now, now_references) = match scanned {
+                Ok(scanned) => scanned,
+                Err(e) => {
+                    warn!(error = %e, "can't read the dataset, checking again");
+                    continue;
+                }
+            };
+            if now_references != references {
+                info!("references changed, evaluating all documents");
+                references = now_references;
+                files = now;
+                measures.clear();
+                break None;
+            }
+            let mut documents = HashSet::new();
+            for (path, (hash, id)) in &now {
+                if files.get(path).map(|(h, _)| h) != Some(hash) {
+                    measures.remove(id);
+                    documents.insert(id.clone());
+                }
+            }
+            let mut removed = 0;
+            for (path, (_, id)) in &files {
+                if !now.contains_key(path) {
+                    measures.remove(id);
+                    removed += 1;
+                }
+            }
+            files = now;
+            if !documents.is_empty() || removed > 0 {
+                info!(changed = documents.len(), removed, "dataset changed");
+                break Some(documents);
+            }
+        };
+    }
+}
+
+/// the files of dir with their hashes, reading the ids of those
+/// whose hash is not the one in known
+fn scan(dir: &Path, reading: &Reading, known: &Files) -> io::Result<Files> {
+    let mut files = Files::new();
+    corpus::for_each_file(dir, reading, |path| {
+        let hash = hash(path)?;
+        let id = match known.get(path) {
+            Some((h, id)) if *h == hash => id.clone(),
+            _ => reading.document_id(path)?,
+        };
+        files.insert(path.to_path_buf(), (hash, id));
+        Ok(())
+    })?;
+    Ok(files)
+}
+
+fn hash(path: &Path) -> io::Result<u64> {
+    let mut hash = Fnv::new();
+    hash.write(&fs::read(path)?);
+    Ok(hash.finish())
+}
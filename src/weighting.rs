@@ -0,0 +1,323 @@
+//! tf-idf weighting computed by the harness from term and document
+//! frequencies, for schemes lib_tfidf does not offer
+use crate::{HulthDocument, HulthToken};
+
+use clap::{Args, ValueEnum};
+use lib_tfidf::Document;
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+/// number of corpus documents containing each term
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DocumentFrequencies {
+    documents: usize,
+    counts: HashMap<Arc<str>, usize>,
+}
+
+impl DocumentFrequencies {
+    pub fn new(docs: &[Box<dyn Document<String, HulthToken>>]) -> Self {
+        let mut frequencies = DocumentFrequencies::default();
+        for doc in docs {
+            frequencies.add(doc.as_ref());
+        }
+        frequencies
+    }
+
+    /// counts doc as a document of the corpus
+    pub fn add(&mut self, doc: &dyn Document<String, HulthToken>) {
+        self.documents += 1;
+        for term in distinct_terms(doc) {
+            *self.counts.entry(term).or_insert(0) += 1;
+        }
+    }
+
+    /// takes a document added before out of the counts,
+    /// terms no document contains any more are dropped
+    pub fn subtract(&mut self, doc: &dyn Document<String, HulthToken>) {
+        self.documents = self.documents.saturating_sub(1);
+        for term in distinct_terms(doc) {
+            if let Some(count) = self.counts.get_mut(&term) {
+                *count -= 1;
+                if *count == 0 {
+                    self.counts.remove(&term);
+                }
+            }
+        }
+    }
+
+    pub fn documents(&self) -> usize {
+        self.documents
+    }
+
+    /// the distinct terms of the corpus
+    pub fn terms(&self) -> impl Iterator<Item = &str> {
+        self.counts.keys().map(|term| &**term)
+    }
+
+    /// `ln(N / df)`, terms outside the corpus count as seen once
+    pub fn idf(&self, term: &str) -> f64 {
+        self.idf_with(term, Idf::Standard)
+    }
+
+    pub fn idf_with(&self, term: &str, scheme: Idf) -> f64 {
+        let n = self.documents.max(1) as f64;
+        let df = self.counts.get(term).copied().unwrap_or(0).max(1) as f64;
+        match scheme {
+            Idf::Standard => (n / df).ln(),
+            Idf::Smooth => ((1.0 + n) / (1.0 + df)).ln() + 1.0,
+            Idf::Probabilistic => ((n - df) / df).ln().max(0.0),
+        }
+    }
+}
+
+fn distinct_terms(doc: &dyn Document<String, HulthToken>) -> HashSet<Arc<str>> {
+    doc.get_content()
+        .into_iter()
+        .map(|t| t.word.clone())
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Tf {
+    /// the number of occurrences in the document
+    Raw,
+    /// 1 + ln(occurrences)
+    Log,
+    /// 1 for every occurring term
+    Boolean,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Idf {
+    /// ln(N / df)
+    Standard,
+    /// ln((1 + N) / (1 + df)) + 1
+    Smooth,
+    /// max(0, ln((N - df) / df))
+    Probabilistic,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Norm {
+    /// scores divided by the sum of their absolute values
+    L1,
+    /// scores divided by their euclidean length
+    L2,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Args, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Weighting {
+    /// term frequency weighting computed by the harness.
+    /// without --tf, --idf and the idf bounds the scores of lib_tfidf are used
+    #[arg(long, value_enum)]
+    pub tf: Option<Tf>,
+
+    /// inverse document frequency computed by the harness, see --tf
+    #[arg(long, value_enum)]
+    pub idf: Option<Idf>,
+
+    /// raises lower idf values to this one, computing the idf in the harness
+    #[arg(long)]
+    pub idf_min: Option<f64>,
+
+    /// lowers higher idf values to this one, curbing terms seen in a single document
+    #[arg(long)]
+    pub idf_max: Option<f64>,
+
+    /// normalizes the scores of every document
+    #[arg(long, value_enum)]
+    pub norm: Option<Norm>,
+}
+
+impl Weighting {
+    /// whether the scores are computed by the harness instead of lib_tfidf
+    pub fn is_custom(&self) -> bool {
+        self.tf.is_some() || self.idf.is_some() || self.is_clamped()
+    }
+
+    pub fn is_clamped(&self) -> bool {
+        self.idf_min.is_some() || self.idf_max.is_some()
+    }
+
+    /// the scores of lib_tfidf for doc, recomputed from the frequencies if
+    /// the weighting is custom and normalized. frequencies are required
+    /// for custom weightings
+    pub fn apply(
+        &self,
+        doc: &HulthDocument,
+        scores: HashMap<String, f64>,
+        frequencies: Option<&DocumentFrequencies>,
+    ) -> HashMap<String, f64> {
+        let mut scores = match frequencies.filter(|_| self.is_custom()) {
+            Some(frequencies) => self.weigh(doc, scores, frequencies),
+            None => scores,
+        };
+        let length = match self.norm {
+            Some(Norm::L1) => scores.values().map(|s| s.abs()).sum::<f64>(),
+            Some(Norm::L2) => scores.values().map(|s| s * s).sum::<f64>().sqrt(),
+            None => return scores,
+        };
+        if length > 0.0 {
+            for score in scores.values_mut() {
+                *score /= length;
+            }
+        }
+        scores
+    }
+
+    /// tf * idf of every term lib_tfidf scored, tf counted in doc
+    fn weigh(
+        &self,
+        doc: &HulthDocument,
+        scores: HashMap<String, f64>,
+        frequencies: &DocumentFrequencies,
+    ) -> HashMap<String, f64> {
+        let mut counts = HashMap::new();
+        for token in doc.sentences.iter().flat_map(|s| s.tokens.iter()) {
+            *counts.entry(&*token.word).or_insert(0usize) += 1;
+        }
+        scores
+            .into_keys()
+            .map(|term| {
+                let count = counts.get(term.as_str()).copied().unwrap_or(0) as f64;
+                let tf = match self.tf.unwrap_or(Tf::Raw) {
+                    Tf::Raw => count,
+                    Tf::Log if count > 0.0 => 1.0 + count.ln(),
+                    Tf::Log => 0.0,
+                    Tf::Boolean => (count > 0.0) as u8 as f64,
+                };
+                let idf = frequencies.idf_with(&term, self.idf.unwrap_or(Idf::Standard));
+                let idf = idf.max(self.idf_min.unwrap_or(f64::NEG_INFINITY));
+                let idf = idf.min(self.idf_max.unwrap_or(f64::INFINITY));
+                (term, tf * idf)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Sentence;
+
+    /// a document of one sentence of words
+    fn document(words: &[&str]) -> HulthDocument {
+        let tokens = words
+            .iter()
+            .map(|word| HulthToken {
+                word: (*word).into(),
+                lemma: (*word).into(),
+                offset_begin: 0,
+                offset_end: 0,
+                pos: "".into(),
+            })
+            .collect();
+        HulthDocument {
+            sentences: vec![Sentence { tokens }],
+        }
+    }
+
+    /// N = 4, df(a) = 3, df(b) = 1, df(c) = 2, df(d) = 1
+    fn frequencies() -> DocumentFrequencies {
+        let docs: Vec<Box<dyn Document<String, HulthToken>>> = vec![
+            Box::new(document(&["a", "a", "a", "b"])),
+            Box::new(document(&["a", "c"])),
+            Box::new(document(&["c"])),
+            Box::new(document(&["a", "d"])),
+        ];
+        DocumentFrequencies::new(&docs)
+    }
+
+    /// the scores of a, b and x, a term of the corpus missing in the document
+    fn scores() -> HashMap<String, f64> {
+        HashMap::from([("a".into(), 1.0), ("b".into(), 1.0), ("x".into(), 1.0)])
+    }
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn idf_variants() {
+        let frequencies = frequencies();
+        assert_eq!(frequencies.documents(), 4);
+        let idf = |term, scheme| frequencies.idf_with(term, scheme);
+        assert!(close(idf("a", Idf::Standard), (4f64 / 3.0).ln()));
+        assert!(close(idf("b", Idf::Standard), 4f64.ln()));
+        assert!(close(frequencies.idf("c"), 2f64.ln()));
+        // unseen terms count as seen once
+        assert!(close(idf("unseen", Idf::Standard), 4f64.ln()));
+        assert!(close(idf("a", Idf::Smooth), (5f64 / 4.0).ln() + 1.0));
+        assert!(close(idf("b", Idf::Smooth), (5f64 / 2.0).ln() + 1.0));
+        assert!(close(idf("b", Idf::Probabilistic), 3f64.ln()));
+        assert!(close(idf("c", Idf::Probabilistic), 0.0));
+        // ln(1 / 3) is clamped to 0
+        assert_eq!(idf("a", Idf::Probabilistic), 0.0);
+    }
+
+    #[test]
+    fn tf_variants() {
+        let frequencies = frequencies();
+        let doc = document(&["a", "a", "a", "b"]);
+        let weigh = |tf| {
+            let weighting = Weighting {
+                tf: Some(tf),
+                ..Weighting::default()
+            };
+            weighting.apply(&doc, scores(), Some(&frequencies))
+        };
+        let (a, b) = ((4f64 / 3.0).ln(), 4f64.ln());
+        let raw = weigh(Tf::Raw);
+        assert!(close(raw["a"], 3.0 * a));
+        assert!(close(raw["b"], b));
+        let log = weigh(Tf::Log);
+        assert!(close(log["a"], (1.0 + 3f64.ln()) * a));
+        assert!(close(log["b"], b));
+        let boolean = weigh(Tf::Boolean);
+        assert!(close(boolean["a"], a));
+        assert!(close(boolean["b"], b));
+        for scores in [raw, log, boolean] {
+            assert_eq!(scores["x"], 0.0);
+        }
+    }
+
+    #[test]
+    fn idf_bounds_clamp_the_idf() {
+        let frequencies = frequencies();
+        let doc = document(&["a", "a", "a", "b"]);
+        let weighting = Weighting {
+            idf_min: Some(0.5),
+            idf_max: Some(1.0),
+            ..Weighting::default()
+        };
+        assert!(weighting.is_custom());
+        let scores = weighting.apply(&doc, scores(), Some(&frequencies));
+        assert!(close(scores["a"], 3.0 * 0.5));
+        assert!(close(scores["b"], 1.0));
+    }
+
+    #[test]
+    fn norms_scale_the_scores() {
+        let doc = document(&["a", "b"]);
+        let lib = HashMap::from([("a".into(), 3.0), ("b".into(), -4.0)]);
+        let normalized = |norm| {
+            let weighting = Weighting {
+                norm: Some(norm),
+                ..Weighting::default()
+            };
+            weighting.apply(&doc, lib.clone(), None)
+        };
+        let l1 = normalized(Norm::L1);
+        assert!(close(l1["a"], 3.0 / 7.0) && close(l1["b"], -4.0 / 7.0));
+        let l2 = normalized(Norm::L2);
+        assert!(close(l2["a"], 0.6) && close(l2["b"], -0.8));
+        // without --tf, --idf or bounds the scores of lib_tfidf are kept
+        let kept = Weighting::default().apply(&doc, lib.clone(), Some(&frequencies()));
+        assert_eq!(kept, lib);
+    }
+}
@@ -0,0 +1,119 @@
+//! golden tests, `eval` of the five documents of tests/fixtures/mini against the
+//! numbers in tests/fixtures/mini/golden. a change of the numbers is either a bug
+//! or, if intended, written to the golden files with `UPDATE_GOLDEN=1 cargo test`
+use serde_json::Value;
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// floats written by a different build may differ in the last bits
+const TOLERANCE: f64 = 1e-9;
+
+fn fixture(path: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/mini")
+        .join(path)
+}
+
+/// runs eval on the fixture with the extra args, writing its results and
+/// summary into a directory of its own
+fn eval(name: &str, args: &[&str]) -> PathBuf {
+    let out = Path::new(env!("CARGO_TARGET_TMPDIR")).join(name);
+    let _ = fs::remove_dir_all(&out);
+    fs::create_dir_all(&out).unwrap();
+    let status = Command::new(env!("CARGO_BIN_EXE_hulth-eval"))
+        .arg("eval")
+        .arg("--dataset")
+        .arg(fixture("documents"))
+        .arg("--references")
+        .arg(fixture("references.json"))
+        .arg("--results")
+        .arg(out.join("results.jsonl"))
+        .arg("--summary-out")
+        .arg(out.join("summary.json"))
+        .args(args)
+        .status()
+        .unwrap();
+    assert!(status.success(), "eval failed with {}", status);
+    out
+}
+
+/// the results of a run by document, the order of the lines depends on the workers
+fn results(path: &Path) -> BTreeMap<String, Value> {
+    fs::read_to_string(path)
+        .unwrap()
+        .lines()
+        .map(|line| {
+            let result: Value = serde_json::from_str(line).unwrap();
+            (result["document"].as_str().unwrap().to_string(), result)
+        })
+        .collect()
+}
+
+/// compares actual with the golden file, or replaces it with UPDATE_GOLDEN set
+fn check(golden: &str, actual: Value) {
+    let path = fixture("golden").join(golden);
+    if env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, serde_json::to_string_pretty(&actual).unwrap() + "\n").unwrap();
+        return;
+    }
+    let expected: Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+    assert_same(&expected, &actual, golden);
+}
+
+/// asserts equal JSON, numbers equal up to TOLERANCE
+fn assert_same(expected: &Value, actual: &Value, at: &str) {
+    match (expected, actual) {
+        (Value::Number(e), Value::Number(a)) => {
+            let (e, a) = (e.as_f64().unwrap(), a.as_f64().unwrap());
+            assert!(
+                (e - a).abs() <= TOLERANCE,
+                "{}: expected {}, got {}",
+                at,
+                e,
+                a
+            );
+        }
+        (Value::Array(e), Value::Array(a)) => {
+            assert_eq!(e.len(), a.len(), "{}: lengths differ", at);
+            for (i, (e, a)) in e.iter().zip(a).enumerate() {
+                assert_same(e, a, &format!("{}[{}]", at, i));
+            }
+        }
+        (Value::Object(e), Value::Object(a)) => {
+            let keys = |o: &serde_json::Map<String, Value>| o.keys().cloned().collect::<Vec<_>>();
+            assert_eq!(keys(e), keys(a), "{}: keys differ", at);
+            for (key, e) in e {
+                assert_same(e, &a[key], &format!("{}.{}", at, key));
+            }
+        }
+        _ => assert_eq!(expected, actual, "{}", at),
+    }
+}
+
+#[test]
+fn per_document() {
+    let out = eval("golden-per-document", &[]);
+    let results = results(&out.join("results.jsonl"));
+    check("results.json", serde_json::to_value(results).unwrap());
+}
+
+#[test]
+fn aggregate() {
+    let out = eval("golden-aggregate", &[]);
+    let summary = serde_json::from_str(&fs::read_to_string(out.join("summary.json")).unwrap());
+    check("summary.json", summary.unwrap());
+}
+
+#[test]
+fn top_k() {
+    let out = eval("golden-top-k", &["--top-k", "3"]);
+    let results = results(&out.join("results.jsonl"));
+    check("results-top-3.json", serde_json::to_value(results).unwrap());
+    let summary = serde_json::from_str(&fs::read_to_string(out.join("summary.json")).unwrap());
+    check("summary-top-3.json", summary.unwrap());
+}